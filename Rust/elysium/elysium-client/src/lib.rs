@@ -1,6 +1,8 @@
-pub mod rpc;
-pub mod server;
-
-pub use rpc::*;
-pub use server::*;
+//! RPC gateway: a non-mining node that follows the network and serves JSON-RPC
+//!
+//! The RPC methods live in [`elysium_node::rpc`] and the WebSocket subscriptions in
+//! [`elysium_node::pubsub`], so a node started with `--rpc-addr` or `--ws-addr` serves the
+//! same API directly.
 
+pub use elysium_node::pubsub::*;
+pub use elysium_node::rpc::*;
@@ -1,25 +1,39 @@
 use clap::Parser;
-use elysium_client::server::RpcServer;
-use elysium_node::Node;
+use elysium_client::{RpcServer, WsServer};
+use elysium_core::GenesisSpec;
+use elysium_node::{Network, Node};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
-use tracing::{info, error};
+use tracing::info;
 
 #[derive(Parser)]
 #[command(name = "elysium-client")]
-#[command(about = "Elysium blockchain RPC client server")]
+#[command(about = "Elysium RPC gateway: follows a node's chain and serves JSON-RPC")]
 struct Args {
     /// RPC server address (e.g., 127.0.0.1:8545)
     #[arg(short, long, default_value = "127.0.0.1:8545")]
     rpc_addr: String,
     
+    /// WebSocket address for JSON-RPC and subscriptions (e.g., 127.0.0.1:8547); disabled if omitted
+    #[arg(long)]
+    ws_addr: Option<String>,
+    
     /// Node address to connect to (e.g., 127.0.0.1:8080)
     #[arg(short, long, default_value = "127.0.0.1:8080")]
     node_addr: String,
     
-    /// Mining difficulty
+    /// Peer-to-peer listen address of the gateway
+    #[arg(short, long, default_value = "127.0.0.1:8090")]
+    listen: String,
+    
+    /// Mining difficulty of the network (ignored when a genesis spec is given)
     #[arg(short, long, default_value_t = 1)]
     difficulty: u64,
+    
+    /// Genesis spec JSON file, shared with the nodes of the network
+    #[arg(long)]
+    genesis: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -28,19 +42,36 @@ async fn main() -> anyhow::Result<()> {
     
     let args = Args::parse();
     
-    info!("Starting Elysium RPC client server...");
+    info!("Starting Elysium RPC gateway...");
     
     // Create node
-    let node = Node::new(args.difficulty);
-    info!("Node created with difficulty: {}", args.difficulty);
+    let node = match &args.genesis {
+        Some(path) => {
+            let spec = GenesisSpec::from_file(path)?;
+            info!("Using genesis spec for chain {}", spec.chain_id);
+            Node::from_genesis(&spec)?
+        }
+        None => Node::new(args.difficulty),
+    };
+    info!("Node created with difficulty: {}", node.blockchain().await.current_difficulty());
+    
+    // Follow the node's chain; transactions sent over RPC are relayed to it
+    let network = Network::new(SocketAddr::from_str(&args.listen)?, node.clone());
+    network.add_peer(SocketAddr::from_str(&args.node_addr)?);
+    let _network_handle = network.listen().await?;
+    info!("Syncing from node {}", args.node_addr);
     
     // Create RPC server
     let rpc_addr = SocketAddr::from_str(&args.rpc_addr)?;
-    let rpc_server = RpcServer::new(node, rpc_addr)?;
-    let _handle = rpc_server.start_async();
+    let _rpc_server = RpcServer::start(node.clone(), rpc_addr)?;
     
     info!("RPC server started successfully on {}", rpc_addr);
     
+    let _ws_server = match &args.ws_addr {
+        Some(addr) => Some(WsServer::start(node, SocketAddr::from_str(addr)?)?),
+        None => None,
+    };
+    
     // Keep the server running
     tokio::signal::ctrl_c().await?;
     info!("Shutting down...");
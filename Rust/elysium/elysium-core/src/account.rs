@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
+use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer};
 use rand::rngs::OsRng;
+use std::collections::BTreeMap;
 use std::fmt;
+use crate::{ElysiumError, MultisigPolicy, Transaction};
 
 /// Account address (32 bytes, hex-encoded)
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -12,7 +14,7 @@ impl Address {
     /// Create an address from a verifying key
     pub fn from_verifying_key(key: &VerifyingKey) -> Self {
         let bytes = key.to_bytes();
-        let hash = Sha256::digest(&bytes);
+        let hash = Sha256::digest(bytes);
         Address(hex::encode(hash))
     }
     
@@ -43,12 +45,33 @@ impl fmt::Display for Address {
     }
 }
 
+/// Part of a balance received through a time-locked transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeLock {
+    pub amount: u64,
+    /// Height of the first block that can spend the amount
+    pub unlock_height: u64,
+}
+
 /// Account with balance
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub address: Address,
+    /// Total balance, including amounts that are still time-locked
     pub balance: u64,
     pub nonce: u64,
+    /// Spending policy, if this is a multisig account
+    #[serde(default)]
+    pub multisig: Option<MultisigPolicy>,
+    /// Time locks on parts of the balance; expired ones are dropped when the account changes
+    #[serde(default)]
+    pub locks: Vec<TimeLock>,
+    /// Contract bytecode in hex format, empty unless this is a contract account
+    #[serde(default)]
+    pub code: String,
+    /// Contract storage; keys absent from the map hold 0
+    #[serde(default)]
+    pub storage: BTreeMap<u64, u64>,
 }
 
 impl Account {
@@ -57,8 +80,29 @@ impl Account {
             address,
             balance: 0,
             nonce: 0,
+            multisig: None,
+            locks: Vec::new(),
+            code: String::new(),
+            storage: BTreeMap::new(),
         }
     }
+    
+    /// Part of the balance that a transaction in the block at `height` cannot spend
+    pub fn locked_at(&self, height: u64) -> u64 {
+        self.locks.iter()
+            .filter(|lock| lock.unlock_height > height)
+            .fold(0u64, |total, lock| total.saturating_add(lock.amount))
+    }
+    
+    /// Check if the account holds contract code
+    pub fn is_contract(&self) -> bool {
+        !self.code.is_empty()
+    }
+    
+    /// Balance that a transaction in the block at `height` can spend
+    pub fn spendable_at(&self, height: u64) -> u64 {
+        self.balance.saturating_sub(self.locked_at(height))
+    }
 }
 
 /// Key pair for signing transactions
@@ -79,6 +123,35 @@ impl KeyPair {
         }
     }
     
+    /// Restore a key pair from its 32-byte secret key
+    pub fn from_secret_bytes(secret: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(secret);
+        let verifying_key = signing_key.verifying_key();
+        Self {
+            signing_key,
+            verifying_key,
+        }
+    }
+    
+    /// Restore a key pair from its secret key in hex format
+    pub fn from_secret_hex(secret: &str) -> crate::Result<Self> {
+        let bytes = zeroize::Zeroizing::new(hex::decode(secret)
+            .map_err(|_| ElysiumError::KeyError("Secret key must be hex".to_string()))?);
+        let secret: &[u8; 32] = bytes.as_slice().try_into()
+            .map_err(|_| ElysiumError::KeyError("Secret key must be 32 bytes".to_string()))?;
+        Ok(Self::from_secret_bytes(secret))
+    }
+    
+    /// Export the 32-byte secret key; anyone holding it controls the account
+    pub fn to_secret_bytes(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+    
+    /// Export the secret key in hex format
+    pub fn to_secret_hex(&self) -> String {
+        hex::encode(self.signing_key.to_bytes())
+    }
+    
     /// Get the address for this key pair
     pub fn address(&self) -> Address {
         Address::from_verifying_key(&self.verifying_key)
@@ -89,6 +162,16 @@ impl KeyPair {
         self.signing_key.sign(data)
     }
     
+    /// Sign a transaction, which must be sent from this key pair's address
+    ///
+    /// The signature covers the transaction's chain id and type, so set those first.
+    pub fn sign_transaction(&self, tx: &mut Transaction) -> crate::Result<()> {
+        if tx.from != self.address() {
+            return Err(ElysiumError::InvalidSignature);
+        }
+        tx.sign(&self.signing_key)
+    }
+    
     /// Get verifying key
     pub fn verifying_key(&self) -> &VerifyingKey {
         &self.verifying_key
@@ -103,6 +186,7 @@ impl KeyPair {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ed25519_dalek::Verifier;
     
     #[test]
     fn test_address_generation() {
@@ -111,6 +195,15 @@ mod tests {
         assert_eq!(address.as_hex().len(), 64);
     }
     
+    #[test]
+    fn test_secret_key_roundtrip() {
+        let keypair = KeyPair::generate();
+        let restored = KeyPair::from_secret_hex(&keypair.to_secret_hex()).unwrap();
+        assert_eq!(restored.address(), keypair.address());
+        assert!(KeyPair::from_secret_hex("abcd").is_err());
+        assert!(KeyPair::from_secret_hex(&"zz".repeat(32)).is_err());
+    }
+    
     #[test]
     fn test_keypair_sign_verify() {
         let keypair = KeyPair::generate();
@@ -1,7 +1,19 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use crate::{Transaction, Result, ElysiumError};
+use crate::{Transaction, Result, ElysiumError, MerkleTree, TransactionProof};
+use crate::codec::{self, Encoder};
+use crate::pow::{self, U256};
 use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Tag of the header seal signed by proof-of-authority validators
+///
+/// It differs from the transaction [`SIGNING_DOMAIN`](crate::SIGNING_DOMAIN), so a validator
+/// may use its key for transactions too.
+pub const BLOCK_SIGNING_DOMAIN: &[u8] = b"elysium-block";
+
+/// Nonces tried between checks of the stop flag in [`BlockHeader::search_nonce`]
+const NONCE_BATCH: u64 = 1024;
 
 /// Block header
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,10 +24,19 @@ pub struct BlockHeader {
     pub state_root: String,
     pub timestamp: u64,
     pub nonce: u64,
-    pub difficulty: u64,
+    /// Proof-of-work target in compact form (see [`U256::from_compact`])
+    pub bits: u32,
+    /// Public key of the validator that sealed the block in hex format; empty under
+    /// proof of work
+    #[serde(default)]
+    pub public_key: String,
+    /// Validator signature over the header's signing hash; empty under proof of work
+    #[serde(default)]
+    pub signature: String,
 }
 
 impl BlockHeader {
+    /// Create a header whose target corresponds to `difficulty`
     pub fn new(number: u64, parent_hash: String, difficulty: u64) -> Self {
         Self {
             number,
@@ -27,29 +48,82 @@ impl BlockHeader {
                 .unwrap()
                 .as_secs(),
             nonce: 0,
-            difficulty,
+            bits: pow::bits_for_difficulty(difficulty),
+            public_key: String::new(),
+            signature: String::new(),
         }
     }
     
-    /// Calculate hash of the block header
+    /// Calculate hash of the block header over its canonical encoding
     pub fn hash(&self) -> String {
-        let bytes = serde_json::to_vec(self).unwrap_or_default();
-        let hash = Sha256::digest(&bytes);
+        let hash = Sha256::digest(codec::to_bytes(self));
         hex::encode(hash)
     }
     
-    /// Check if the block meets the difficulty requirement
+    /// Hash a validator signs to seal the header: the encoding of every field except the
+    /// seal, after a domain tag
+    pub fn signing_hash(&self) -> String {
+        hex::encode(self.signing_hash_bytes())
+    }
+    
+    pub(crate) fn signing_hash_bytes(&self) -> [u8; 32] {
+        let mut encoder = Encoder::new();
+        encoder.put_bytes(BLOCK_SIGNING_DOMAIN);
+        encoder.put_u8(codec::CODEC_VERSION);
+        self.encode_unsealed(&mut encoder);
+        Sha256::digest(encoder.into_bytes()).into()
+    }
+    
+    /// Encode the fields covered by a validator signature
+    pub(crate) fn encode_unsealed(&self, encoder: &mut Encoder) {
+        encoder.put_u64(self.number);
+        encoder.put_hex(&self.parent_hash);
+        encoder.put_hex(&self.transactions_root);
+        encoder.put_hex(&self.state_root);
+        encoder.put_u64(self.timestamp);
+        encoder.put_u64(self.nonce);
+        encoder.put_u32(self.bits);
+    }
+    
+    /// Check if the block hash is at or below the target
     pub fn meets_difficulty(&self) -> bool {
-        let hash = self.hash();
-        let hash_bytes = hex::decode(&hash).unwrap_or_default();
-        
-        // Count leading zero bytes
-        let leading_zeros = hash_bytes.iter()
-            .take_while(|&&b| b == 0)
-            .count();
-        
-        // Difficulty is number of leading zero bytes required
-        leading_zeros >= self.difficulty as usize
+        let target = match U256::from_compact(self.bits) {
+            Some(target) => target,
+            None => return false,
+        };
+        let hash: [u8; 32] = Sha256::digest(codec::to_bytes(self)).into();
+        U256::from_be_bytes(hash) <= target
+    }
+    
+    /// Try the nonces `start`, `start + step`, `start + 2 * step`, ... until one meets the
+    /// target, returning it, or give up with `None` once `stop` is set
+    ///
+    /// Threads searching the same header with different starts and a common step never
+    /// try the same nonce. `hashes` counts the nonces tried.
+    pub fn search_nonce(&self, start: u64, step: u64, stop: &AtomicBool, hashes: &AtomicU64) -> Option<u64> {
+        let mut header = self.clone();
+        header.nonce = start;
+        while !stop.load(Ordering::Relaxed) {
+            for tried in 1..=NONCE_BATCH {
+                if header.meets_difficulty() {
+                    hashes.fetch_add(tried, Ordering::Relaxed);
+                    return Some(header.nonce);
+                }
+                header.nonce = header.nonce.wrapping_add(step);
+            }
+            hashes.fetch_add(NONCE_BATCH, Ordering::Relaxed);
+        }
+        None
+    }
+    
+    /// Difficulty of the header's target relative to the maximum target
+    pub fn difficulty(&self) -> u64 {
+        pow::difficulty_for_bits(self.bits)
+    }
+    
+    /// Expected number of hashes needed to mine a header at this target
+    pub fn work(&self) -> u128 {
+        pow::work_for_bits(self.bits)
     }
 }
 
@@ -80,21 +154,42 @@ impl Block {
     
     /// Update the transactions root hash
     fn update_transactions_root(&mut self) {
-        if self.transactions.is_empty() {
-            // For empty blocks, use hash of empty string
-            let hash = Sha256::digest(b"");
-            self.header.transactions_root = hex::encode(hash);
-        } else {
-            let tx_hashes: Vec<String> = self.transactions.iter()
-                .map(|tx| tx.hash())
-                .collect();
-            let combined = tx_hashes.join("");
-            let hash = Sha256::digest(combined.as_bytes());
-            self.header.transactions_root = hex::encode(hash);
-        }
+        self.header.transactions_root = self.transactions_tree().root();
+    }
+    
+    /// Merkle tree over the transaction hashes, in block order
+    pub fn transactions_tree(&self) -> MerkleTree {
+        let tx_hashes: Vec<String> = self.transactions.iter()
+            .map(|tx| tx.hash())
+            .collect();
+        MerkleTree::from_hashes(&tx_hashes)
+    }
+    
+    /// Build an inclusion proof for a transaction in this block
+    pub fn transaction_proof(&self, tx_hash: &str) -> Option<TransactionProof> {
+        let index = self.transactions.iter().position(|tx| tx.hash() == tx_hash)?;
+        let proof = self.transactions_tree().proof(index)?;
+        Some(TransactionProof {
+            transaction_hash: tx_hash.to_string(),
+            block_hash: self.hash(),
+            header: self.header.clone(),
+            proof,
+        })
+    }
+    
+    /// The block's coinbase transaction, if it has one
+    pub fn coinbase(&self) -> Option<&Transaction> {
+        self.transactions.first().filter(|tx| tx.is_coinbase())
     }
     
-    /// Mine the block (find a nonce that meets difficulty)
+    /// Total fees paid by the block's transfers
+    pub fn total_fees(&self) -> Option<u64> {
+        self.transactions.iter()
+            .filter(|tx| !tx.is_coinbase())
+            .try_fold(0u64, |total, tx| total.checked_add(tx.fee))
+    }
+    
+    /// Mine the block under proof of work (find a nonce that meets difficulty)
     pub fn mine(&mut self) {
         while !self.header.meets_difficulty() {
             self.header.nonce += 1;
@@ -107,14 +202,9 @@ impl Block {
     }
     
     /// Validate the block with comprehensive security checks
+    ///
+    /// The seal is checked by the chain's [`crate::Consensus`] engine, not here.
     pub fn validate(&self) -> Result<()> {
-        // Verify difficulty
-        if !self.header.meets_difficulty() {
-            return Err(ElysiumError::InvalidBlock(
-                "Block does not meet difficulty requirement".to_string()
-            ));
-        }
-        
         // Verify transactions root
         // If transactions_root is empty, it means it hasn't been calculated yet
         // Calculate it and compare
@@ -130,7 +220,22 @@ impl Block {
         // Validate all transactions in the block (if any)
         // Empty blocks (like genesis blocks) are valid
         if !self.transactions.is_empty() {
-            for tx in &self.transactions {
+            for (index, tx) in self.transactions.iter().enumerate() {
+                // Only the first transaction may be a coinbase, which is unsigned
+                if tx.is_coinbase() {
+                    if index != 0 {
+                        return Err(ElysiumError::InvalidBlock(
+                            "Coinbase must be the first transaction".to_string()
+                        ));
+                    }
+                    if tx.nonce != self.header.number || tx.fee != 0 {
+                        return Err(ElysiumError::InvalidBlock(
+                            "Malformed coinbase transaction".to_string()
+                        ));
+                    }
+                    continue;
+                }
+                
                 // Verify signature
                 tx.verify()?;
             }
@@ -163,6 +268,7 @@ impl fmt::Display for Block {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::account::KeyPair;
     
     #[test]
     fn test_block_creation() {
@@ -178,6 +284,25 @@ mod tests {
         assert!(block.header.meets_difficulty());
     }
     
+    #[test]
+    fn test_search_nonce() {
+        let header = Block::new(0, "0".repeat(64), 1).header;
+        let hashes = AtomicU64::new(0);
+        
+        // Odd nonces only, counting every one tried
+        let nonce = header.search_nonce(1, 2, &AtomicBool::new(false), &hashes).unwrap();
+        assert_eq!(nonce % 2, 1);
+        assert_eq!(hashes.load(Ordering::Relaxed), nonce / 2 + 1);
+        let mut mined = header.clone();
+        mined.nonce = nonce;
+        assert!(mined.meets_difficulty());
+        
+        // A set stop flag ends the search
+        let mut impossible = header;
+        impossible.bits = 0x03000001;
+        assert_eq!(impossible.search_nonce(0, 1, &AtomicBool::new(true), &hashes), None);
+    }
+    
     #[test]
     fn test_block_validation() {
         let mut block = Block::new(0, "0".repeat(64), 1);
@@ -185,5 +310,28 @@ mod tests {
         // Empty blocks are valid (genesis block)
         assert!(block.validate().is_ok());
     }
+    
+    #[test]
+    fn test_transaction_proof() {
+        let keypair = KeyPair::generate();
+        let to = KeyPair::generate().address();
+        let mut block = Block::new(1, "0".repeat(64), 1);
+        for nonce in 0..5 {
+            let mut tx = Transaction::new(keypair.address(), to.clone(), 1, nonce);
+            tx.sign(keypair.signing_key()).unwrap();
+            block.add_transaction(tx);
+        }
+        block.mine();
+        
+        let tx_hash = block.transactions[3].hash();
+        let proof = block.transaction_proof(&tx_hash).unwrap();
+        assert!(proof.verify());
+        assert_eq!(proof.block_hash, block.hash());
+        assert!(block.transaction_proof(&"0".repeat(64)).is_none());
+        
+        // Reordering transactions changes the root
+        let mut reordered = block.clone();
+        reordered.transactions.swap(0, 1);
+        assert!(reordered.validate().is_err());
+    }
 }
-
@@ -1,38 +1,303 @@
 use std::collections::{HashMap, HashSet};
-use crate::{Block, Transaction, Address, Account, Result, ElysiumError};
-use sha2::Sha256;
+use std::sync::Arc;
+use crate::{Block, BlockHeader, Transaction, TransactionType, TransactionProof, Address, Account, TimeLock, Result, ElysiumError};
+use crate::account::KeyPair;
+use crate::consensus::{Consensus, ProofOfWork};
+use crate::contract::{self, AccountHost, CallResult, MAX_BLOCK_GAS};
+use crate::genesis::{self, GenesisSpec, DEV_CHAIN_ID};
+use crate::index::{AddressHistory, ChainIndex, ExecutionStatus, TransactionLookup, TransactionReceipt, MAX_HISTORY_PAGE};
+use crate::state::{StateTree, StateProof};
+use crate::pow::{self, RetargetConfig};
+use crate::mempool::Mempool;
+use crate::multisig::MultisigPolicy;
+use crate::reward::RewardSchedule;
+use crate::storage::{Storage, StateSnapshot, UndoRecord};
+
+/// Maximum number of main-chain blocks a reorganization may roll back
+pub const MAX_REORG_DEPTH: u64 = 100;
+
+/// Maximum number of pooled transactions a locally mined block includes
+pub const MAX_BLOCK_TRANSACTIONS: usize = 1_000;
+
+/// How far ahead of the local clock a block timestamp may be, in seconds
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
+
+/// Maximum number of headers returned by one [`Blockchain::get_headers`] call
+pub const MAX_HEADERS_PER_REQUEST: usize = 1_000;
+
+/// A known block, on the main chain or on a side branch
+#[derive(Debug, Clone)]
+struct TreeEntry {
+    block: Block,
+    /// Consensus weight of this block and all of its ancestors
+    total_work: u128,
+}
 
 /// Blockchain state
+///
+/// `blocks` is the main chain: the branch of the block tree with the most cumulative
+/// work, as weighed by the chain's [`Consensus`] engine. Blocks on side branches are kept in the tree, and when a side branch
+/// overtakes the main chain the state is rolled back to the fork point using the undo
+/// records of the main-chain blocks and the side branch is applied in its place.
 #[derive(Debug, Clone)]
 pub struct Blockchain {
     pub blocks: Vec<Block>,
     pub accounts: HashMap<Address, Account>,
-    pub pending_transactions: Vec<Transaction>,
+    /// Transactions waiting to be mined
+    pub mempool: Mempool,
+    /// Difficulty of the genesis block, which the first retarget window keeps
     pub difficulty: u64,
+    /// How the proof-of-work target follows the observed block rate
+    pub retarget: RetargetConfig,
+    /// New coins each block may mint
+    pub rewards: RewardSchedule,
+    /// Address credited with the reward and fees of locally mined blocks; without one,
+    /// blocks are mined without a coinbase and their fees are burned
+    pub miner_address: Option<Address>,
+    /// Key sealing locally produced blocks under proof of authority
+    pub validator_key: Option<KeyPair>,
+    /// Engine sealing blocks and checking their seals, from the genesis spec
+    consensus: Arc<dyn Consensus>,
+    /// Every known block by hash, including side branches
+    tree: HashMap<String, TreeEntry>,
+    /// Account states from before each recent main-chain block was applied, by block hash
+    undo: HashMap<String, UndoRecord>,
+    /// Transactions, addresses and block hashes of the main chain
+    index: ChainIndex,
+    /// Optional persistent storage; every block added to the chain is written to it
+    storage: Option<Arc<dyn Storage>>,
 }
 
 impl Blockchain {
-    /// Create a new blockchain
+    /// Create a development blockchain with a fresh genesis block
+    ///
+    /// The genesis block carries the current time, so it is unique to this instance.
+    /// Nodes that need to agree on a chain use [`Blockchain::from_genesis`].
     pub fn new(difficulty: u64) -> Self {
-        let mut blockchain = Self {
+        let mut blockchain = Self::empty(difficulty);
+        
+        // Create genesis block
+        blockchain.create_genesis_block();
+        blockchain
+    }
+    
+    /// Create a blockchain from a genesis specification, with its allocations applied
+    pub fn from_genesis(spec: &GenesisSpec) -> Result<Self> {
+        spec.validate()?;
+        let mut blockchain = Self::empty(spec.difficulty);
+        blockchain.consensus = spec.consensus.engine()?;
+        blockchain.retarget = spec.retarget;
+        blockchain.accounts = spec.accounts();
+        blockchain.insert_genesis(spec.genesis_block());
+        Ok(blockchain)
+    }
+    
+    /// Create a blockchain without any blocks
+    fn empty(difficulty: u64) -> Self {
+        Self {
             blocks: Vec::new(),
             accounts: HashMap::new(),
-            pending_transactions: Vec::new(),
+            mempool: Mempool::default(),
             difficulty,
+            retarget: RetargetConfig::default(),
+            rewards: RewardSchedule::default(),
+            miner_address: None,
+            validator_key: None,
+            consensus: Arc::new(ProofOfWork),
+            tree: HashMap::new(),
+            undo: HashMap::new(),
+            index: ChainIndex::default(),
+            storage: None,
+        }
+    }
+    
+    /// Open a blockchain backed by storage
+    ///
+    /// An empty storage is initialized with a fresh genesis block. Otherwise every stored
+    /// block is verified with the consensus engine recorded in the storage and linked into
+    /// the block tree, the account state is restored from the snapshot, and blocks stored
+    /// after the snapshot was taken are replayed by moving to the branch with the most work.
+    pub fn open(difficulty: u64, storage: Arc<dyn Storage>) -> Result<Self> {
+        Self::open_with(difficulty, None, storage)
+    }
+    
+    /// Open a blockchain backed by storage, starting from a genesis specification
+    ///
+    /// An empty storage is initialized with the specified genesis block. A stored chain
+    /// must start with that same block.
+    pub fn open_genesis(spec: &GenesisSpec, storage: Arc<dyn Storage>) -> Result<Self> {
+        Self::open_with(spec.difficulty, Some(spec), storage)
+    }
+    
+    fn open_with(difficulty: u64, spec: Option<&GenesisSpec>, storage: Arc<dyn Storage>) -> Result<Self> {
+        let mut stored = storage.load_blocks()?.into_iter();
+        
+        let genesis = match stored.next() {
+            Some(genesis) => genesis,
+            None => {
+                let mut blockchain = match spec {
+                    Some(spec) => Self::from_genesis(spec)?,
+                    None => Self::new(difficulty),
+                };
+                storage.save_consensus(&spec.map(|spec| spec.consensus.clone()).unwrap_or_default())?;
+                storage.save_retarget(&blockchain.retarget)?;
+                blockchain.storage = Some(storage);
+                let genesis = blockchain.latest_block().clone();
+                blockchain.persist_block(&genesis)?;
+                return Ok(blockchain);
+            }
         };
         
-        // Create genesis block
-        blockchain.create_genesis_block();
-        blockchain
+        // A chain opened without its spec is checked with the rules it was created with
+        let stored_consensus = storage.load_consensus()?;
+        let consensus = match (spec, &stored_consensus) {
+            (Some(spec), Some(stored)) if stored != &spec.consensus => {
+                return Err(ElysiumError::StorageError(
+                    "Stored chain was created with a different consensus engine".to_string()
+                ));
+            }
+            (Some(spec), _) => spec.consensus.clone(),
+            (None, stored) => stored.clone().unwrap_or_default(),
+        };
+        let stored_retarget = storage.load_retarget()?;
+        let retarget = match (spec, stored_retarget) {
+            (Some(spec), Some(stored)) if stored != spec.retarget => {
+                return Err(ElysiumError::StorageError(
+                    "Stored chain was created with different retarget rules".to_string()
+                ));
+            }
+            (Some(spec), _) => spec.retarget,
+            (None, stored) => stored.unwrap_or_default(),
+        };
+        let mut blockchain = Self::empty(difficulty);
+        blockchain.consensus = consensus.engine()?;
+        blockchain.retarget = retarget;
+        genesis.validate()?;
+        blockchain.verify_seal(&genesis)?;
+        if genesis.header.number != 0 {
+            return Err(ElysiumError::StorageError(
+                "Stored chain does not start with a genesis block".to_string()
+            ));
+        }
+        if let Some(spec) = spec {
+            if genesis.hash() != spec.genesis_block().hash() {
+                return Err(ElysiumError::StorageError(
+                    "Stored chain was created from a different genesis spec".to_string()
+                ));
+            }
+            blockchain.accounts = spec.accounts();
+        }
+        // Data directories from before the rules were recorded hold chains with the defaults
+        if stored_consensus.is_none() {
+            storage.save_consensus(&consensus)?;
+        }
+        if stored_retarget.is_none() {
+            storage.save_retarget(&retarget)?;
+        }
+        blockchain.insert_genesis(genesis);
+        
+        for block in stored {
+            block.validate()?;
+            blockchain.verify_seal(&block)?;
+            blockchain.insert_into_tree(block)
+                .map_err(|e| ElysiumError::StorageError(format!("Invalid stored block: {}", e)))?;
+        }
+        
+        if let Some(snapshot) = storage.load_state()? {
+            let main_chain = blockchain.branch_to(&snapshot.tip_hash)
+                .filter(|chain| chain.len() as u64 == snapshot.height)
+                .ok_or_else(|| ElysiumError::StorageError(
+                    "State snapshot does not match the stored chain".to_string()
+                ))?;
+            blockchain.blocks = main_chain;
+            blockchain.index = ChainIndex::default();
+            for block in &blockchain.blocks {
+                blockchain.index.connect(block, &[]);
+            }
+            blockchain.index.restore_executions(snapshot.executions);
+            blockchain.accounts = snapshot.accounts.into_iter()
+                .map(|account| (account.address.clone(), account))
+                .collect();
+            blockchain.undo = snapshot.undo.into_iter()
+                .map(|record| (record.block_hash.clone(), record))
+                .collect();
+        } else if blockchain.state_root() != blockchain.blocks[0].header.state_root {
+            return Err(ElysiumError::StorageError(
+                "State snapshot is missing and the genesis allocations are unknown".to_string()
+            ));
+        }
+        
+        let replayed = blockchain.adopt_heaviest_branch();
+        
+        blockchain.storage = Some(storage);
+        if replayed {
+            blockchain.save_state()?;
+        }
+        
+        Ok(blockchain)
+    }
+    
+    /// Write a block that was just added to the chain, plus the resulting state, to storage
+    fn persist_block(&self, block: &Block) -> Result<()> {
+        if let Some(storage) = &self.storage {
+            storage.append_block(block)?;
+            self.save_state()?;
+        }
+        Ok(())
+    }
+    
+    /// Write the current account state snapshot to storage
+    fn save_state(&self) -> Result<()> {
+        if let Some(storage) = &self.storage {
+            storage.save_state(&StateSnapshot {
+                height: self.height(),
+                tip_hash: self.latest_block().hash(),
+                accounts: self.accounts.values().cloned().collect(),
+                undo: self.undo.values().cloned().collect(),
+                executions: self.index.executions()
+                    .map(|(tx_hash, status)| (tx_hash.clone(), *status))
+                    .collect(),
+            })?;
+        }
+        Ok(())
     }
     
     /// Create the genesis block
     fn create_genesis_block(&mut self) {
         let mut genesis = Block::new(0, "0".repeat(64), self.difficulty);
+        genesis.header.state_root = self.state_root();
         genesis.mine();
+        self.insert_genesis(genesis);
+    }
+    
+    /// Make a block the root of the block tree and of the main chain
+    fn insert_genesis(&mut self, genesis: Block) {
+        self.tree.insert(genesis.hash(), TreeEntry {
+            total_work: self.consensus.work(&genesis.header),
+            block: genesis.clone(),
+        });
+        self.index.connect(&genesis, &[]);
         self.blocks.push(genesis);
     }
     
+    /// Engine sealing the chain's blocks
+    pub fn consensus(&self) -> &Arc<dyn Consensus> {
+        &self.consensus
+    }
+    
+    /// Check a block's seal; a genesis block is only sealed under proof of work
+    fn verify_seal(&self, block: &Block) -> Result<()> {
+        if block.header.number == 0 && !self.consensus.is_proof_of_work() {
+            return Ok(());
+        }
+        self.consensus.verify_seal(&block.header)
+    }
+    
+    /// Chain id committed to by the genesis block
+    pub fn chain_id(&self) -> u64 {
+        genesis::chain_id_of(&self.blocks[0]).unwrap_or(DEV_CHAIN_ID)
+    }
+    
     /// Get the latest block
     pub fn latest_block(&self) -> &Block {
         self.blocks.last().unwrap()
@@ -45,6 +310,36 @@ impl Blockchain {
             .unwrap_or(0)
     }
     
+    /// Get the part of an account's balance the next block can spend, excluding time-locked amounts
+    pub fn get_spendable_balance(&self, address: &Address) -> u64 {
+        self.accounts.get(address)
+            .map(|acc| acc.spendable_at(self.height()))
+            .unwrap_or(0)
+    }
+    
+    /// Get the hex-encoded code of a contract account, empty for other accounts
+    pub fn get_code(&self, address: &Address) -> String {
+        self.accounts.get(address)
+            .map(|acc| acc.code.clone())
+            .unwrap_or_default()
+    }
+    
+    /// Run a contract against the current state without changing it
+    ///
+    /// The call sees the height of the next block and sends no value.
+    pub fn call_contract(&self, address: &Address, input: Vec<u64>, gas_limit: u64) -> Result<CallResult> {
+        let account = self.accounts.get(address)
+            .filter(|acc| acc.is_contract())
+            .ok_or_else(|| ElysiumError::ContractError(format!("{} is not a contract", address)))?;
+        let code = hex::decode(&account.code)
+            .map_err(|e| ElysiumError::ContractError(e.to_string()))?;
+        let context = elysium_vm::Context { value: 0, input, gas_limit: gas_limit.min(MAX_BLOCK_GAS) };
+        let outcome = elysium_vm::execute(&code, &context, &AccountHost::new(Some(account), self.height()));
+        let output = outcome.result
+            .map_err(|e| ElysiumError::ContractError(e.to_string()))?;
+        Ok(CallResult { output, gas_used: outcome.gas_used })
+    }
+    
     /// Get account nonce
     pub fn get_nonce(&self, address: &Address) -> u64 {
         self.accounts.get(address)
@@ -53,32 +348,45 @@ impl Blockchain {
     }
     
     /// Add a transaction to the pending pool
+    ///
+    /// Its nonce may be ahead of the sender's confirmed nonce, in which case it waits in
+    /// the pool until the transactions before it arrive. See [`Mempool`] for the rules.
     pub fn add_transaction(&mut self, tx: Transaction) -> Result<()> {
         // Validate transaction
         self.validate_transaction(&tx)?;
         
-        // Check balance
-        let balance = self.get_balance(&tx.from);
-        if balance < tx.amount {
-            return Err(ElysiumError::InsufficientBalance);
-        }
-        
-        // Check nonce
-        let nonce = self.get_nonce(&tx.from);
-        if tx.nonce != nonce {
-            return Err(ElysiumError::InvalidTransaction(
-                format!("Invalid nonce: expected {}, got {}", nonce, tx.nonce)
-            ));
-        }
-        
-        self.pending_transactions.push(tx);
-        Ok(())
+        // Check nonce and balance against the confirmed state and the sender's pooled transactions
+        self.mempool.insert(tx, &self.accounts, self.height())
+    }
+    
+    /// All pooled transactions, executable or waiting for earlier nonces
+    pub fn pending_transactions(&self) -> Vec<Transaction> {
+        self.mempool.transactions()
+    }
+    
+    /// Nonce for the sender's next transaction, after its executable pooled ones
+    pub fn get_pending_nonce(&self, address: &Address) -> u64 {
+        self.mempool.pending_nonce(address, &self.accounts)
     }
     
     /// Validate a transaction with comprehensive security checks
     fn validate_transaction(&self, tx: &Transaction) -> Result<()> {
-        // Validate amount
-        if tx.amount == 0 {
+        // Coinbase transactions are only created by miners, inside a block
+        if tx.is_coinbase() {
+            return Err(ElysiumError::InvalidTransaction(
+                "Coinbase transactions cannot be submitted".to_string()
+            ));
+        }
+        
+        // Signatures from other networks must not be replayable here
+        self.check_chain_id(tx)?;
+        
+        // Validate amount; creating a multisig account or using a contract need not send any
+        let unfunded = matches!(
+            tx.transaction_type,
+            TransactionType::CreateMultisig(_) | TransactionType::Deploy { .. } | TransactionType::Call { .. }
+        );
+        if tx.amount == 0 && !unfunded {
             return Err(ElysiumError::InvalidTransaction(
                 "Transaction amount must be greater than 0".to_string()
             ));
@@ -94,15 +402,15 @@ impl Blockchain {
         // Verify signature - CRITICAL for security
         tx.verify()?;
         
-        // Check for duplicate transactions in pending pool (prevent replay attacks)
-        if self.pending_transactions.iter().any(|pending_tx| {
-            pending_tx.from == tx.from &&
-            pending_tx.nonce == tx.nonce &&
-            pending_tx.hash() == tx.hash()
-        }) {
-            return Err(ElysiumError::InvalidTransaction(
-                "Duplicate transaction detected in pending pool".to_string()
-            ));
+        // Multisig accounts are only spent from with enough co-signatures
+        check_multisig(tx, self.multisig_policy(&tx.from), self.multisig_policy(&tx.to))?;
+        
+        // Contract code must be valid, and calls must reach a deployed contract
+        contract::check_contract_transaction(tx)?;
+        if let TransactionType::Call { .. } = tx.transaction_type {
+            if self.get_code(&tx.to).is_empty() {
+                return Err(ElysiumError::ContractError(format!("{} is not a contract", tx.to)));
+            }
         }
         
         // Check for duplicate transactions in recent blocks (prevent replay attacks)
@@ -123,9 +431,48 @@ impl Blockchain {
         Ok(())
     }
     
+    /// Spending policy of an account, if it is a multisig account
+    pub fn multisig_policy(&self, address: &Address) -> Option<&MultisigPolicy> {
+        self.accounts.get(address).and_then(|acc| acc.multisig.as_ref())
+    }
+    
+    /// Check that a transaction was made for this network
+    fn check_chain_id(&self, tx: &Transaction) -> Result<()> {
+        let chain_id = self.chain_id();
+        if tx.chain_id != chain_id {
+            return Err(ElysiumError::InvalidTransaction(
+                format!("Wrong chain id: expected {}, got {}", chain_id, tx.chain_id)
+            ));
+        }
+        Ok(())
+    }
+    
     /// Mine a new block with pending transactions
     pub fn mine_block(&mut self) -> Result<Block> {
-        if self.pending_transactions.is_empty() {
+        let mut block = self.mining_template()?;
+        self.consensus.seal(&mut block.header, self.validator_key.as_ref())?;
+        self.add_block(block.clone())?;
+        Ok(block)
+    }
+    
+    /// Block of pending transactions on top of the current tip, complete except for
+    /// its seal
+    ///
+    /// Only reads the chain, so a proof-of-work nonce can be searched for without holding
+    /// on to it; the sealed block is then added with [`Blockchain::add_block`].
+    pub fn mining_template(&self) -> Result<Block> {
+        // Executable pooled transactions, highest fee first
+        let mut transactions = self.mempool.ready(&self.accounts, MAX_BLOCK_TRANSACTIONS);
+        // Stop at the block gas limit; a prefix keeps every sender's nonces contiguous
+        let mut gas = 0u64;
+        let within_gas = transactions.iter()
+            .take_while(|tx| {
+                gas = gas.saturating_add(tx.gas_limit());
+                gas <= MAX_BLOCK_GAS
+            })
+            .count();
+        transactions.truncate(within_gas);
+        if transactions.is_empty() {
             return Err(ElysiumError::BlockchainError(
                 "No pending transactions to mine".to_string()
             ));
@@ -137,85 +484,560 @@ impl Blockchain {
             latest.hash(),
             self.difficulty,
         );
+        new_block.header.bits = self.next_bits(&latest.hash())
+            .expect("tip is in the block tree");
+        // Timestamps never go backwards, even if the parent's clock ran ahead of ours
+        new_block.header.timestamp = new_block.header.timestamp.max(latest.header.timestamp);
         
-        // Move pending transactions to the block
-        let transactions = self.pending_transactions.drain(..).collect::<Vec<_>>();
+        // The coinbase paying the miner goes first
+        if let Some(miner) = &self.miner_address {
+            let fees = transactions.iter().map(|tx| tx.fee).fold(0u64, u64::saturating_add);
+            let reward = self.rewards.reward_at(new_block.header.number).saturating_add(fees);
+            let coinbase = Transaction::coinbase(miner.clone(), reward, new_block.header.number)
+                .with_chain_id(self.chain_id());
+            transactions.insert(0, coinbase);
+        }
         for tx in &transactions {
             new_block.add_transaction(tx.clone());
         }
         
-        // Mine the block
-        new_block.mine();
+        // Commit to the state the block produces
+        new_block.header.state_root = self.state_root_after(&transactions, new_block.header.number);
         
-        // Validate the block
-        new_block.validate()?;
+        Ok(new_block)
+    }
+    
+    /// Apply the transactions of the block at `height` to the state, returning the
+    /// outcome of each deploy and call
+    fn apply_transactions(&mut self, transactions: &[Transaction], height: u64) -> Vec<Option<ExecutionStatus>> {
+        apply_to_accounts(&mut self.accounts, transactions, height)
+    }
+    
+    /// Root of the sparse Merkle tree over the current account state
+    pub fn state_root(&self) -> String {
+        StateTree::from_accounts(self.accounts.values()).root()
+    }
+    
+    /// State root after applying the transactions of the block at `height` to the current state
+    fn state_root_after(&self, transactions: &[Transaction], height: u64) -> String {
+        let mut accounts = self.accounts.clone();
+        apply_to_accounts(&mut accounts, transactions, height);
+        StateTree::from_accounts(accounts.values()).root()
+    }
+    
+    /// Build a proof of an account's state as of the latest block
+    pub fn get_account_proof(&self, address: &Address) -> StateProof {
+        let latest = self.latest_block();
+        StateProof {
+            block_hash: latest.hash(),
+            header: latest.header.clone(),
+            proof: StateTree::from_accounts(self.accounts.values()).proof(address),
+        }
+    }
+    
+    /// Add a block to the chain (for syncing)
+    ///
+    /// A block extending the main chain is validated and applied immediately. A block
+    /// on a side branch is stored in the block tree and only validated against the
+    /// account state if its branch accumulates more work than the main chain, in which
+    /// case the chain reorganizes onto it.
+    pub fn add_block(&mut self, block: Block) -> Result<()> {
+        // Validate block structure and seal
+        block.validate()?;
+        self.verify_seal(&block)?;
+        self.validate_header_rules(&block)?;
         
-        // Apply transactions to state
-        self.apply_transactions(&transactions)?;
+        let extends_tip = block.header.parent_hash == self.latest_block().hash();
+        if extends_tip {
+            // Validate transactions before the block enters the tree
+            self.validate_block_state(&block)?;
+        }
         
-        // Add block to chain
-        self.blocks.push(new_block.clone());
+        let hash = self.insert_into_tree(block.clone())?;
         
-        Ok(new_block)
+        if extends_tip {
+            self.connect_block(block);
+            self.mempool.prune(&self.accounts, self.height());
+            self.persist_block(self.latest_block())?;
+            return Ok(());
+        }
+        
+        // Side branch: keep it, and switch to it once it has more work
+        if let Some(storage) = &self.storage {
+            storage.append_block(&block)?;
+        }
+        if self.tree[&hash].total_work > self.tip_work() {
+            self.reorganize(&hash)?;
+            self.save_state()?;
+        }
+        
+        Ok(())
     }
     
-    /// Apply transactions to the state
-    fn apply_transactions(&mut self, transactions: &[Transaction]) -> Result<()> {
-        for tx in transactions {
-            // Update sender account
-            let sender = self.accounts.entry(tx.from.clone())
-                .or_insert_with(|| Account::new(tx.from.clone()));
-            sender.balance = sender.balance.saturating_sub(tx.amount);
-            sender.nonce += 1;
-            
-            // Update receiver account
-            let receiver = self.accounts.entry(tx.to.clone())
-                .or_insert_with(|| Account::new(tx.to.clone()));
-            receiver.balance += tx.amount;
+    /// Check the target and timestamp of a block against its parent, if the parent is known
+    fn validate_header_rules(&self, block: &Block) -> Result<()> {
+        let parent = match self.tree.get(&block.header.parent_hash) {
+            Some(parent) => &parent.block,
+            None => return Ok(()), // Rejected as an unknown parent when inserted
+        };
+        
+        let expected_bits = self.next_bits(&block.header.parent_hash)
+            .expect("parent is in the block tree");
+        if block.header.bits != expected_bits {
+            return Err(ElysiumError::InvalidBlock(
+                format!("Invalid target: expected bits {:#010x}, got {:#010x}",
+                    expected_bits, block.header.bits)
+            ));
+        }
+        
+        if block.header.timestamp < parent.header.timestamp {
+            return Err(ElysiumError::InvalidBlock(
+                "Block timestamp is before its parent's".to_string()
+            ));
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if block.header.timestamp > now + MAX_FUTURE_BLOCK_TIME {
+            return Err(ElysiumError::InvalidBlock(
+                "Block timestamp is too far in the future".to_string()
+            ));
         }
         
         Ok(())
     }
     
-    /// Add a block to the chain (for syncing)
-    pub fn add_block(&mut self, block: Block) -> Result<()> {
-        // Validate block structure
-        block.validate()?;
+    /// Compact target required for a child of the given block
+    ///
+    /// The target stays the same within a retarget window. The first block of a new
+    /// window gets a target scaled by how long the previous window took on its branch.
+    pub fn next_bits(&self, parent_hash: &str) -> Option<u32> {
+        let parent = &self.tree.get(parent_hash)?.block;
+        let height = parent.header.number + 1;
+        // Without proof of work every block keeps the genesis target
+        if !self.consensus.is_proof_of_work() || !self.retarget.is_retarget_height(height) {
+            return Some(parent.header.bits);
+        }
         
-        // Check parent hash
-        let latest = self.latest_block();
-        if block.header.parent_hash != latest.hash() {
+        // First block of the window that just ended
+        let mut first = parent;
+        for _ in 1..self.retarget.window {
+            first = &self.tree.get(&first.header.parent_hash)?.block;
+        }
+        let timespan = parent.header.timestamp.saturating_sub(first.header.timestamp);
+        Some(self.retarget.next_bits(parent.header.bits, timespan))
+    }
+    
+    /// Difficulty the next block on the main chain has to meet
+    pub fn current_difficulty(&self) -> u64 {
+        self.next_bits(&self.latest_block().hash())
+            .map(pow::difficulty_for_bits)
+            .unwrap_or(0)
+    }
+    
+    /// Estimated network hash rate in hashes per second, from the work and timestamps
+    /// of up to `blocks` recent main-chain blocks
+    pub fn estimated_hashrate(&self, blocks: u64) -> u64 {
+        let tip = self.blocks.len() - 1;
+        let count = (blocks as usize).min(tip);
+        if count == 0 || !self.consensus.is_proof_of_work() {
+            return 0;
+        }
+        
+        let first = &self.blocks[tip - count];
+        let work: u128 = self.blocks[tip - count + 1..].iter()
+            .map(|block| block.header.work())
+            .sum();
+        let timespan = self.blocks[tip].header.timestamp
+            .saturating_sub(first.header.timestamp)
+            .max(1);
+        (work / timespan as u128).min(u64::MAX as u128) as u64
+    }
+    
+    /// Check whether a block is known, on the main chain or a side branch
+    pub fn contains_block(&self, hash: &str) -> bool {
+        self.tree.contains_key(hash)
+    }
+    
+    /// Get a known block by hash, on the main chain or a side branch
+    pub fn get_block_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.tree.get(hash).map(|entry| &entry.block)
+    }
+    
+    /// Headers of up to `count` consecutive main-chain blocks starting at `start`
+    ///
+    /// At most [`MAX_HEADERS_PER_REQUEST`] headers are returned, fewer if the chain ends first.
+    pub fn get_headers(&self, start: u64, count: usize) -> Vec<BlockHeader> {
+        self.blocks.iter()
+            .skip(start.min(self.blocks.len() as u64) as usize)
+            .take(count.min(MAX_HEADERS_PER_REQUEST))
+            .map(|block| block.header.clone())
+            .collect()
+    }
+    
+    /// Build an inclusion proof for a transaction on the main chain
+    pub fn get_transaction_proof(&self, tx_hash: &str) -> Option<TransactionProof> {
+        let location = self.index.location(tx_hash)?;
+        self.blocks[location.block_number as usize].transaction_proof(tx_hash)
+    }
+    
+    /// Number of a block on the main chain
+    pub fn block_number(&self, block_hash: &str) -> Option<u64> {
+        self.index.block_number(block_hash)
+    }
+    
+    /// Find a transaction on the main chain or in the pending pool
+    pub fn get_transaction(&self, tx_hash: &str) -> Option<TransactionLookup> {
+        match self.index.location(tx_hash) {
+            Some(location) => Some(TransactionLookup {
+                transaction: self.blocks[location.block_number as usize].transactions[location.index].clone(),
+                location: Some(location.clone()),
+            }),
+            None => self.mempool.get(tx_hash).map(|tx| TransactionLookup {
+                transaction: tx.clone(),
+                location: None,
+            }),
+        }
+    }
+    
+    /// Receipt of a transaction on the main chain
+    pub fn get_transaction_receipt(&self, tx_hash: &str) -> Option<TransactionReceipt> {
+        let location = self.index.location(tx_hash)?;
+        let tx = &self.blocks[location.block_number as usize].transactions[location.index];
+        let status = self.index.execution(tx_hash)
+            .unwrap_or(ExecutionStatus { success: true, gas_used: 0 });
+        Some(TransactionReceipt {
+            transaction_hash: tx_hash.to_string(),
+            block_hash: location.block_hash.clone(),
+            block_number: location.block_number,
+            index: location.index,
+            from: tx.from.clone(),
+            to: tx.to.clone(),
+            success: status.success,
+            gas_used: status.gas_used,
+            cost: tx.fee.saturating_add(status.gas_used.saturating_mul(tx.gas_price())),
+            confirmations: self.height() - location.block_number,
+        })
+    }
+    
+    /// Main-chain transactions sending from or to an address, newest first
+    ///
+    /// Skips `offset` transactions and returns at most `limit`, capped at [`MAX_HISTORY_PAGE`].
+    pub fn get_address_history(&self, address: &Address, offset: usize, limit: usize) -> AddressHistory {
+        let (hashes, total) = self.index.address_history(address, offset, limit.min(MAX_HISTORY_PAGE));
+        AddressHistory {
+            address: address.clone(),
+            total,
+            offset,
+            transactions: hashes.into_iter()
+                .filter_map(|tx_hash| self.get_transaction(tx_hash))
+                .collect(),
+        }
+    }
+    
+    /// Cumulative proof of work of the main chain
+    pub fn tip_work(&self) -> u128 {
+        self.tree.get(&self.latest_block().hash())
+            .map(|entry| entry.total_work)
+            .unwrap_or(0)
+    }
+    
+    /// Link a block into the block tree below its parent, returning its hash
+    fn insert_into_tree(&mut self, block: Block) -> Result<String> {
+        let hash = block.hash();
+        if self.tree.contains_key(&hash) {
             return Err(ElysiumError::InvalidBlock(
-                "Parent hash mismatch".to_string()
+                "Block already known".to_string()
             ));
         }
         
+        let parent = self.tree.get(&block.header.parent_hash)
+            .ok_or_else(|| ElysiumError::InvalidBlock(
+                "Parent hash mismatch: unknown parent block".to_string()
+            ))?;
+        
         // Validate block number is sequential
-        if block.header.number != latest.header.number + 1 {
+        let expected_number = parent.block.header.number + 1;
+        if block.header.number != expected_number {
             return Err(ElysiumError::InvalidBlock(
                 format!("Invalid block number: expected {}, got {}", 
-                    latest.header.number + 1, block.header.number)
+                    expected_number, block.header.number)
+            ));
+        }
+        
+        let total_work = parent.total_work.saturating_add(self.consensus.work(&block.header));
+        self.tree.insert(hash.clone(), TreeEntry { block, total_work });
+        Ok(hash)
+    }
+    
+    /// Apply a validated block on top of the main chain, recording how to undo it
+    fn connect_block(&mut self, block: Block) {
+        let mut touched: Vec<Address> = Vec::new();
+        for tx in &block.transactions {
+            let addresses = if tx.is_coinbase() { vec![&tx.to] } else { vec![&tx.from, &tx.to] };
+            for address in addresses {
+                if !touched.contains(address) {
+                    touched.push(address.clone());
+                }
+            }
+        }
+        let hash = block.hash();
+        self.undo.insert(hash.clone(), UndoRecord {
+            block_hash: hash,
+            accounts: touched.into_iter()
+                .map(|address| {
+                    let previous = self.accounts.get(&address).cloned();
+                    (address, previous)
+                })
+                .collect(),
+        });
+        
+        let executions = self.apply_transactions(&block.transactions, block.header.number);
+        self.index.connect(&block, &executions);
+        self.blocks.push(block);
+        
+        // Only recent blocks can be rolled back
+        if let Some(expired) = self.blocks.len().checked_sub(MAX_REORG_DEPTH as usize + 1) {
+            let expired_hash = self.blocks[expired].hash();
+            self.undo.remove(&expired_hash);
+        }
+    }
+    
+    /// Roll back the tip of the main chain, returning the removed block
+    fn disconnect_tip(&mut self) -> Result<Block> {
+        let hash = self.latest_block().hash();
+        let record = self.undo.remove(&hash)
+            .ok_or_else(|| ElysiumError::BlockchainError(
+                format!("No undo data for block {}", hash)
+            ))?;
+        
+        for (address, previous) in record.accounts {
+            match previous {
+                Some(account) => self.accounts.insert(address, account),
+                None => self.accounts.remove(&address),
+            };
+        }
+        
+        let block = self.blocks.pop().expect("tip has undo data, so it is not genesis");
+        self.index.disconnect(&block);
+        Ok(block)
+    }
+    
+    /// Blocks from genesis to the given block, if it is known
+    fn branch_to(&self, hash: &str) -> Option<Vec<Block>> {
+        let mut branch = Vec::new();
+        let mut current = self.tree.get(hash)?;
+        loop {
+            branch.push(current.block.clone());
+            if current.block.header.number == 0 {
+                break;
+            }
+            current = self.tree.get(&current.block.header.parent_hash)?;
+        }
+        branch.reverse();
+        Some(branch)
+    }
+    
+    /// Check whether a block is on the main chain
+    fn is_on_main_chain(&self, block: &Block) -> bool {
+        self.blocks.get(block.header.number as usize)
+            .map(|main| main.hash() == block.hash())
+            .unwrap_or(false)
+    }
+    
+    /// Switch the main chain to the branch ending at `new_tip`
+    ///
+    /// Main-chain blocks after the fork point are rolled back and their transactions
+    /// returned to the pending pool; the side branch is then validated and applied.
+    /// If the branch turns out to be invalid (or forks off too deep), the original
+    /// chain is restored and the offending blocks are dropped from the tree.
+    fn reorganize(&mut self, new_tip: &str) -> Result<()> {
+        // Side-branch blocks from the fork point up to the new tip
+        let mut branch = Vec::new();
+        let mut current = self.tree.get(new_tip)
+            .ok_or_else(|| ElysiumError::BlockchainError("Unknown block".to_string()))?;
+        while !self.is_on_main_chain(&current.block) {
+            branch.push(current.block.clone());
+            current = &self.tree[&current.block.header.parent_hash];
+        }
+        branch.reverse();
+        
+        let fork_point = current.block.header.number;
+        let depth = self.latest_block().header.number - fork_point;
+        let can_roll_back = self.blocks[fork_point as usize + 1..].iter()
+            .all(|block| self.undo.contains_key(&block.hash()));
+        if depth > MAX_REORG_DEPTH || !can_roll_back {
+            self.remove_subtree(&branch[0].hash());
+            return Err(ElysiumError::BlockchainError(
+                format!("Cannot reorganize {} blocks deep (maximum is {})", depth, MAX_REORG_DEPTH)
+            ));
+        }
+        
+        let mut disconnected = Vec::new();
+        while self.latest_block().header.number > fork_point {
+            disconnected.push(self.disconnect_tip()?);
+        }
+        disconnected.reverse();
+        
+        for block in &branch {
+            if let Err(e) = self.validate_block_state(block) {
+                // Restore the original chain
+                while self.latest_block().header.number > fork_point {
+                    self.disconnect_tip()?;
+                }
+                for old in disconnected {
+                    self.connect_block(old);
+                }
+                self.remove_subtree(&block.hash());
+                return Err(e);
+            }
+            self.connect_block(block.clone());
+        }
+        
+        // Orphaned transactions go back to the pool if they are still valid
+        self.mempool.prune(&self.accounts, self.height());
+        for tx in disconnected.into_iter().flat_map(|block| block.transactions) {
+            if !tx.is_coinbase() {
+                let _ = self.mempool.insert(tx, &self.accounts, self.height());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Move to the known branch with the most work, skipping branches that fail
+    /// validation; returns whether the main chain changed
+    fn adopt_heaviest_branch(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            let tip_work = self.tip_work();
+            let best = self.tree.iter()
+                .filter(|(_, entry)| entry.total_work > tip_work)
+                .max_by_key(|(_, entry)| entry.total_work)
+                .map(|(hash, _)| hash.clone());
+            
+            match best {
+                Some(hash) => changed |= self.reorganize(&hash).is_ok(),
+                None => return changed,
+            }
+        }
+    }
+    
+    /// Forget a block and all of its descendants
+    fn remove_subtree(&mut self, hash: &str) {
+        let mut removed: HashSet<String> = HashSet::from([hash.to_string()]);
+        loop {
+            let children: Vec<String> = self.tree.iter()
+                .filter(|(child, entry)| {
+                    removed.contains(&entry.block.header.parent_hash) && !removed.contains(*child)
+                })
+                .map(|(child, _)| child.clone())
+                .collect();
+            if children.is_empty() {
+                break;
+            }
+            removed.extend(children);
+        }
+        self.tree.retain(|hash, _| !removed.contains(hash));
+    }
+    
+    /// Validate a block that is about to be applied on top of the current state
+    fn validate_block_state(&self, block: &Block) -> Result<()> {
+        self.validate_block_transactions(block)?;
+        
+        let expected = self.state_root_after(&block.transactions, block.header.number);
+        if block.header.state_root != expected {
+            return Err(ElysiumError::InvalidBlock(
+                format!("Invalid state root: expected {}, got {}", expected, block.header.state_root)
             ));
         }
         
-        // Validate all transactions in the block
+        Ok(())
+    }
+    
+    /// Validate the transactions of a block against the current account state
+    fn validate_block_transactions(&self, block: &Block) -> Result<()> {
+        // Spendable balances, nonces and new multisig accounts as they evolve while the
+        // block is applied, so that several transactions from one sender are checked in order
+        let height = block.header.number;
+        let mut balances: HashMap<&Address, u64> = HashMap::new();
+        let mut nonces: HashMap<&Address, u64> = HashMap::new();
+        let mut created: HashMap<&Address, &MultisigPolicy> = HashMap::new();
+        let mut gas: u64 = 0;
+        let spendable = |address: &Address| {
+            self.accounts.get(address).map(|acc| acc.spendable_at(height)).unwrap_or(0)
+        };
+        
+        // Every transaction, the coinbase included, must be for this network
         for tx in &block.transactions {
+            self.check_chain_id(tx)?;
+        }
+        
+        // The coinbase may mint at most the scheduled reward plus the block's fees
+        if let Some(coinbase) = block.coinbase() {
+            let allowed = block.total_fees()
+                .and_then(|fees| fees.checked_add(self.rewards.reward_at(block.header.number)))
+                .ok_or_else(|| ElysiumError::InvalidBlock("Block fees overflow".to_string()))?;
+            if coinbase.amount > allowed {
+                return Err(ElysiumError::InvalidBlock(
+                    format!("Coinbase pays {}, but the reward plus fees is {}", coinbase.amount, allowed)
+                ));
+            }
+            let receiver_balance = spendable(&coinbase.to);
+            balances.insert(&coinbase.to, receiver_balance.saturating_add(coinbase.amount));
+        }
+        
+        // Validate all transfers in the block
+        for tx in block.transactions.iter().filter(|tx| !tx.is_coinbase()) {
             // Verify signature
             tx.verify()?;
             
-            // Check balance
-            let balance = self.get_balance(&tx.from);
-            if balance < tx.amount {
+            // Check co-signatures against multisig policies, including ones created earlier in the block
+            let policy_of = |address: &Address| {
+                created.get(address).copied().or_else(|| self.multisig_policy(address))
+            };
+            check_multisig(tx, policy_of(&tx.from), policy_of(&tx.to))?;
+            if let TransactionType::CreateMultisig(policy) = &tx.transaction_type {
+                created.insert(&tx.to, policy);
+            }
+            
+            // Deploys and calls must be well formed and fit in the block's gas limit
+            contract::check_contract_transaction(tx)?;
+            gas = gas.saturating_add(tx.gas_limit());
+            if gas > MAX_BLOCK_GAS {
+                return Err(ElysiumError::InvalidBlock(
+                    format!("Block reserves more than {} gas", MAX_BLOCK_GAS)
+                ));
+            }
+            
+            // Check balance covers the amount, the fee and the whole gas limit
+            let balance = *balances.entry(&tx.from)
+                .or_insert_with(|| spendable(&tx.from));
+            let cost = tx.total_cost().ok_or(ElysiumError::InsufficientBalance)?;
+            if balance < cost {
                 return Err(ElysiumError::InsufficientBalance);
             }
             
             // Check nonce
-            let nonce = self.get_nonce(&tx.from);
+            let nonce = *nonces.entry(&tx.from)
+                .or_insert_with(|| self.get_nonce(&tx.from));
             if tx.nonce != nonce {
-                return Err(ElysiumError::InvalidTransaction(
-                    format!("Invalid nonce: expected {}, got {}", nonce, tx.nonce)
-                ));
+                return Err(ElysiumError::InvalidNonce { expected: nonce, got: tx.nonce });
             }
+            
+            balances.insert(&tx.from, balance - cost);
+            nonces.insert(&tx.from, nonce + 1);
+            // Time-locked amounts cannot be spent later in this block, and amounts sent to
+            // contracts depend on the execution, which only runs when the block is applied
+            let received = match (&tx.transaction_type, tx.unlock_height()) {
+                (_, Some(unlock_height)) if unlock_height > height => 0,
+                (TransactionType::Deploy { .. } | TransactionType::Call { .. }, _) => 0,
+                _ => tx.amount,
+            };
+            let receiver_balance = *balances.entry(&tx.to)
+                .or_insert_with(|| spendable(&tx.to));
+            balances.insert(&tx.to, receiver_balance.saturating_add(received));
         }
         
         // Check for duplicate transactions in the block
@@ -229,12 +1051,6 @@ impl Blockchain {
             }
         }
         
-        // Apply transactions to state
-        self.apply_transactions(&block.transactions)?;
-        
-        // Add block
-        self.blocks.push(block);
-        
         Ok(())
     }
     
@@ -244,6 +1060,102 @@ impl Blockchain {
     }
 }
 
+/// Check a transaction against the multisig policies of its sender and receiver
+///
+/// A multisig account can only send multisig transfers carrying enough co-signatures,
+/// and an account can only be made a multisig account once, at its policy's address.
+fn check_multisig(
+    tx: &Transaction,
+    sender_policy: Option<&MultisigPolicy>,
+    receiver_policy: Option<&MultisigPolicy>,
+) -> Result<()> {
+    if let TransactionType::MultisigTransfer = tx.transaction_type {
+        let policy = sender_policy.ok_or_else(|| ElysiumError::InvalidTransaction(
+            "Sender is not a multisig account".to_string()
+        ))?;
+        return policy.authorize(tx);
+    }
+    if sender_policy.is_some() {
+        return Err(ElysiumError::InvalidTransaction(
+            "Multisig accounts can only send multisig transfers".to_string()
+        ));
+    }
+    if let TransactionType::CreateMultisig(policy) = &tx.transaction_type {
+        policy.validate()?;
+        if tx.to != policy.address() {
+            return Err(ElysiumError::InvalidTransaction(
+                "Multisig account address does not match its policy".to_string()
+            ));
+        }
+        if receiver_policy.is_some() {
+            return Err(ElysiumError::InvalidTransaction(
+                "Multisig account already exists".to_string()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Apply the transactions of the block at `height` to a set of accounts, returning the
+/// outcome of each deploy and call (`None` for other transactions)
+///
+/// Deploys and calls run first, against the state before the transaction. If the
+/// execution fails, the amount stays with the sender and no code or storage changes,
+/// but the fee and the gas used are still charged.
+fn apply_to_accounts(
+    accounts: &mut HashMap<Address, Account>,
+    transactions: &[Transaction],
+    height: u64,
+) -> Vec<Option<ExecutionStatus>> {
+    let mut executions = Vec::with_capacity(transactions.len());
+    for tx in transactions {
+        let outcome = contract::execute(accounts, tx, height);
+        executions.push(outcome.as_ref().map(|outcome| ExecutionStatus {
+            success: outcome.is_success(),
+            gas_used: outcome.gas_used,
+        }));
+        let succeeded = outcome.as_ref().is_none_or(|outcome| outcome.is_success());
+        let amount = if succeeded { tx.amount } else { 0 };
+        
+        // Update sender account; a coinbase mints its amount instead.
+        // Fees leave the sender here and reach the miner through the coinbase; gas is burned
+        if !tx.is_coinbase() {
+            let gas_cost = outcome.as_ref()
+                .map(|outcome| outcome.gas_used.saturating_mul(tx.gas_price()))
+                .unwrap_or(0);
+            let sender = accounts.entry(tx.from.clone())
+                .or_insert_with(|| Account::new(tx.from.clone()));
+            sender.balance = sender.balance.saturating_sub(amount.saturating_add(tx.fee).saturating_add(gas_cost));
+            sender.nonce += 1;
+            sender.locks.retain(|lock| lock.unlock_height > height);
+        }
+        
+        // Update receiver account
+        let receiver = accounts.entry(tx.to.clone())
+            .or_insert_with(|| Account::new(tx.to.clone()));
+        receiver.balance = receiver.balance.saturating_add(amount);
+        receiver.locks.retain(|lock| lock.unlock_height > height);
+        match &tx.transaction_type {
+            TransactionType::TimeLocked { unlock_height } if *unlock_height > height && tx.amount > 0 => {
+                receiver.locks.push(TimeLock { amount: tx.amount, unlock_height: *unlock_height });
+            }
+            TransactionType::CreateMultisig(policy) => receiver.multisig = Some(policy.clone()),
+            TransactionType::Deploy { code, .. } if succeeded => receiver.code = code.clone(),
+            _ => {}
+        }
+        
+        // Storing 0 clears a key, so storage never holds zeros
+        for (key, value) in outcome.map(|outcome| outcome.writes).unwrap_or_default() {
+            if value == 0 {
+                receiver.storage.remove(&key);
+            } else {
+                receiver.storage.insert(key, value);
+            }
+        }
+    }
+    executions
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,5 +1234,745 @@ mod tests {
         assert_eq!(block.transactions.len(), 1);
         assert_eq!(blockchain.get_balance(&keypair2.address()), 50);
     }
+    
+    #[test]
+    fn test_transaction_proof() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut blockchain = funded_chain(&alice);
+        let block = mine_transfer(&mut blockchain, &alice, &bob.address(), 10);
+        mine_transfer(&mut blockchain, &alice, &bob.address(), 10);
+        
+        let tx_hash = block.transactions[0].hash();
+        let proof = blockchain.get_transaction_proof(&tx_hash).unwrap();
+        assert!(proof.verify());
+        assert_eq!(proof.header.number, 1);
+        assert!(blockchain.get_transaction_proof(&"0".repeat(64)).is_none());
+    }
+    
+    #[test]
+    fn test_state_root_is_committed_and_checked() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        let block = mine_transfer(&mut chain_a, &alice, &bob.address(), 10);
+        assert_eq!(block.header.state_root, chain_a.state_root());
+        
+        let proof = chain_a.get_account_proof(&bob.address());
+        assert!(proof.verify());
+        assert_eq!(proof.proof.account.unwrap().balance, 10);
+        
+        // A block committing to a different state is rejected
+        let mut forged = block.clone();
+        forged.header.state_root = chain_b.state_root();
+        forged.mine();
+        assert!(chain_b.add_block(forged).is_err());
+        assert_eq!(chain_b.height(), 1);
+        
+        chain_b.add_block(block).unwrap();
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+    }
+    
+    #[test]
+    fn test_reopen_from_storage() {
+        let storage = crate::storage::MemoryStorage::new();
+        let mut blockchain = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        let keypair1 = KeyPair::generate();
+        let keypair2 = KeyPair::generate();
+        
+        // Give keypair1 some balance
+        let mut account = Account::new(keypair1.address());
+        account.balance = 100;
+        blockchain.accounts.insert(keypair1.address(), account);
+        
+        let mut tx = Transaction::new(
+            keypair1.address(),
+            keypair2.address(),
+            50,
+            0,
+        );
+        tx.sign(keypair1.signing_key()).unwrap();
+        blockchain.add_transaction(tx).unwrap();
+        blockchain.mine_block().unwrap();
+        
+        let reopened = Blockchain::open(1, Arc::new(storage)).unwrap();
+        assert_eq!(reopened.height(), 2);
+        assert_eq!(reopened.latest_block().hash(), blockchain.latest_block().hash());
+        assert_eq!(reopened.get_balance(&keypair1.address()), 50);
+        assert_eq!(reopened.get_balance(&keypair2.address()), 50);
+        assert_eq!(reopened.get_nonce(&keypair1.address()), 1);
+    }
+    
+    #[test]
+    fn test_reopen_rejects_tampered_chain() {
+        let storage = crate::storage::MemoryStorage::new();
+        let blockchain = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        
+        // A block that does not link to the stored genesis
+        let mut orphan = Block::new(1, "f".repeat(64), 1);
+        orphan.mine();
+        storage.append_block(&orphan).unwrap();
+        
+        assert_eq!(blockchain.height(), 1);
+        assert!(Blockchain::open(1, Arc::new(storage)).is_err());
+    }
+    
+    /// A chain where `funded` holds 1000 coins
+    fn funded_chain(funded: &KeyPair) -> Blockchain {
+        let mut blockchain = Blockchain::new(1);
+        let mut account = Account::new(funded.address());
+        account.balance = 1000;
+        blockchain.accounts.insert(funded.address(), account);
+        blockchain
+    }
+    
+    /// Add a signed transfer and mine it into a block
+    fn mine_transfer(blockchain: &mut Blockchain, from: &KeyPair, to: &Address, amount: u64) -> Block {
+        let nonce = blockchain.get_nonce(&from.address());
+        let mut tx = Transaction::new(from.address(), to.clone(), amount, nonce)
+            .with_chain_id(blockchain.chain_id());
+        from.sign_transaction(&mut tx).unwrap();
+        blockchain.add_transaction(tx).unwrap();
+        blockchain.mine_block().unwrap()
+    }
+    
+    #[test]
+    fn test_reorganize_to_branch_with_more_work() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        // Both chains mine a block at height 1; A sees B's block as a side branch
+        let orphaned = mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        let fork_1 = mine_transfer(&mut chain_b, &alice, &carol.address(), 10);
+        let fork_2 = mine_transfer(&mut chain_b, &alice, &carol.address(), 20);
+        
+        chain_a.add_block(fork_1.clone()).unwrap();
+        assert_eq!(chain_a.latest_block().hash(), orphaned.hash());
+        assert!(chain_a.contains_block(&fork_1.hash()));
+        
+        // The second block gives B's branch more work and triggers the reorganization
+        chain_a.add_block(fork_2.clone()).unwrap();
+        assert_eq!(chain_a.height(), 3);
+        assert_eq!(chain_a.latest_block().hash(), fork_2.hash());
+        assert_eq!(chain_a.get_balance(&bob.address()), 0);
+        assert_eq!(chain_a.get_balance(&carol.address()), 30);
+        assert_eq!(chain_a.get_balance(&alice.address()), 970);
+        assert_eq!(chain_a.get_nonce(&alice.address()), 2);
+        
+        // Alice's nonce moved past the orphaned transfer, so it cannot return to the pool
+        assert!(chain_a.mempool.is_empty());
+    }
+    
+    #[test]
+    fn test_reorganize_returns_orphaned_transactions() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut account = Account::new(carol.address());
+        account.balance = 1000;
+        chain_a.accounts.insert(carol.address(), account);
+        let mut chain_b = chain_a.clone();
+        
+        let orphaned = mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        let fork_1 = mine_transfer(&mut chain_b, &carol, &bob.address(), 1);
+        let fork_2 = mine_transfer(&mut chain_b, &carol, &bob.address(), 2);
+        
+        chain_a.add_block(fork_1).unwrap();
+        chain_a.add_block(fork_2).unwrap();
+        
+        assert_eq!(chain_a.get_balance(&alice.address()), 1000);
+        assert_eq!(chain_a.get_balance(&bob.address()), 3);
+        assert_eq!(chain_a.mempool.len(), 1);
+        assert!(chain_a.mempool.contains(&orphaned.transactions[0].hash()));
+        
+        // The returned transaction can be mined on the new chain
+        let block = chain_a.mine_block().unwrap();
+        assert_eq!(block.header.parent_hash, chain_b.latest_block().hash());
+        assert_eq!(chain_a.get_balance(&bob.address()), 103);
+    }
+    
+    #[test]
+    fn test_index_follows_reorganization() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut account = Account::new(carol.address());
+        account.balance = 1000;
+        chain_a.accounts.insert(carol.address(), account);
+        let mut chain_b = chain_a.clone();
+        
+        let orphaned = mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        let orphaned_tx = orphaned.transactions[0].hash();
+        assert_eq!(chain_a.block_number(&orphaned.hash()), Some(1));
+        assert_eq!(chain_a.get_transaction_receipt(&orphaned_tx).unwrap().confirmations, 1);
+        assert_eq!(chain_a.get_address_history(&bob.address(), 0, 10).total, 1);
+        
+        let fork_1 = mine_transfer(&mut chain_b, &carol, &bob.address(), 1);
+        let fork_2 = mine_transfer(&mut chain_b, &carol, &bob.address(), 2);
+        chain_a.add_block(fork_1.clone()).unwrap();
+        chain_a.add_block(fork_2.clone()).unwrap();
+        
+        // The orphaned block is unindexed and its transaction is pending again
+        assert_eq!(chain_a.block_number(&orphaned.hash()), None);
+        assert_eq!(chain_a.block_number(&fork_2.hash()), Some(2));
+        assert!(chain_a.get_transaction_receipt(&orphaned_tx).is_none());
+        assert!(chain_a.get_transaction(&orphaned_tx).unwrap().location.is_none());
+        assert_eq!(chain_a.get_address_history(&alice.address(), 0, 10).total, 0);
+        
+        let history = chain_a.get_address_history(&bob.address(), 0, 10);
+        assert_eq!(history.total, 2);
+        assert_eq!(history.transactions[0].transaction.amount, 2);
+        let location = history.transactions[1].location.as_ref().unwrap();
+        assert_eq!((location.block_number, location.index), (1, 0));
+        assert_eq!(location.block_hash, fork_1.hash());
+        
+        let receipt = chain_a.get_transaction_receipt(&fork_1.transactions[0].hash()).unwrap();
+        assert_eq!(receipt.confirmations, 2);
+        assert!(receipt.success);
+        assert!(chain_a.get_transaction_proof(&orphaned_tx).is_none());
+    }
+    
+    #[test]
+    fn test_equal_work_keeps_first_seen_chain() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        let first = mine_transfer(&mut chain_a, &alice, &bob.address(), 1);
+        let competing = mine_transfer(&mut chain_b, &alice, &bob.address(), 2);
+        
+        chain_a.add_block(competing.clone()).unwrap();
+        assert_eq!(chain_a.latest_block().hash(), first.hash());
+        assert_eq!(chain_a.get_balance(&bob.address()), 1);
+        assert!(chain_a.add_block(competing).is_err()); // Already known
+    }
+    
+    #[test]
+    fn test_invalid_branch_is_rejected_and_chain_restored() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        let kept = mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        
+        // B's branch spends coins that only exist on B
+        let mallory = KeyPair::generate();
+        let mut account = Account::new(mallory.address());
+        account.balance = 500;
+        chain_b.accounts.insert(mallory.address(), account);
+        let fork_1 = mine_transfer(&mut chain_b, &mallory, &bob.address(), 10);
+        let fork_2 = mine_transfer(&mut chain_b, &mallory, &bob.address(), 10);
+        
+        chain_a.add_block(fork_1.clone()).unwrap();
+        assert!(chain_a.add_block(fork_2.clone()).is_err());
+        
+        assert_eq!(chain_a.latest_block().hash(), kept.hash());
+        assert_eq!(chain_a.get_balance(&bob.address()), 100);
+        assert_eq!(chain_a.get_balance(&alice.address()), 900);
+        assert!(!chain_a.contains_block(&fork_1.hash()));
+        assert!(!chain_a.contains_block(&fork_2.hash()));
+    }
+    
+    #[test]
+    fn test_reopen_after_reorganization() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let storage = crate::storage::MemoryStorage::new();
+        let mut chain_a = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        let mut account = Account::new(alice.address());
+        account.balance = 1000;
+        chain_a.accounts.insert(alice.address(), account);
+        let mut chain_b = chain_a.clone();
+        chain_b.storage = None;
+        
+        mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        let fork_1 = mine_transfer(&mut chain_b, &alice, &carol.address(), 10);
+        let fork_2 = mine_transfer(&mut chain_b, &alice, &carol.address(), 20);
+        chain_a.add_block(fork_1).unwrap();
+        chain_a.add_block(fork_2.clone()).unwrap();
+        
+        let reopened = Blockchain::open(1, Arc::new(storage)).unwrap();
+        assert_eq!(reopened.height(), 3);
+        assert_eq!(reopened.latest_block().hash(), fork_2.hash());
+        assert_eq!(reopened.get_balance(&carol.address()), 30);
+        assert_eq!(reopened.get_balance(&bob.address()), 0);
+        assert_eq!(reopened.tip_work(), chain_a.tip_work());
+    }
+    
+    #[test]
+    fn test_block_with_wrong_target_is_rejected() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        let mut block = mine_transfer(&mut chain_b, &alice, &bob.address(), 10);
+        block.header.bits = pow::bits_for_difficulty(2);
+        block.mine();
+        let err = chain_a.add_block(block).unwrap_err();
+        assert!(err.to_string().contains("Invalid target"));
+        
+        let mut block = chain_b.latest_block().clone();
+        block.header.timestamp += 10 * MAX_FUTURE_BLOCK_TIME;
+        block.mine();
+        let err = chain_a.add_block(block).unwrap_err();
+        assert!(err.to_string().contains("future"));
+        assert_eq!(chain_a.height(), 1);
+    }
+    
+    #[test]
+    fn test_target_retargets_at_window_boundary() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        chain_a.retarget = RetargetConfig { target_block_time: 10, window: 4 };
+        let mut chain_b = chain_a.clone();
+        
+        // Blocks mined back to back are far faster than the target, so difficulty
+        // rises by the maximum factor at each window boundary
+        for _ in 0..3 {
+            let block = mine_transfer(&mut chain_b, &alice, &bob.address(), 1);
+            assert_eq!(block.header.difficulty(), 1);
+            chain_a.add_block(block).unwrap();
+        }
+        let block = mine_transfer(&mut chain_b, &alice, &bob.address(), 1);
+        assert_eq!(block.header.difficulty(), pow::MAX_ADJUSTMENT_FACTOR);
+        assert_eq!(chain_a.current_difficulty(), pow::MAX_ADJUSTMENT_FACTOR);
+        chain_a.add_block(block).unwrap();
+        
+        // Within the window the target is unchanged
+        let block = mine_transfer(&mut chain_b, &alice, &bob.address(), 1);
+        assert_eq!(block.header.difficulty(), pow::MAX_ADJUSTMENT_FACTOR);
+        chain_a.add_block(block).unwrap();
+        assert!(chain_a.estimated_hashrate(4) > 0);
+    }
+    
+    fn genesis_spec(funded: &KeyPair) -> GenesisSpec {
+        let mut spec = GenesisSpec {
+            chain_id: 7,
+            timestamp: 1_700_000_000,
+            difficulty: 1,
+            alloc: Default::default(),
+            consensus: Default::default(),
+            retarget: Default::default(),
+        };
+        spec.alloc.insert(funded.address().as_hex().to_string(), 1000);
+        spec
+    }
+    
+    #[test]
+    fn test_nodes_share_genesis_from_spec() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let spec = genesis_spec(&alice);
+        let mut chain_a = Blockchain::from_genesis(&spec).unwrap();
+        let mut chain_b = Blockchain::from_genesis(&spec).unwrap();
+        
+        assert_eq!(chain_a.latest_block().hash(), chain_b.latest_block().hash());
+        assert_eq!(chain_a.chain_id(), 7);
+        assert_eq!(chain_a.get_balance(&alice.address()), 1000);
+        assert_eq!(chain_a.state_root(), chain_a.latest_block().header.state_root);
+        
+        // Blocks mined on one node connect on the other
+        let block = mine_transfer(&mut chain_a, &alice, &bob.address(), 100);
+        chain_b.add_block(block).unwrap();
+        assert_eq!(chain_b.get_balance(&bob.address()), 100);
+    }
+    
+    #[test]
+    fn test_proof_of_authority_chain() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut spec = genesis_spec(&alice);
+        spec.consensus = crate::ConsensusSpec::ProofOfAuthority { validators: vec![alice.address(), bob.address()] };
+        let storage = crate::storage::MemoryStorage::new();
+        let mut chain_a = Blockchain::open_genesis(&spec, Arc::new(storage.clone())).unwrap();
+        let mut chain_b = Blockchain::from_genesis(&spec).unwrap();
+        assert_eq!(chain_a.consensus().name(), "proof_of_authority");
+        
+        // Block 1 is bob's slot
+        let mut tx = Transaction::new(alice.address(), bob.address(), 100, 0).with_chain_id(7);
+        alice.sign_transaction(&mut tx).unwrap();
+        chain_a.add_transaction(tx).unwrap();
+        assert!(chain_a.mine_block().is_err());
+        chain_a.validator_key = Some(alice.clone());
+        assert!(chain_a.mine_block().is_err());
+        
+        // Neither a proof-of-work seal nor another validator's signature is accepted
+        let mut template = chain_a.mining_template().unwrap();
+        template.mine();
+        assert!(chain_b.add_block(template.clone()).is_err());
+        template.header.public_key = hex::encode(alice.verifying_key().to_bytes());
+        template.header.signature = hex::encode(alice.sign(&template.header.signing_hash_bytes()).to_bytes());
+        assert!(chain_b.add_block(template).is_err());
+        
+        chain_a.validator_key = Some(bob.clone());
+        let block = chain_a.mine_block().unwrap();
+        chain_b.add_block(block.clone()).unwrap();
+        assert_eq!(chain_b.get_balance(&bob.address()), 100);
+        assert_eq!(block.header.bits, spec.genesis_block().header.bits);
+        
+        let reopened = Blockchain::open_genesis(&spec, Arc::new(storage.clone())).unwrap();
+        assert_eq!(reopened.latest_block().hash(), block.hash());
+        
+        // The engine is recorded with the chain, so the spec is not needed to reopen it
+        let reopened = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        assert_eq!(reopened.consensus().name(), "proof_of_authority");
+        assert_eq!(reopened.latest_block().hash(), block.hash());
+        let mut other_validators = spec.clone();
+        other_validators.consensus = crate::ConsensusSpec::ProofOfAuthority { validators: vec![bob.address()] };
+        assert!(Blockchain::open_genesis(&other_validators, Arc::new(storage)).is_err());
+    }
+    
+    #[test]
+    fn test_transactions_from_other_chains_are_rejected() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let spec = genesis_spec(&alice);
+        let mut chain_a = Blockchain::from_genesis(&spec).unwrap();
+        let mut other_spec = spec.clone();
+        other_spec.chain_id = 8;
+        let mut chain_b = Blockchain::from_genesis(&other_spec).unwrap();
+        
+        // A transfer signed for chain 8 cannot be submitted on chain 7
+        let mut tx = Transaction::new(alice.address(), bob.address(), 100, 0).with_chain_id(8);
+        alice.sign_transaction(&mut tx).unwrap();
+        let err = chain_a.add_transaction(tx.clone()).unwrap_err();
+        assert!(err.to_string().contains("Wrong chain id"));
+        
+        // Nor replayed inside a block
+        chain_b.add_transaction(tx).unwrap();
+        let mut block = chain_b.mine_block().unwrap();
+        block.header.parent_hash = chain_a.latest_block().hash();
+        block.header.bits = chain_a.latest_block().header.bits;
+        block.mine();
+        let err = chain_a.add_block(block).unwrap_err();
+        assert!(err.to_string().contains("Wrong chain id"));
+        assert_eq!(chain_a.get_balance(&bob.address()), 0);
+    }
+    
+    #[test]
+    fn test_reopen_from_genesis_spec() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let spec = genesis_spec(&alice);
+        let storage = crate::storage::MemoryStorage::new();
+        let mut blockchain = Blockchain::open_genesis(&spec, Arc::new(storage.clone())).unwrap();
+        mine_transfer(&mut blockchain, &alice, &bob.address(), 100);
+        
+        let reopened = Blockchain::open_genesis(&spec, Arc::new(storage.clone())).unwrap();
+        assert_eq!(reopened.latest_block().hash(), blockchain.latest_block().hash());
+        assert_eq!(reopened.get_balance(&alice.address()), 900);
+        
+        // A different spec does not match the stored genesis
+        let mut other = spec.clone();
+        other.chain_id = 8;
+        assert!(Blockchain::open_genesis(&other, Arc::new(storage)).is_err());
+    }
+    
+    #[test]
+    fn test_retarget_rules_come_from_spec() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut spec = genesis_spec(&alice);
+        spec.retarget = RetargetConfig { target_block_time: 10, window: 4 };
+        let storage = crate::storage::MemoryStorage::new();
+        let mut chain_a = Blockchain::open_genesis(&spec, Arc::new(storage.clone())).unwrap();
+        let mut chain_b = Blockchain::from_genesis(&spec).unwrap();
+        
+        // Both nodes expect the same target for every block. The first window spans the
+        // time since the genesis timestamp and leaves the target at the maximum; the
+        // second one is harder because its blocks came back to back.
+        for height in 1..=9 {
+            let tip = chain_b.latest_block().hash();
+            assert_eq!(chain_a.next_bits(&tip), chain_b.next_bits(&tip));
+            let block = mine_transfer(&mut chain_b, &alice, &bob.address(), 1);
+            let expected = if height < 8 { 1 } else { pow::MAX_ADJUSTMENT_FACTOR };
+            assert_eq!(block.header.difficulty(), expected);
+            chain_a.add_block(block).unwrap();
+        }
+        assert_eq!(chain_a.latest_block().hash(), chain_b.latest_block().hash());
+        
+        // The rules are recorded with the chain, so the spec is not needed to reopen it
+        let reopened = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        assert_eq!(reopened.retarget, spec.retarget);
+        assert_eq!(reopened.latest_block().hash(), chain_a.latest_block().hash());
+        let mut other_rules = spec.clone();
+        other_rules.retarget = RetargetConfig::default();
+        assert!(Blockchain::open_genesis(&other_rules, Arc::new(storage)).is_err());
+    }
+    
+    #[test]
+    fn test_coinbase_pays_reward_and_fees() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let miner = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        chain_a.miner_address = Some(miner.address());
+        let mut chain_b = chain_a.clone();
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 100, 0).with_fee(7);
+        tx.sign(alice.signing_key()).unwrap();
+        chain_a.add_transaction(tx).unwrap();
+        let block = chain_a.mine_block().unwrap();
+        
+        let reward = chain_a.rewards.reward_at(1);
+        assert_eq!(block.coinbase().unwrap().amount, reward + 7);
+        assert_eq!(chain_a.get_balance(&alice.address()), 893);
+        assert_eq!(chain_a.get_balance(&bob.address()), 100);
+        assert_eq!(chain_a.get_balance(&miner.address()), reward + 7);
+        
+        // Other nodes accept the block and reach the same state
+        chain_b.add_block(block).unwrap();
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+        
+        // Coinbase transactions cannot enter the pool
+        let coinbase = Transaction::coinbase(bob.address(), 1, 2);
+        assert!(chain_a.add_transaction(coinbase).is_err());
+        
+        // Fees count against the balance
+        let mut tx = Transaction::new(alice.address(), bob.address(), 893, 1).with_fee(1);
+        tx.sign(alice.signing_key()).unwrap();
+        assert!(matches!(chain_a.add_transaction(tx), Err(ElysiumError::InsufficientBalance)));
+    }
+    
+    #[test]
+    fn test_coinbase_above_schedule_is_rejected() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let miner = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        chain_b.miner_address = Some(miner.address());
+        chain_b.rewards.initial_reward = 1_000;
+        
+        let block = mine_transfer(&mut chain_b, &alice, &bob.address(), 10);
+        let err = chain_a.add_block(block).unwrap_err();
+        assert!(err.to_string().contains("Coinbase pays"));
+        
+        // A coinbase anywhere but first is malformed
+        let mut block = Block::new(1, chain_a.latest_block().hash(), 1);
+        let mut tx = Transaction::new(alice.address(), bob.address(), 10, 0);
+        tx.sign(alice.signing_key()).unwrap();
+        block.add_transaction(tx);
+        block.add_transaction(Transaction::coinbase(miner.address(), 1, 1));
+        block.mine();
+        assert!(block.validate().is_err());
+    }
+    
+    #[test]
+    fn test_multisig_account_requires_cosignatures() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let dave = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        let policy = MultisigPolicy::new(2, vec![alice.address(), bob.address(), carol.address()]).unwrap();
+        let treasury = policy.address();
+        
+        let mut create = Transaction::create_multisig(alice.address(), policy.clone(), 300, 0);
+        alice.sign_transaction(&mut create).unwrap();
+        chain_a.add_transaction(create).unwrap();
+        chain_b.add_block(chain_a.mine_block().unwrap()).unwrap();
+        assert_eq!(chain_a.multisig_policy(&treasury), Some(&policy));
+        assert_eq!(chain_a.get_balance(&treasury), 300);
+        
+        // The account exists only once
+        let mut again = Transaction::create_multisig(alice.address(), policy.clone(), 1, 1);
+        alice.sign_transaction(&mut again).unwrap();
+        let err = chain_a.add_transaction(again).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        
+        // One co-signature is not enough, and outsiders do not count
+        let mut spend = Transaction::multisig_transfer(&policy, dave.address(), 100, 0).with_fee(2);
+        spend.cosign(bob.signing_key());
+        spend.cosign(dave.signing_key());
+        assert!(chain_a.add_transaction(spend.clone()).is_err());
+        
+        // Nor can a signer spend alone through a plain transfer
+        let mut plain = Transaction::new(treasury.clone(), dave.address(), 100, 0);
+        plain.sign(bob.signing_key()).unwrap();
+        assert!(chain_a.add_transaction(plain).is_err());
+        
+        spend.cosignatures.pop();
+        spend.cosign(carol.signing_key());
+        chain_a.add_transaction(spend).unwrap();
+        let block = chain_a.mine_block().unwrap();
+        assert_eq!(chain_a.get_balance(&treasury), 198);
+        assert_eq!(chain_a.get_balance(&dave.address()), 100);
+        assert_eq!(chain_a.get_nonce(&treasury), 1);
+        
+        // Other nodes check the co-signatures too
+        chain_b.add_block(block).unwrap();
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+    }
+    
+    #[test]
+    fn test_time_locked_transfer() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        let mut chain_b = chain_a.clone();
+        
+        // Locked until the block at height 3
+        let mut tx = Transaction::time_locked(alice.address(), bob.address(), 100, 0, 3);
+        alice.sign_transaction(&mut tx).unwrap();
+        chain_a.add_transaction(tx).unwrap();
+        chain_b.add_block(chain_a.mine_block().unwrap()).unwrap();
+        assert_eq!(chain_a.get_balance(&bob.address()), 100);
+        assert_eq!(chain_a.get_spendable_balance(&bob.address()), 0);
+        
+        let mut early = Transaction::new(bob.address(), carol.address(), 10, 0);
+        bob.sign_transaction(&mut early).unwrap();
+        assert!(matches!(chain_a.add_transaction(early.clone()), Err(ElysiumError::InsufficientBalance)));
+        
+        // A block spending the locked amount early is rejected
+        let mut premature = chain_a.clone();
+        let height = premature.height();
+        premature.mempool.insert(early, &premature.accounts.clone(), u64::MAX).unwrap();
+        let mut block = premature.mining_template().unwrap();
+        block.mine();
+        assert_eq!(block.header.number, height);
+        assert!(matches!(chain_a.add_block(block), Err(ElysiumError::InsufficientBalance)));
+        
+        // From the unlock height on, the amount is spendable
+        chain_b.add_block(mine_transfer(&mut chain_a, &alice, &carol.address(), 1)).unwrap();
+        assert_eq!(chain_a.get_spendable_balance(&bob.address()), 100);
+        chain_b.add_block(mine_transfer(&mut chain_a, &bob, &carol.address(), 10)).unwrap();
+        assert_eq!(chain_a.get_balance(&carol.address()), 11);
+        assert!(chain_a.accounts[&bob.address()].locks.is_empty());
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+    }
+    
+    fn mine_contract_tx(blockchain: &mut Blockchain, from: &KeyPair, mut tx: Transaction) -> Block {
+        tx.chain_id = blockchain.chain_id();
+        from.sign_transaction(&mut tx).unwrap();
+        blockchain.add_transaction(tx).unwrap();
+        blockchain.mine_block().unwrap()
+    }
+    
+    #[test]
+    fn test_deploy_and_call_contract() {
+        use elysium_vm::{Opcode, Program};
+        
+        let alice = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        chain_a.accounts.get_mut(&alice.address()).unwrap().balance = 10_000;
+        let mut chain_b = chain_a.clone();
+        
+        // storage[0] += input[0], returning the new total
+        let code = Program::new()
+            .push(0).op(Opcode::Input).push(0).op(Opcode::SLoad).op(Opcode::Add)
+            .op(Opcode::Dup).push(0).op(Opcode::SStore)
+            .push(1).op(Opcode::Return)
+            .build();
+        let deploy = Transaction::deploy(alice.address(), &code, 0, 2_000, 1);
+        let contract = deploy.to.clone();
+        chain_b.add_block(mine_contract_tx(&mut chain_a, &alice, deploy)).unwrap();
+        assert_eq!(chain_a.get_code(&contract), hex::encode(&code));
+        assert_eq!(chain_a.get_balance(&alice.address()), 10_000 - contract::deploy_gas(code.len()));
+        
+        // Calls are paid for with the gas they use, and the amount reaches the contract
+        let call = Transaction::call(alice.address(), contract.clone(), 3, vec![5], 1, 1_000, 2);
+        chain_b.add_block(mine_contract_tx(&mut chain_a, &alice, call)).unwrap();
+        let account = &chain_a.accounts[&contract];
+        assert_eq!(account.storage, std::collections::BTreeMap::from([(0, 5)]));
+        assert_eq!(account.balance, 3);
+        let gas_used = 10_000 - contract::deploy_gas(code.len()) - 3 - chain_a.get_balance(&alice.address());
+        assert!(gas_used > 0 && gas_used.is_multiple_of(2) && gas_used < 2_000);
+        
+        // Peers replaying the blocks reach the same state
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+        assert_eq!(chain_b.accounts[&contract].storage, account.storage);
+        
+        // Read-only calls see the state without changing it
+        let result = chain_a.call_contract(&contract, vec![4], 1_000).unwrap();
+        assert_eq!(result.output, vec![9]);
+        assert_eq!(chain_a.accounts[&contract].storage[&0], 5);
+        assert!(matches!(chain_a.call_contract(&alice.address(), Vec::new(), 1_000), Err(ElysiumError::ContractError(_))));
+        
+        // Calls to accounts without code are refused
+        let mut call = Transaction::call(alice.address(), KeyPair::generate().address(), 0, Vec::new(), 2, 1_000, 1);
+        alice.sign_transaction(&mut call).unwrap();
+        assert!(matches!(chain_a.add_transaction(call), Err(ElysiumError::ContractError(_))));
+    }
+    
+    #[test]
+    fn test_failed_call_charges_gas() {
+        use elysium_vm::{Opcode, Program};
+        
+        let alice = KeyPair::generate();
+        let mut chain_a = funded_chain(&alice);
+        chain_a.accounts.get_mut(&alice.address()).unwrap().balance = 10_000;
+        let mut chain_b = chain_a.clone();
+        
+        // Write to storage, then revert
+        let code = Program::new().push(1).push(1).op(Opcode::SStore).op(Opcode::Revert).build();
+        let deploy = Transaction::deploy(alice.address(), &code, 0, 2_000, 0);
+        let contract = deploy.to.clone();
+        chain_b.add_block(mine_contract_tx(&mut chain_a, &alice, deploy)).unwrap();
+        
+        // A revert only charges the gas used, and keeps the amount and storage unchanged
+        let call = Transaction::call(alice.address(), contract.clone(), 50, Vec::new(), 1, 1_000, 1).with_fee(2);
+        chain_b.add_block(mine_contract_tx(&mut chain_a, &alice, call)).unwrap();
+        let gas_used = 1 + 1 + 200 + 1;
+        assert_eq!(chain_a.get_balance(&alice.address()), 10_000 - 2 - gas_used);
+        assert_eq!(chain_a.get_balance(&contract), 0);
+        assert!(chain_a.accounts[&contract].storage.is_empty());
+        assert_eq!(chain_a.get_nonce(&alice.address()), 2);
+        
+        // Running out of gas charges the whole limit
+        let call = Transaction::call(alice.address(), contract.clone(), 50, Vec::new(), 2, 100, 1);
+        chain_b.add_block(mine_contract_tx(&mut chain_a, &alice, call)).unwrap();
+        assert_eq!(chain_a.get_balance(&alice.address()), 10_000 - 2 - gas_used - 100);
+        assert_eq!(chain_b.state_root(), chain_a.state_root());
+        
+        // The sender must be able to pay for the whole gas limit up front
+        let mut call = Transaction::call(alice.address(), contract, 0, Vec::new(), 3, 10_000, 1);
+        alice.sign_transaction(&mut call).unwrap();
+        assert!(matches!(chain_a.add_transaction(call), Err(ElysiumError::InsufficientBalance)));
+    }
+    
+    #[test]
+    fn test_receipts_survive_reopen() {
+        use elysium_vm::{Opcode, Program};
+        
+        let alice = KeyPair::generate();
+        let storage = crate::storage::MemoryStorage::new();
+        let mut blockchain = Blockchain::open(1, Arc::new(storage.clone())).unwrap();
+        let mut account = Account::new(alice.address());
+        account.balance = 10_000;
+        blockchain.accounts.insert(alice.address(), account);
+        
+        let code = Program::new().op(Opcode::Revert).build();
+        let deploy = Transaction::deploy(alice.address(), &code, 0, 2_000, 1);
+        let contract = deploy.to.clone();
+        let deployed = mine_contract_tx(&mut blockchain, &alice, deploy);
+        let call = Transaction::call(alice.address(), contract, 50, Vec::new(), 1, 1_000, 3).with_fee(2);
+        let called = mine_contract_tx(&mut blockchain, &alice, call);
+        
+        let reopened = Blockchain::open(1, Arc::new(storage)).unwrap();
+        let receipt = reopened.get_transaction_receipt(&deployed.transactions[0].hash()).unwrap();
+        assert!(receipt.success);
+        assert_eq!(receipt.gas_used, contract::deploy_gas(code.len()));
+        
+        let receipt = reopened.get_transaction_receipt(&called.transactions[0].hash()).unwrap();
+        assert!(!receipt.success);
+        assert_eq!(receipt.cost, 2 + 3 * receipt.gas_used);
+        assert_eq!((receipt.block_number, receipt.confirmations), (2, 1));
+        assert_eq!(reopened.get_address_history(&alice.address(), 0, 10).total, 2);
+        assert_eq!(reopened.block_number(&called.hash()), Some(2));
+    }
 }
-
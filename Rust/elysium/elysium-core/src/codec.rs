@@ -0,0 +1,586 @@
+use crate::{
+    Address, Block, BlockHeader, ConsensusSpec, Cosignature, ElysiumError, MultisigPolicy, Result,
+    RetargetConfig, TimeLock, Transaction, TransactionType,
+};
+
+/// Version of the canonical binary encoding, written as the first byte of every
+/// top-level encoding
+///
+/// Hashes are computed over the versioned encoding, so changing how any consensus
+/// type is encoded requires a new version.
+pub const CODEC_VERSION: u8 = 7;
+
+/// Marks a hex string stored as its decoded bytes
+const HEX_TAG_BYTES: u8 = 0;
+
+/// Marks a string that is not canonical hex, stored as UTF-8
+const HEX_TAG_TEXT: u8 = 1;
+
+/// Type with a canonical binary encoding
+///
+/// Integers are fixed-width big-endian, byte strings and sequences carry a `u32`
+/// length prefix, and struct fields are written in declaration order. The
+/// encoding of a value is unique, so it can be hashed and signed.
+pub trait Encode {
+    fn encode(&self, encoder: &mut Encoder);
+}
+
+/// Type that can be read back from its canonical binary encoding
+pub trait Decode: Sized {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self>;
+}
+
+/// Encode a value, prefixed with the codec version
+pub fn to_bytes<T: Encode + ?Sized>(value: &T) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    encoder.put_u8(CODEC_VERSION);
+    value.encode(&mut encoder);
+    encoder.into_bytes()
+}
+
+/// Decode a value written by [`to_bytes`], rejecting unknown versions and trailing bytes
+pub fn from_bytes<T: Decode>(bytes: &[u8]) -> Result<T> {
+    let mut decoder = Decoder::new(bytes);
+    let version = decoder.get_u8()?;
+    if version != CODEC_VERSION {
+        return Err(ElysiumError::SerializationError(
+            format!("Unsupported codec version {} (expected {})", version, CODEC_VERSION)
+        ));
+    }
+    let value = T::decode(&mut decoder)?;
+    decoder.finish()?;
+    Ok(value)
+}
+
+/// Writer for the canonical binary encoding
+#[derive(Debug, Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+    
+    pub fn put_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+    
+    pub fn put_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    
+    pub fn put_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    
+    /// Write a length-prefixed byte string
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.put_u32(value.len() as u32);
+        self.bytes.extend_from_slice(value);
+    }
+    
+    /// Write a length-prefixed UTF-8 string
+    pub fn put_str(&mut self, value: &str) {
+        self.put_bytes(value.as_bytes());
+    }
+    
+    /// Write a hex string (hash, address, signature, key)
+    ///
+    /// Canonical lowercase hex is stored as its decoded bytes. Anything else is kept
+    /// verbatim as text so that distinct strings never share an encoding.
+    pub fn put_hex(&mut self, value: &str) {
+        match hex::decode(value) {
+            Ok(bytes) if hex::encode(&bytes) == value => {
+                self.put_u8(HEX_TAG_BYTES);
+                self.put_bytes(&bytes);
+            }
+            _ => {
+                self.put_u8(HEX_TAG_TEXT);
+                self.put_str(value);
+            }
+        }
+    }
+    
+    /// Write a length-prefixed sequence
+    pub fn put_seq<T: Encode>(&mut self, items: &[T]) {
+        self.put_u32(items.len() as u32);
+        for item in items {
+            item.encode(self);
+        }
+    }
+}
+
+/// Reader for the canonical binary encoding
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+fn truncated() -> ElysiumError {
+    ElysiumError::SerializationError("Unexpected end of input".to_string())
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+    
+    /// Number of bytes not yet read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+    
+    /// Fail unless all input was consumed
+    pub fn finish(&self) -> Result<()> {
+        if !self.bytes.is_empty() {
+            return Err(ElysiumError::SerializationError(
+                format!("{} trailing bytes after value", self.bytes.len())
+            ));
+        }
+        Ok(())
+    }
+    
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(truncated());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+    
+    pub fn get_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    
+    pub fn get_u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+    
+    pub fn get_u64(&mut self) -> Result<u64> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+    
+    pub fn get_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.get_u32()? as usize;
+        self.take(len)
+    }
+    
+    pub fn get_str(&mut self) -> Result<String> {
+        let bytes = self.get_bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))
+    }
+    
+    pub fn get_hex(&mut self) -> Result<String> {
+        match self.get_u8()? {
+            HEX_TAG_BYTES => Ok(hex::encode(self.get_bytes()?)),
+            HEX_TAG_TEXT => {
+                let text = self.get_str()?;
+                // Canonical hex must use the compact form
+                if hex::decode(&text).map(|bytes| hex::encode(bytes) == text).unwrap_or(false) {
+                    return Err(ElysiumError::SerializationError(
+                        "Non-canonical hex encoding".to_string()
+                    ));
+                }
+                Ok(text)
+            }
+            tag => Err(ElysiumError::SerializationError(
+                format!("Invalid hex tag {}", tag)
+            )),
+        }
+    }
+    
+    pub fn get_seq<T: Decode>(&mut self) -> Result<Vec<T>> {
+        let len = self.get_u32()? as usize;
+        // Every item takes at least one byte, which bounds the allocation
+        if len > self.remaining() {
+            return Err(truncated());
+        }
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(T::decode(self)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Encode for Address {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_hex(self.as_hex());
+    }
+}
+
+impl Decode for Address {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        let hex = decoder.get_hex()?;
+        Address::from_hex(&hex).map_err(ElysiumError::SerializationError)
+    }
+}
+
+impl Encode for MultisigPolicy {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_u32(self.threshold);
+        encoder.put_seq(&self.signers);
+    }
+}
+
+impl Decode for MultisigPolicy {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            threshold: decoder.get_u32()?,
+            signers: decoder.get_seq()?,
+        })
+    }
+}
+
+impl Encode for Cosignature {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_hex(&self.public_key);
+        encoder.put_hex(&self.signature);
+    }
+}
+
+impl Decode for Cosignature {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            public_key: decoder.get_hex()?,
+            signature: decoder.get_hex()?,
+        })
+    }
+}
+
+impl Encode for TimeLock {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_u64(self.amount);
+        encoder.put_u64(self.unlock_height);
+    }
+}
+
+impl Decode for TimeLock {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            amount: decoder.get_u64()?,
+            unlock_height: decoder.get_u64()?,
+        })
+    }
+}
+
+impl Encode for TransactionType {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            TransactionType::Transfer => encoder.put_u8(0),
+            TransactionType::Coinbase => encoder.put_u8(1),
+            TransactionType::TimeLocked { unlock_height } => {
+                encoder.put_u8(2);
+                encoder.put_u64(*unlock_height);
+            }
+            TransactionType::CreateMultisig(policy) => {
+                encoder.put_u8(3);
+                policy.encode(encoder);
+            }
+            TransactionType::MultisigTransfer => encoder.put_u8(4),
+            TransactionType::Deploy { code, gas_limit, gas_price } => {
+                encoder.put_u8(5);
+                encoder.put_hex(code);
+                encoder.put_u64(*gas_limit);
+                encoder.put_u64(*gas_price);
+            }
+            TransactionType::Call { input, gas_limit, gas_price } => {
+                encoder.put_u8(6);
+                encoder.put_seq(input);
+                encoder.put_u64(*gas_limit);
+                encoder.put_u64(*gas_price);
+            }
+        }
+    }
+}
+
+impl Decode for TransactionType {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        match decoder.get_u8()? {
+            0 => Ok(TransactionType::Transfer),
+            1 => Ok(TransactionType::Coinbase),
+            2 => Ok(TransactionType::TimeLocked { unlock_height: decoder.get_u64()? }),
+            3 => Ok(TransactionType::CreateMultisig(MultisigPolicy::decode(decoder)?)),
+            4 => Ok(TransactionType::MultisigTransfer),
+            5 => Ok(TransactionType::Deploy {
+                code: decoder.get_hex()?,
+                gas_limit: decoder.get_u64()?,
+                gas_price: decoder.get_u64()?,
+            }),
+            6 => Ok(TransactionType::Call {
+                input: decoder.get_seq()?,
+                gas_limit: decoder.get_u64()?,
+                gas_price: decoder.get_u64()?,
+            }),
+            tag => Err(ElysiumError::SerializationError(
+                format!("Unknown transaction type {}", tag)
+            )),
+        }
+    }
+}
+
+impl Encode for Transaction {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.encode_unsigned(encoder);
+        encoder.put_hex(&self.signature);
+        encoder.put_hex(&self.public_key);
+        encoder.put_seq(&self.cosignatures);
+    }
+}
+
+impl Decode for Transaction {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        let from = Address::decode(decoder)?;
+        let to = Address::decode(decoder)?;
+        let amount = decoder.get_u64()?;
+        let fee = decoder.get_u64()?;
+        let nonce = decoder.get_u64()?;
+        let transaction_type = TransactionType::decode(decoder)?;
+        let chain_id = decoder.get_u64()?;
+        Ok(Self {
+            from,
+            to,
+            amount,
+            fee,
+            nonce,
+            transaction_type,
+            chain_id,
+            signature: decoder.get_hex()?,
+            public_key: decoder.get_hex()?,
+            cosignatures: decoder.get_seq()?,
+        })
+    }
+}
+
+impl Encode for BlockHeader {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.encode_unsealed(encoder);
+        encoder.put_hex(&self.public_key);
+        encoder.put_hex(&self.signature);
+    }
+}
+
+impl Decode for BlockHeader {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            number: decoder.get_u64()?,
+            parent_hash: decoder.get_hex()?,
+            transactions_root: decoder.get_hex()?,
+            state_root: decoder.get_hex()?,
+            timestamp: decoder.get_u64()?,
+            nonce: decoder.get_u64()?,
+            bits: decoder.get_u32()?,
+            public_key: decoder.get_hex()?,
+            signature: decoder.get_hex()?,
+        })
+    }
+}
+
+impl Encode for Block {
+    fn encode(&self, encoder: &mut Encoder) {
+        self.header.encode(encoder);
+        encoder.put_seq(&self.transactions);
+    }
+}
+
+impl Decode for Block {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            header: BlockHeader::decode(decoder)?,
+            transactions: decoder.get_seq()?,
+        })
+    }
+}
+
+impl Encode for ConsensusSpec {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            ConsensusSpec::ProofOfWork => encoder.put_u8(0),
+            ConsensusSpec::ProofOfAuthority { validators } => {
+                encoder.put_u8(1);
+                encoder.put_seq(validators);
+            }
+        }
+    }
+}
+
+impl Decode for ConsensusSpec {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        match decoder.get_u8()? {
+            0 => Ok(ConsensusSpec::ProofOfWork),
+            1 => Ok(ConsensusSpec::ProofOfAuthority { validators: decoder.get_seq()? }),
+            tag => Err(ElysiumError::SerializationError(
+                format!("Unknown consensus engine {}", tag)
+            )),
+        }
+    }
+}
+
+impl Encode for RetargetConfig {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_u64(self.target_block_time);
+        encoder.put_u64(self.window);
+    }
+}
+
+impl Decode for RetargetConfig {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        Ok(Self {
+            target_block_time: decoder.get_u64()?,
+            window: decoder.get_u64()?,
+        })
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_u64(*self);
+    }
+}
+
+impl Decode for u64 {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        decoder.get_u64()
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_str(self);
+    }
+}
+
+impl Decode for String {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        decoder.get_str()
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_seq(self);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        decoder.get_seq()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn signed_block() -> Block {
+        let keypair = KeyPair::generate();
+        let mut block = Block::new(3, "ab".repeat(32), 1);
+        for nonce in 0..3 {
+            let mut tx = Transaction::new(keypair.address(), KeyPair::generate().address(), 5, nonce);
+            tx.sign(keypair.signing_key()).unwrap();
+            block.add_transaction(tx);
+        }
+        block
+    }
+    
+    #[test]
+    fn test_block_roundtrip() {
+        let block = signed_block();
+        let bytes = to_bytes(&block);
+        assert_eq!(bytes[0], CODEC_VERSION);
+        
+        let decoded: Block = from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.hash(), block.hash());
+        assert_eq!(decoded.transactions.len(), 3);
+        assert_eq!(decoded.transactions[1].hash(), block.transactions[1].hash());
+        assert!(decoded.transactions[1].verify().is_ok());
+        assert_eq!(to_bytes(&decoded), bytes);
+    }
+    
+    #[test]
+    fn test_transaction_types_roundtrip() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let policy = MultisigPolicy::new(2, vec![alice.address(), bob.address()]).unwrap();
+        
+        let mut locked = Transaction::time_locked(alice.address(), bob.address(), 5, 0, 100);
+        locked.sign(alice.signing_key()).unwrap();
+        let mut create = Transaction::create_multisig(alice.address(), policy.clone(), 5, 1);
+        create.sign(alice.signing_key()).unwrap();
+        let mut spend = Transaction::multisig_transfer(&policy, bob.address(), 5, 0);
+        spend.cosign(alice.signing_key());
+        spend.cosign(bob.signing_key());
+        let mut deploy = Transaction::deploy(alice.address(), &[0x01, 0, 0, 0, 0, 0, 0, 0, 7], 2, 2_000, 3);
+        deploy.sign(alice.signing_key()).unwrap();
+        let mut call = Transaction::call(alice.address(), deploy.to.clone(), 0, vec![1, u64::MAX], 3, 500, 3);
+        call.sign(alice.signing_key()).unwrap();
+        
+        for tx in [locked, create, spend, deploy, call] {
+            let decoded: Transaction = from_bytes(&to_bytes(&tx)).unwrap();
+            assert_eq!(decoded.transaction_type, tx.transaction_type);
+            assert_eq!(decoded.cosignatures, tx.cosignatures);
+            assert_eq!(decoded.hash(), tx.hash());
+            assert!(decoded.verify().is_ok());
+        }
+    }
+    
+    #[test]
+    fn test_consensus_rules_roundtrip() {
+        let validators = vec![KeyPair::generate().address(), KeyPair::generate().address()];
+        for spec in [ConsensusSpec::ProofOfWork, ConsensusSpec::ProofOfAuthority { validators }] {
+            assert_eq!(from_bytes::<ConsensusSpec>(&to_bytes(&spec)).unwrap(), spec);
+        }
+        let retarget = RetargetConfig { target_block_time: 30, window: 20 };
+        assert_eq!(from_bytes::<RetargetConfig>(&to_bytes(&retarget)).unwrap(), retarget);
+        assert!(from_bytes::<ConsensusSpec>(&[CODEC_VERSION, 2]).is_err());
+    }
+    
+    #[test]
+    fn test_non_hex_strings_roundtrip() {
+        let mut header = BlockHeader::new(1, "invalid".to_string(), 1);
+        header.state_root = String::new();
+        let decoded: BlockHeader = from_bytes(&to_bytes(&header)).unwrap();
+        assert_eq!(decoded.parent_hash, "invalid");
+        assert_eq!(decoded.state_root, "");
+        assert_eq!(decoded.hash(), header.hash());
+        
+        // Upper-case hex is a different string, so it must not share an encoding
+        let mut upper = header.clone();
+        upper.parent_hash = "AB".repeat(32);
+        let mut lower = header;
+        lower.parent_hash = "ab".repeat(32);
+        assert_ne!(upper.hash(), lower.hash());
+    }
+    
+    #[test]
+    fn test_malformed_input_rejected() {
+        let bytes = to_bytes(&signed_block());
+        
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = CODEC_VERSION + 1;
+        assert!(from_bytes::<Block>(&wrong_version).is_err());
+        
+        assert!(from_bytes::<Block>(&bytes[..bytes.len() - 1]).is_err());
+        
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(from_bytes::<Block>(&trailing).is_err());
+        
+        // A huge sequence length must not cause a huge allocation
+        let mut encoder = Encoder::new();
+        encoder.put_u8(CODEC_VERSION);
+        encoder.put_u32(u32::MAX);
+        assert!(from_bytes::<Vec<Block>>(&encoder.into_bytes()).is_err());
+    }
+}
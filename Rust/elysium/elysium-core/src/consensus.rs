@@ -0,0 +1,253 @@
+use std::fmt;
+use std::sync::Arc;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use crate::account::KeyPair;
+use crate::{Address, BlockHeader, ElysiumError, Result};
+
+/// Rules deciding who may seal a block and how its seal is checked
+///
+/// The engine of a chain comes from its genesis spec (see [`ConsensusSpec`]). Block
+/// structure, transactions and state are checked the same way under every engine;
+/// only the seal and the weight used for fork choice differ.
+pub trait Consensus: fmt::Debug + Send + Sync {
+    /// Short name of the engine, as used in genesis specs
+    fn name(&self) -> &'static str;
+    
+    /// Whether blocks are sealed with proof of work, whose target follows the block rate
+    /// and whose nonce search is worth running on several threads
+    ///
+    /// Other engines keep the genesis target in every block.
+    fn is_proof_of_work(&self) -> bool {
+        false
+    }
+    
+    /// Check the seal of a header that is not a genesis header
+    fn verify_seal(&self, header: &BlockHeader) -> Result<()>;
+    
+    /// Seal a header whose other fields are final, with `signer` as the validator key
+    /// if the engine needs one
+    fn seal(&self, header: &mut BlockHeader, signer: Option<&KeyPair>) -> Result<()>;
+    
+    /// Address that must seal the block at `number`, if the engine restricts it
+    fn sealer_at(&self, number: u64) -> Option<&Address>;
+    
+    /// Weight a header adds to its branch; the main chain is the heaviest branch
+    fn work(&self, header: &BlockHeader) -> u128;
+}
+
+/// Proof of work: a block is sealed by a nonce that brings its hash under the target
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProofOfWork;
+
+impl Consensus for ProofOfWork {
+    fn name(&self) -> &'static str {
+        "proof_of_work"
+    }
+    
+    fn is_proof_of_work(&self) -> bool {
+        true
+    }
+    
+    fn verify_seal(&self, header: &BlockHeader) -> Result<()> {
+        if !header.meets_difficulty() {
+            return Err(ElysiumError::InvalidBlock(
+                "Block does not meet difficulty requirement".to_string()
+            ));
+        }
+        if !header.public_key.is_empty() || !header.signature.is_empty() {
+            return Err(ElysiumError::InvalidBlock(
+                "Proof-of-work blocks are not signed".to_string()
+            ));
+        }
+        Ok(())
+    }
+    
+    fn seal(&self, header: &mut BlockHeader, _signer: Option<&KeyPair>) -> Result<()> {
+        while !header.meets_difficulty() {
+            header.nonce = header.nonce.wrapping_add(1);
+        }
+        Ok(())
+    }
+    
+    fn sealer_at(&self, _number: u64) -> Option<&Address> {
+        None
+    }
+    
+    fn work(&self, header: &BlockHeader) -> u128 {
+        header.work()
+    }
+}
+
+/// Proof of authority: a fixed set of validators takes turns sealing blocks
+///
+/// The block at height `n` must be signed by validator `n % validators.len()`. Every
+/// block adds the same weight, so the longest branch is the main chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofOfAuthority {
+    validators: Vec<Address>,
+}
+
+impl ProofOfAuthority {
+    pub fn new(validators: Vec<Address>) -> Result<Self> {
+        if validators.is_empty() {
+            return Err(ElysiumError::BlockchainError(
+                "Proof of authority needs at least one validator".to_string()
+            ));
+        }
+        let mut unique = validators.clone();
+        unique.sort_by(|a, b| a.as_hex().cmp(b.as_hex()));
+        unique.dedup();
+        if unique.len() != validators.len() {
+            return Err(ElysiumError::BlockchainError(
+                "Proof-of-authority validators must be unique".to_string()
+            ));
+        }
+        Ok(Self { validators })
+    }
+    
+    /// Validators in slot order
+    pub fn validators(&self) -> &[Address] {
+        &self.validators
+    }
+    
+    /// Validator whose turn it is to seal the block at `number`
+    pub fn validator_at(&self, number: u64) -> &Address {
+        &self.validators[(number % self.validators.len() as u64) as usize]
+    }
+}
+
+impl Consensus for ProofOfAuthority {
+    fn name(&self) -> &'static str {
+        "proof_of_authority"
+    }
+    
+    fn verify_seal(&self, header: &BlockHeader) -> Result<()> {
+        let public_key: [u8; 32] = hex::decode(&header.public_key).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ElysiumError::InvalidSignature)?;
+        let verifying_key = VerifyingKey::from_bytes(&public_key)
+            .map_err(|_| ElysiumError::InvalidSignature)?;
+        
+        let signer = Address::from_verifying_key(&verifying_key);
+        let expected = self.validator_at(header.number);
+        if &signer != expected {
+            return Err(ElysiumError::InvalidBlock(format!(
+                "Block {} must be sealed by validator {}, not {}", header.number, expected, signer
+            )));
+        }
+        
+        let signature: [u8; 64] = hex::decode(&header.signature).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ElysiumError::InvalidSignature)?;
+        verifying_key.verify(&header.signing_hash_bytes(), &Signature::from_bytes(&signature))
+            .map_err(|_| ElysiumError::InvalidSignature)
+    }
+    
+    fn seal(&self, header: &mut BlockHeader, signer: Option<&KeyPair>) -> Result<()> {
+        let signer = signer.ok_or_else(|| ElysiumError::KeyError(
+            "Sealing a proof-of-authority block needs a validator key".to_string()
+        ))?;
+        let expected = self.validator_at(header.number);
+        if &signer.address() != expected {
+            return Err(ElysiumError::KeyError(format!(
+                "Block {} must be sealed by validator {}, not {}", header.number, expected, signer.address()
+            )));
+        }
+        header.public_key = hex::encode(signer.verifying_key().to_bytes());
+        header.signature = hex::encode(signer.sign(&header.signing_hash_bytes()).to_bytes());
+        Ok(())
+    }
+    
+    fn sealer_at(&self, number: u64) -> Option<&Address> {
+        Some(self.validator_at(number))
+    }
+    
+    fn work(&self, _header: &BlockHeader) -> u128 {
+        1
+    }
+}
+
+/// Consensus engine selected by a genesis spec
+///
+/// ```json
+/// {"engine": "proof_of_authority", "validators": ["a1b2c3d4...", "e5f6a7b8..."]}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "engine", rename_all = "snake_case")]
+pub enum ConsensusSpec {
+    #[default]
+    ProofOfWork,
+    /// Validator addresses in slot order
+    ProofOfAuthority { validators: Vec<Address> },
+}
+
+impl ConsensusSpec {
+    /// Build the engine the spec describes
+    pub fn engine(&self) -> Result<Arc<dyn Consensus>> {
+        Ok(match self {
+            ConsensusSpec::ProofOfWork => Arc::new(ProofOfWork),
+            ConsensusSpec::ProofOfAuthority { validators } => Arc::new(ProofOfAuthority::new(validators.clone())?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Block;
+    
+    #[test]
+    fn test_proof_of_work_seal() {
+        let mut header = Block::new(1, "0".repeat(64), 1).header;
+        ProofOfWork.seal(&mut header, None).unwrap();
+        assert!(ProofOfWork.verify_seal(&header).is_ok());
+        assert_eq!(ProofOfWork.work(&header), header.work());
+        
+        // A signature does not replace the proof of work
+        header.signature = "00".repeat(64);
+        assert!(ProofOfWork.verify_seal(&header).is_err());
+    }
+    
+    #[test]
+    fn test_proof_of_authority_slots() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let engine = ProofOfAuthority::new(vec![alice.address(), bob.address()]).unwrap();
+        assert_eq!(engine.sealer_at(1), Some(&bob.address()));
+        assert_eq!(engine.sealer_at(4), Some(&alice.address()));
+        
+        // Only the validator of the slot can seal
+        let mut header = Block::new(1, "0".repeat(64), 1).header;
+        assert!(engine.seal(&mut header, Some(&alice)).is_err());
+        assert!(engine.seal(&mut header, None).is_err());
+        engine.seal(&mut header, Some(&bob)).unwrap();
+        assert!(engine.verify_seal(&header).is_ok());
+        assert_eq!(engine.work(&header), 1);
+        
+        // The signature covers the header, and a signature for another slot is rejected
+        let mut tampered = header.clone();
+        tampered.timestamp += 1;
+        assert!(matches!(engine.verify_seal(&tampered), Err(ElysiumError::InvalidSignature)));
+        let mut other_slot = Block::new(2, "0".repeat(64), 1).header;
+        other_slot.public_key = header.public_key.clone();
+        other_slot.signature = header.signature.clone();
+        assert!(matches!(engine.verify_seal(&other_slot), Err(ElysiumError::InvalidBlock(_))));
+        assert!(engine.verify_seal(&Block::new(3, "0".repeat(64), 1).header).is_err());
+        
+        assert!(ProofOfAuthority::new(vec![]).is_err());
+        assert!(ProofOfAuthority::new(vec![alice.address(), alice.address()]).is_err());
+    }
+    
+    #[test]
+    fn test_spec_json() {
+        let address = KeyPair::generate().address();
+        let json = format!(r#"{{"engine": "proof_of_authority", "validators": ["{}"]}}"#, address.as_hex());
+        let spec: ConsensusSpec = serde_json::from_str(&json).unwrap();
+        assert_eq!(spec, ConsensusSpec::ProofOfAuthority { validators: vec![address] });
+        assert_eq!(spec.engine().unwrap().name(), "proof_of_authority");
+        
+        let spec: ConsensusSpec = serde_json::from_str(r#"{"engine": "proof_of_work"}"#).unwrap();
+        assert!(spec.engine().unwrap().is_proof_of_work());
+    }
+}
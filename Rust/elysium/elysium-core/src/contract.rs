@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use elysium_vm::{self as vm, Context, Host, Outcome, VmError};
+use crate::codec::{Encode, Encoder};
+use crate::{Account, Address, ElysiumError, Result, Transaction, TransactionType};
+
+/// Most gas the transactions of one block may reserve
+pub const MAX_BLOCK_GAS: u64 = 10_000_000;
+
+/// Gas charged for every deployment
+pub const DEPLOY_GAS_BASE: u64 = 1_000;
+
+/// Gas charged per byte of deployed code
+pub const DEPLOY_GAS_PER_BYTE: u64 = 10;
+
+/// Most input words a contract call can carry
+pub const MAX_CALL_INPUT: usize = 256;
+
+/// Tag prefixed to the deployer and nonce when deriving a contract address
+const CONTRACT_ADDRESS_DOMAIN: &[u8] = b"elysium-contract";
+
+/// Address of the contract deployed by `deployer` with its transaction `nonce`
+pub fn contract_address(deployer: &Address, nonce: u64) -> Address {
+    let mut encoder = Encoder::new();
+    encoder.put_bytes(CONTRACT_ADDRESS_DOMAIN);
+    deployer.encode(&mut encoder);
+    encoder.put_u64(nonce);
+    let hash = Sha256::digest(encoder.into_bytes());
+    Address::from_hex(&hex::encode(hash)).expect("SHA-256 digests are valid addresses")
+}
+
+/// Gas used by deploying `code_len` bytes of code
+pub fn deploy_gas(code_len: usize) -> u64 {
+    DEPLOY_GAS_BASE.saturating_add(DEPLOY_GAS_PER_BYTE.saturating_mul(code_len as u64))
+}
+
+/// Result of a read-only contract call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallResult {
+    /// Words returned by the contract
+    pub output: Vec<u64>,
+    pub gas_used: u64,
+}
+
+/// Chain state seen by a contract running in the block at `height`
+pub struct AccountHost<'a> {
+    account: Option<&'a Account>,
+    height: u64,
+}
+
+impl<'a> AccountHost<'a> {
+    pub fn new(account: Option<&'a Account>, height: u64) -> Self {
+        Self { account, height }
+    }
+}
+
+impl Host for AccountHost<'_> {
+    fn storage(&self, key: u64) -> u64 {
+        self.account
+            .and_then(|account| account.storage.get(&key))
+            .copied()
+            .unwrap_or(0)
+    }
+    
+    fn balance(&self) -> u64 {
+        self.account.map(|account| account.balance).unwrap_or(0)
+    }
+    
+    fn block_height(&self) -> u64 {
+        self.height
+    }
+}
+
+/// Check the parts of a deploy or call that do not depend on the chain state
+///
+/// Other transactions always pass.
+pub fn check_contract_transaction(tx: &Transaction) -> Result<()> {
+    match &tx.transaction_type {
+        TransactionType::Deploy { code, .. } => {
+            let code = hex::decode(code)
+                .map_err(|_| ElysiumError::ContractError("Contract code must be hex".to_string()))?;
+            if code.is_empty() {
+                return Err(ElysiumError::ContractError("Contract code cannot be empty".to_string()));
+            }
+            vm::validate_code(&code)
+                .map_err(|e| ElysiumError::ContractError(format!("Invalid contract code: {}", e)))?;
+            if tx.to != contract_address(&tx.from, tx.nonce) {
+                return Err(ElysiumError::ContractError(
+                    "Contract address does not match the deployer and nonce".to_string()
+                ));
+            }
+        }
+        TransactionType::Call { input, .. } => {
+            if input.len() > MAX_CALL_INPUT {
+                return Err(ElysiumError::ContractError(
+                    format!("Call input has {} words, at most {} allowed", input.len(), MAX_CALL_INPUT)
+                ));
+            }
+        }
+        _ => return Ok(()),
+    }
+    if tx.gas_limit() > MAX_BLOCK_GAS {
+        return Err(ElysiumError::ContractError(
+            format!("Gas limit {} exceeds the block gas limit {}", tx.gas_limit(), MAX_BLOCK_GAS)
+        ));
+    }
+    Ok(())
+}
+
+/// Run a deploy or call in the block at `height` against the state before it
+///
+/// Returns `None` for other transactions. A deployment stores its code without running
+/// it; a call to an account without code runs empty code, which succeeds.
+pub(crate) fn execute(accounts: &HashMap<Address, Account>, tx: &Transaction, height: u64) -> Option<Outcome> {
+    match &tx.transaction_type {
+        TransactionType::Deploy { code, gas_limit, .. } => {
+            let gas = deploy_gas(code.len() / 2);
+            Some(if gas <= *gas_limit {
+                Outcome { gas_used: gas, result: Ok(Vec::new()), writes: Default::default() }
+            } else {
+                Outcome { gas_used: *gas_limit, result: Err(VmError::OutOfGas), writes: Default::default() }
+            })
+        }
+        TransactionType::Call { input, gas_limit, .. } => {
+            let account = accounts.get(&tx.to);
+            // Code was validated when it was deployed
+            let code = account
+                .map(|account| hex::decode(&account.code).unwrap_or_default())
+                .unwrap_or_default();
+            let context = Context { value: tx.amount, input: input.clone(), gas_limit: *gas_limit };
+            Some(vm::execute(&code, &context, &AccountHost::new(account, height)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    use elysium_vm::{Opcode, Program};
+    
+    #[test]
+    fn test_check_contract_transaction() {
+        let deployer = KeyPair::generate();
+        let code = Program::new().push(1).op(Opcode::Stop).build();
+        let deploy = Transaction::deploy(deployer.address(), &code, 4, 5_000, 1);
+        assert_eq!(deploy.to, contract_address(&deployer.address(), 4));
+        check_contract_transaction(&deploy).unwrap();
+        
+        let mut elsewhere = deploy.clone();
+        elsewhere.to = contract_address(&deployer.address(), 5);
+        assert!(check_contract_transaction(&elsewhere).is_err());
+        
+        // Truncated and unknown instructions are rejected at deployment
+        assert!(check_contract_transaction(&Transaction::deploy(deployer.address(), &code[..5], 4, 5_000, 1)).is_err());
+        assert!(check_contract_transaction(&Transaction::deploy(deployer.address(), &[0xee], 4, 5_000, 1)).is_err());
+        assert!(check_contract_transaction(&Transaction::deploy(deployer.address(), &[], 4, 5_000, 1)).is_err());
+        
+        let call = Transaction::call(deployer.address(), deploy.to.clone(), 0, vec![0; MAX_CALL_INPUT + 1], 5, 100, 1);
+        assert!(check_contract_transaction(&call).is_err());
+        let call = Transaction::call(deployer.address(), deploy.to, 0, vec![1], 5, MAX_BLOCK_GAS + 1, 1);
+        assert!(check_contract_transaction(&call).is_err());
+    }
+}
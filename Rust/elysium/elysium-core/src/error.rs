@@ -1,5 +1,20 @@
+use serde_json::{json, Value};
 use thiserror::Error;
 
+/// Stable JSON-RPC error codes, one per [`ElysiumError`] variant
+pub mod error_code {
+    pub const INVALID_TRANSACTION: i64 = -32001;
+    pub const INVALID_BLOCK: i64 = -32002;
+    pub const INSUFFICIENT_BALANCE: i64 = -32003;
+    pub const INVALID_SIGNATURE: i64 = -32004;
+    pub const BLOCKCHAIN_ERROR: i64 = -32005;
+    pub const SERIALIZATION_ERROR: i64 = -32006;
+    pub const STORAGE_ERROR: i64 = -32007;
+    pub const CONTRACT_ERROR: i64 = -32008;
+    pub const KEY_ERROR: i64 = -32009;
+    pub const INVALID_NONCE: i64 = -32010;
+}
+
 #[derive(Error, Debug)]
 pub enum ElysiumError {
     #[error("Invalid transaction: {0}")]
@@ -19,7 +34,111 @@ pub enum ElysiumError {
     
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    
+    #[error("Storage error: {0}")]
+    StorageError(String),
+    
+    #[error("Contract error: {0}")]
+    ContractError(String),
+    
+    #[error("Key error: {0}")]
+    KeyError(String),
+    
+    #[error("Invalid nonce: expected {expected}, got {got}")]
+    InvalidNonce { expected: u64, got: u64 },
+}
+
+impl ElysiumError {
+    /// JSON-RPC error code of the variant, from [`error_code`]
+    pub fn code(&self) -> i64 {
+        match self {
+            ElysiumError::InvalidTransaction(_) => error_code::INVALID_TRANSACTION,
+            ElysiumError::InvalidBlock(_) => error_code::INVALID_BLOCK,
+            ElysiumError::InsufficientBalance => error_code::INSUFFICIENT_BALANCE,
+            ElysiumError::InvalidSignature => error_code::INVALID_SIGNATURE,
+            ElysiumError::BlockchainError(_) => error_code::BLOCKCHAIN_ERROR,
+            ElysiumError::SerializationError(_) => error_code::SERIALIZATION_ERROR,
+            ElysiumError::StorageError(_) => error_code::STORAGE_ERROR,
+            ElysiumError::ContractError(_) => error_code::CONTRACT_ERROR,
+            ElysiumError::KeyError(_) => error_code::KEY_ERROR,
+            ElysiumError::InvalidNonce { .. } => error_code::INVALID_NONCE,
+        }
+    }
+    
+    /// Fields of the variant for the `data` of a JSON-RPC error: `{"reason": ...}` for
+    /// variants carrying a message, `{"expected": ..., "got": ...}` for a nonce mismatch
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            ElysiumError::InvalidTransaction(reason)
+            | ElysiumError::InvalidBlock(reason)
+            | ElysiumError::BlockchainError(reason)
+            | ElysiumError::SerializationError(reason)
+            | ElysiumError::StorageError(reason)
+            | ElysiumError::ContractError(reason)
+            | ElysiumError::KeyError(reason) => Some(json!({ "reason": reason })),
+            ElysiumError::InvalidNonce { expected, got } => Some(json!({ "expected": expected, "got": got })),
+            ElysiumError::InsufficientBalance | ElysiumError::InvalidSignature => None,
+        }
+    }
+    
+    /// Rebuild an error from its code and data, or `None` if the code is not one of ours
+    pub fn from_code(code: i64, data: Option<&Value>) -> Option<Self> {
+        let reason = || {
+            data.and_then(|data| data["reason"].as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let error = match code {
+            error_code::INVALID_TRANSACTION => ElysiumError::InvalidTransaction(reason()),
+            error_code::INVALID_BLOCK => ElysiumError::InvalidBlock(reason()),
+            error_code::INSUFFICIENT_BALANCE => ElysiumError::InsufficientBalance,
+            error_code::INVALID_SIGNATURE => ElysiumError::InvalidSignature,
+            error_code::BLOCKCHAIN_ERROR => ElysiumError::BlockchainError(reason()),
+            error_code::SERIALIZATION_ERROR => ElysiumError::SerializationError(reason()),
+            error_code::STORAGE_ERROR => ElysiumError::StorageError(reason()),
+            error_code::CONTRACT_ERROR => ElysiumError::ContractError(reason()),
+            error_code::KEY_ERROR => ElysiumError::KeyError(reason()),
+            error_code::INVALID_NONCE => {
+                let field = |name: &str| data.and_then(|data| data[name].as_u64());
+                ElysiumError::InvalidNonce { expected: field("expected")?, got: field("got")? }
+            }
+            _ => return None,
+        };
+        Some(error)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ElysiumError>;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_codes_round_trip() {
+        let errors = [
+            ElysiumError::InvalidTransaction("duplicate".to_string()),
+            ElysiumError::InvalidBlock("bad root".to_string()),
+            ElysiumError::InsufficientBalance,
+            ElysiumError::InvalidSignature,
+            ElysiumError::BlockchainError("no work".to_string()),
+            ElysiumError::SerializationError("eof".to_string()),
+            ElysiumError::StorageError("disk".to_string()),
+            ElysiumError::ContractError("out of gas".to_string()),
+            ElysiumError::KeyError("locked".to_string()),
+            ElysiumError::InvalidNonce { expected: 3, got: 1 },
+        ];
+        for error in &errors {
+            let rebuilt = ElysiumError::from_code(error.code(), error.data().as_ref()).unwrap();
+            assert_eq!(rebuilt.code(), error.code());
+            assert_eq!(rebuilt.to_string(), error.to_string());
+        }
+        
+        let mut codes: Vec<i64> = errors.iter().map(ElysiumError::code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(ElysiumError::from_code(-32602, None).is_none());
+        assert!(ElysiumError::from_code(error_code::INVALID_NONCE, None).is_none());
+    }
+}
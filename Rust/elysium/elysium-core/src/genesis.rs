@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::codec::{self, Encode, Encoder};
+use crate::state::StateTree;
+use crate::{Account, Address, Block, ConsensusSpec, ElysiumError, Result, RetargetConfig};
+
+/// Chain id of development chains created without a genesis specification
+pub const DEV_CHAIN_ID: u64 = 0;
+
+/// Genesis specification: everything every node needs to build the same first block
+///
+/// ```json
+/// {
+///   "chain_id": 7,
+///   "timestamp": 1700000000,
+///   "difficulty": 1,
+///   "alloc": {
+///     "a1b2c3d4...": 1000000
+///   },
+///   "consensus": {"engine": "proof_of_authority", "validators": ["a1b2c3d4..."]},
+///   "retarget": {"target_block_time": 10, "window": 60}
+/// }
+/// ```
+///
+/// Without `consensus`, blocks are sealed with proof of work. Without `retarget`, the
+/// proof-of-work target follows the default block time and window.
+///
+/// The genesis block has a fixed timestamp, commits to the allocated balances through
+/// its state root, and commits to the chain id and the consensus rules through its
+/// parent hash (see [`GenesisSpec::parent_hash`]). Two chains with different ids,
+/// allocations, engines or retarget settings therefore never share a genesis hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisSpec {
+    pub chain_id: u64,
+    /// Unix timestamp of the genesis block
+    pub timestamp: u64,
+    /// Initial mining difficulty
+    pub difficulty: u64,
+    /// Initial balances by hex-encoded address
+    #[serde(default)]
+    pub alloc: BTreeMap<String, u64>,
+    /// Engine sealing the chain's blocks
+    #[serde(default)]
+    pub consensus: ConsensusSpec,
+    /// How the proof-of-work target follows the observed block rate
+    #[serde(default)]
+    pub retarget: RetargetConfig,
+}
+
+impl GenesisSpec {
+    /// Parse a specification from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        let spec: Self = serde_json::from_str(json)
+            .map_err(|e| ElysiumError::SerializationError(format!("Invalid genesis spec: {}", e)))?;
+        spec.validate()?;
+        Ok(spec)
+    }
+    
+    /// Load a specification from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| ElysiumError::StorageError(
+            format!("Failed to read genesis spec {}: {}", path.display(), e)
+        ))?;
+        Self::from_json(&json)
+    }
+    
+    /// Check the difficulty, addresses, total supply and consensus engine
+    pub fn validate(&self) -> Result<()> {
+        if self.difficulty == 0 {
+            return Err(ElysiumError::BlockchainError(
+                "Genesis difficulty must be at least 1".to_string()
+            ));
+        }
+        
+        if let ConsensusSpec::ProofOfAuthority { validators } = &self.consensus {
+            for validator in validators {
+                Address::from_hex(validator.as_hex()).map_err(|e| ElysiumError::BlockchainError(
+                    format!("Invalid validator address {}: {}", validator.as_hex(), e)
+                ))?;
+            }
+        }
+        self.consensus.engine()?;
+        
+        let mut supply = 0u64;
+        for (address, balance) in &self.alloc {
+            Address::from_hex(address).map_err(|e| ElysiumError::BlockchainError(
+                format!("Invalid genesis allocation address {}: {}", address, e)
+            ))?;
+            if address.chars().any(|c| c.is_ascii_uppercase()) {
+                return Err(ElysiumError::BlockchainError(
+                    format!("Genesis allocation address {} must be lowercase hex", address)
+                ));
+            }
+            supply = supply.checked_add(*balance).ok_or_else(|| ElysiumError::BlockchainError(
+                "Genesis allocations overflow the total supply".to_string()
+            ))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Accounts holding the allocated balances
+    pub fn accounts(&self) -> HashMap<Address, Account> {
+        self.alloc.iter()
+            .filter_map(|(address, balance)| {
+                let address = Address::from_hex(address).ok()?;
+                let mut account = Account::new(address.clone());
+                account.balance = *balance;
+                Some((address, account))
+            })
+            .collect()
+    }
+    
+    /// Parent hash of the genesis block, which encodes the consensus rules and the chain id
+    ///
+    /// The first 48 hex digits are taken from the SHA-256 hash of the encoded consensus
+    /// engine and retarget settings; the last 16 are the chain id.
+    pub fn parent_hash(&self) -> String {
+        let mut encoder = Encoder::new();
+        encoder.put_u8(codec::CODEC_VERSION);
+        self.consensus.encode(&mut encoder);
+        self.retarget.encode(&mut encoder);
+        let rules = Sha256::digest(encoder.into_bytes());
+        format!("{}{:016x}", hex::encode(&rules[..24]), self.chain_id)
+    }
+    
+    /// Build the genesis block, mined under proof of work; the result is the same on every node
+    ///
+    /// Under proof of authority the genesis block has no slot and is not sealed.
+    pub fn genesis_block(&self) -> Block {
+        let accounts = self.accounts();
+        let mut genesis = Block::new(0, self.parent_hash(), self.difficulty);
+        genesis.header.timestamp = self.timestamp;
+        genesis.header.state_root = StateTree::from_accounts(accounts.values()).root();
+        if self.consensus == ConsensusSpec::ProofOfWork {
+            genesis.mine();
+        }
+        genesis
+    }
+}
+
+/// Chain id committed to by the last 16 hex digits of a genesis block's parent hash
+pub fn chain_id_of(genesis: &Block) -> Option<u64> {
+    let parent_hash = &genesis.header.parent_hash;
+    if parent_hash.len() != 64 || !parent_hash.is_char_boundary(48) {
+        return None;
+    }
+    u64::from_str_radix(&parent_hash[48..], 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn spec_json(address: &Address) -> String {
+        format!(
+            r#"{{"chain_id": 7, "timestamp": 1700000000, "difficulty": 2, "alloc": {{"{}": 5000}}}}"#,
+            address.as_hex()
+        )
+    }
+    
+    #[test]
+    fn test_genesis_is_deterministic() {
+        let address = KeyPair::generate().address();
+        let spec = GenesisSpec::from_json(&spec_json(&address)).unwrap();
+        let genesis = spec.genesis_block();
+        
+        assert_eq!(genesis.hash(), spec.genesis_block().hash());
+        assert_eq!(genesis.header.timestamp, 1_700_000_000);
+        assert_eq!(genesis.header.difficulty(), 2);
+        assert_eq!(chain_id_of(&genesis), Some(7));
+        
+        // The chain id, the allocations and the retarget settings are part of the genesis hash
+        let mut other_chain = spec.clone();
+        other_chain.chain_id = 8;
+        assert_ne!(other_chain.genesis_block().hash(), genesis.hash());
+        let mut other_alloc = spec.clone();
+        other_alloc.alloc.insert(address.as_hex().to_string(), 5001);
+        assert_ne!(other_alloc.genesis_block().hash(), genesis.hash());
+        let mut other_retarget = spec.clone();
+        other_retarget.retarget.window = 20;
+        assert_ne!(other_retarget.genesis_block().hash(), genesis.hash());
+        assert_eq!(chain_id_of(&other_retarget.genesis_block()), Some(7));
+    }
+    
+    #[test]
+    fn test_genesis_commits_to_consensus_engine() {
+        let alice = KeyPair::generate().address();
+        let bob = KeyPair::generate().address();
+        let mut spec = GenesisSpec::from_json(&spec_json(&alice)).unwrap();
+        let proof_of_work = spec.genesis_block().hash();
+        
+        // Same chain id and allocations, different engines or validator sets
+        let mut hashes = vec![proof_of_work];
+        for validators in [vec![alice.clone()], vec![bob.clone()], vec![alice.clone(), bob.clone()], vec![bob, alice]] {
+            spec.consensus = ConsensusSpec::ProofOfAuthority { validators };
+            let genesis = spec.genesis_block();
+            assert_eq!(chain_id_of(&genesis), Some(7));
+            assert!(!hashes.contains(&genesis.hash()));
+            hashes.push(genesis.hash());
+        }
+    }
+    
+    #[test]
+    fn test_invalid_specs_are_rejected() {
+        assert!(GenesisSpec::from_json("{}").is_err());
+        assert!(GenesisSpec::from_json(
+            r#"{"chain_id": 1, "timestamp": 0, "difficulty": 0}"#
+        ).is_err());
+        assert!(GenesisSpec::from_json(
+            r#"{"chain_id": 1, "timestamp": 0, "difficulty": 1, "alloc": {"1234": 10}}"#
+        ).is_err());
+        
+        let upper = "AB".repeat(32);
+        let json = format!(
+            r#"{{"chain_id": 1, "timestamp": 0, "difficulty": 1, "alloc": {{"{}": 10}}}}"#,
+            upper
+        );
+        assert!(GenesisSpec::from_json(&json).is_err());
+        
+        let json = format!(
+            r#"{{"chain_id": 1, "timestamp": 0, "difficulty": 1, "alloc": {{"{}": {}, "{}": 1}}}}"#,
+            "11".repeat(32), u64::MAX, "22".repeat(32)
+        );
+        assert!(GenesisSpec::from_json(&json).is_err());
+    }
+    
+    #[test]
+    fn test_proof_of_authority_spec() {
+        let validator = KeyPair::generate().address();
+        let json = format!(
+            r#"{{"chain_id": 7, "timestamp": 0, "difficulty": 1, "consensus": {{"engine": "proof_of_authority", "validators": ["{}"]}}}}"#,
+            validator.as_hex()
+        );
+        let spec = GenesisSpec::from_json(&json).unwrap();
+        assert_eq!(spec.consensus, ConsensusSpec::ProofOfAuthority { validators: vec![validator] });
+        assert!(spec.genesis_block().header.signature.is_empty());
+        
+        let empty = r#"{"chain_id": 7, "timestamp": 0, "difficulty": 1, "consensus": {"engine": "proof_of_authority", "validators": []}}"#;
+        assert!(GenesisSpec::from_json(empty).is_err());
+        let invalid = r#"{"chain_id": 7, "timestamp": 0, "difficulty": 1, "consensus": {"engine": "proof_of_authority", "validators": ["1234"]}}"#;
+        assert!(GenesisSpec::from_json(invalid).is_err());
+    }
+    
+    #[test]
+    fn test_retarget_spec() {
+        let spec = GenesisSpec::from_json(&spec_json(&KeyPair::generate().address())).unwrap();
+        assert_eq!(spec.retarget, RetargetConfig::default());
+        
+        // Settings left out keep their defaults
+        let json = r#"{"chain_id": 7, "timestamp": 0, "difficulty": 1, "retarget": {"window": 20}}"#;
+        let spec = GenesisSpec::from_json(json).unwrap();
+        assert_eq!(spec.retarget, RetargetConfig { window: 20, ..RetargetConfig::default() });
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::{Address, Block, Transaction};
+
+/// Transactions in one page of an address history unless a size is asked for
+pub const DEFAULT_HISTORY_PAGE: usize = 50;
+
+/// Most transactions one page of an address history may hold
+pub const MAX_HISTORY_PAGE: usize = 1_000;
+
+/// Where a transaction was included in the main chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionLocation {
+    pub block_hash: String,
+    pub block_number: u64,
+    /// Position of the transaction in its block
+    pub index: usize,
+}
+
+/// Result of running a deploy or call when its block was applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionStatus {
+    pub success: bool,
+    pub gas_used: u64,
+}
+
+/// A transaction and its location, which is `None` while it is pending
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionLookup {
+    pub transaction: Transaction,
+    pub location: Option<TransactionLocation>,
+}
+
+/// Outcome of a transaction included in the main chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    pub transaction_hash: String,
+    pub block_hash: String,
+    pub block_number: u64,
+    pub index: usize,
+    pub from: Address,
+    pub to: Address,
+    /// False if a deploy or call failed; its amount then stayed with the sender
+    pub success: bool,
+    /// Gas used by a deploy or call, 0 for other transactions
+    pub gas_used: u64,
+    /// Fee plus gas paid by the sender
+    pub cost: u64,
+    /// Number of main-chain blocks from the including block to the tip, both included
+    pub confirmations: u64,
+}
+
+/// One page of the main-chain transactions touching an address, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressHistory {
+    pub address: Address,
+    /// Number of transactions touching the address, over all pages
+    pub total: usize,
+    pub offset: usize,
+    pub transactions: Vec<TransactionLookup>,
+}
+
+/// Lookup tables over the main chain
+///
+/// Blocks are added and removed at the tip, in the same order the chain connects and
+/// disconnects them, so a reorganization keeps the index in step with the main chain.
+#[derive(Debug, Clone, Default)]
+pub struct ChainIndex {
+    /// Main-chain block numbers by block hash
+    blocks: HashMap<String, u64>,
+    transactions: HashMap<String, TransactionLocation>,
+    /// Hashes of the transactions sending from or to each address, oldest first
+    addresses: HashMap<Address, Vec<String>>,
+    /// Outcomes of deploys and calls by transaction hash
+    executions: HashMap<String, ExecutionStatus>,
+}
+
+impl ChainIndex {
+    /// Index a block connected at the tip, with the outcome of each of its transactions
+    /// (`None` for transactions that run no code)
+    pub fn connect(&mut self, block: &Block, executions: &[Option<ExecutionStatus>]) {
+        let block_hash = block.hash();
+        self.blocks.insert(block_hash.clone(), block.header.number);
+        
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let tx_hash = tx.hash();
+            self.transactions.insert(tx_hash.clone(), TransactionLocation {
+                block_hash: block_hash.clone(),
+                block_number: block.header.number,
+                index,
+            });
+            for address in touched_addresses(tx) {
+                self.addresses.entry(address.clone()).or_default().push(tx_hash.clone());
+            }
+            if let Some(Some(status)) = executions.get(index) {
+                self.executions.insert(tx_hash, *status);
+            }
+        }
+    }
+    
+    /// Remove the block disconnected from the tip
+    pub fn disconnect(&mut self, block: &Block) {
+        self.blocks.remove(&block.hash());
+        
+        for tx in block.transactions.iter().rev() {
+            let tx_hash = tx.hash();
+            self.transactions.remove(&tx_hash);
+            self.executions.remove(&tx_hash);
+            for address in touched_addresses(tx) {
+                if let Some(hashes) = self.addresses.get_mut(address) {
+                    if hashes.last() == Some(&tx_hash) {
+                        hashes.pop();
+                    }
+                    if hashes.is_empty() {
+                        self.addresses.remove(address);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Number of a main-chain block
+    pub fn block_number(&self, block_hash: &str) -> Option<u64> {
+        self.blocks.get(block_hash).copied()
+    }
+    
+    /// Location of a main-chain transaction
+    pub fn location(&self, tx_hash: &str) -> Option<&TransactionLocation> {
+        self.transactions.get(tx_hash)
+    }
+    
+    /// Outcome of a main-chain deploy or call
+    pub fn execution(&self, tx_hash: &str) -> Option<ExecutionStatus> {
+        self.executions.get(tx_hash).copied()
+    }
+    
+    /// Every recorded deploy and call outcome, for persisting with the state
+    pub fn executions(&self) -> impl Iterator<Item = (&String, &ExecutionStatus)> {
+        self.executions.iter()
+    }
+    
+    /// Restore outcomes saved by [`ChainIndex::executions`] for blocks indexed without them
+    pub fn restore_executions(&mut self, executions: impl IntoIterator<Item = (String, ExecutionStatus)>) {
+        for (tx_hash, status) in executions {
+            if self.transactions.contains_key(&tx_hash) {
+                self.executions.insert(tx_hash, status);
+            }
+        }
+    }
+    
+    /// Hashes of the transactions touching an address, newest first, skipping `offset`
+    /// and returning at most `limit`, plus the total count
+    pub fn address_history(&self, address: &Address, offset: usize, limit: usize) -> (Vec<&String>, usize) {
+        let hashes = self.addresses.get(address).map(Vec::as_slice).unwrap_or_default();
+        let page = hashes.iter().rev().skip(offset).take(limit).collect();
+        (page, hashes.len())
+    }
+}
+
+/// Addresses a transaction sends from or to; a coinbase has no real sender
+fn touched_addresses(tx: &Transaction) -> Vec<&Address> {
+    if tx.is_coinbase() || tx.from == tx.to {
+        vec![&tx.to]
+    } else {
+        vec![&tx.from, &tx.to]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn block(number: u64, parent_hash: String, transactions: &[Transaction]) -> Block {
+        let mut block = Block::new(number, parent_hash, 1);
+        for tx in transactions {
+            block.add_transaction(tx.clone());
+        }
+        block
+    }
+    
+    #[test]
+    fn test_connect_and_disconnect() {
+        let alice = KeyPair::generate().address();
+        let bob = KeyPair::generate().address();
+        let carol = KeyPair::generate().address();
+        let first = Transaction::new(alice.clone(), bob.clone(), 10, 0);
+        let second = Transaction::new(alice.clone(), carol.clone(), 5, 1);
+        let third = Transaction::new(bob.clone(), carol.clone(), 1, 0);
+        
+        let genesis = block(0, "0".repeat(64), &[]);
+        let one = block(1, genesis.hash(), &[first.clone(), second.clone()]);
+        let two = block(2, one.hash(), std::slice::from_ref(&third));
+        let status = ExecutionStatus { success: false, gas_used: 7 };
+        
+        let mut index = ChainIndex::default();
+        index.connect(&genesis, &[]);
+        index.connect(&one, &[None, Some(status)]);
+        index.connect(&two, &[None]);
+        
+        assert_eq!(index.block_number(&two.hash()), Some(2));
+        let location = index.location(&second.hash()).unwrap();
+        assert_eq!((location.block_number, location.index), (1, 1));
+        assert_eq!(location.block_hash, one.hash());
+        assert_eq!(index.execution(&second.hash()), Some(status));
+        assert_eq!(index.execution(&first.hash()), None);
+        
+        let (page, total) = index.address_history(&carol, 0, 10);
+        assert_eq!(total, 2);
+        assert_eq!(page, vec![&third.hash(), &second.hash()]);
+        let (page, total) = index.address_history(&alice, 1, 10);
+        assert_eq!((page, total), (vec![&first.hash()], 2));
+        assert_eq!(index.address_history(&alice, 0, 1).0, vec![&second.hash()]);
+        
+        // Disconnecting the tip forgets its block and transactions
+        index.disconnect(&two);
+        assert_eq!(index.block_number(&two.hash()), None);
+        assert!(index.location(&third.hash()).is_none());
+        assert_eq!(index.address_history(&carol, 0, 10), (vec![&second.hash()], 1));
+        
+        index.disconnect(&one);
+        assert_eq!(index.address_history(&alice, 0, 10), (Vec::<&String>::new(), 0));
+        assert_eq!(index.execution(&second.hash()), None);
+        assert_eq!(index.block_number(&genesis.hash()), Some(0));
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::account::KeyPair;
+use crate::{Address, ElysiumError, Result};
+
+/// Version of the keystore file format
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// scrypt cost (log2 of N) used for new keystores: 32 MiB of memory per attempt
+pub const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+
+/// Highest scrypt cost (log2 of N) accepted when opening a keystore
+pub const MAX_SCRYPT_LOG_N: u8 = 20;
+
+/// Most memory scrypt may use (128·r·N bytes) when opening a keystore, so a crafted
+/// file cannot make decryption exhaust the machine's memory
+pub const MAX_SCRYPT_MEMORY: u64 = 256 * 1024 * 1024;
+
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// scrypt parameters a keystore's encryption key was derived with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    /// Random salt in hex format
+    pub salt: String,
+}
+
+/// Encrypted secret key and how to decrypt it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    /// Always `"chacha20poly1305"`
+    pub cipher: String,
+    /// Encrypted secret key followed by the authentication tag, in hex format
+    pub ciphertext: String,
+    /// Cipher nonce in hex format
+    pub nonce: String,
+    /// Always `"scrypt"`
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+}
+
+/// Password-protected key pair, stored as JSON
+///
+/// The secret key is encrypted with ChaCha20-Poly1305 under a key derived from the
+/// password with scrypt. The address is stored in the clear so keystores can be listed
+/// without the password, and is authenticated along with the ciphertext, so a file
+/// whose address was edited fails to open.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub address: Address,
+    pub crypto: KeystoreCrypto,
+}
+
+impl Keystore {
+    /// Encrypt a key pair with the default scrypt cost
+    pub fn encrypt(keypair: &KeyPair, password: &str) -> Result<Self> {
+        Self::encrypt_with_cost(keypair, password, DEFAULT_SCRYPT_LOG_N)
+    }
+    
+    /// Encrypt a key pair with scrypt cost `log_n`
+    ///
+    /// Lower costs are faster to open but easier to brute-force; use them for tests only.
+    pub fn encrypt_with_cost(keypair: &KeyPair, password: &str, log_n: u8) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        
+        let kdf_params = KdfParams { log_n, r: SCRYPT_R, p: SCRYPT_P, salt: hex::encode(salt) };
+        let key = derive_key(password, &kdf_params)?;
+        let address = keypair.address();
+        let secret = Zeroizing::new(keypair.to_secret_bytes());
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret.as_slice(), aad: &address.as_bytes() })
+            .map_err(|_| ElysiumError::KeyError("Encryption failed".to_string()))?;
+        
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            address,
+            crypto: KeystoreCrypto {
+                cipher: "chacha20poly1305".to_string(),
+                ciphertext: hex::encode(ciphertext),
+                nonce: hex::encode(nonce),
+                kdf: "scrypt".to_string(),
+                kdf_params,
+            },
+        })
+    }
+    
+    /// Decrypt the key pair, failing if the password is wrong or the file was modified
+    pub fn decrypt(&self, password: &str) -> Result<KeyPair> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(ElysiumError::KeyError(
+                format!("Unsupported keystore version {}", self.version)
+            ));
+        }
+        if self.crypto.cipher != "chacha20poly1305" || self.crypto.kdf != "scrypt" {
+            return Err(ElysiumError::KeyError(
+                format!("Unsupported keystore encryption {}/{}", self.crypto.kdf, self.crypto.cipher)
+            ));
+        }
+        let nonce = hex::decode(&self.crypto.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == NONCE_LEN)
+            .ok_or_else(|| ElysiumError::KeyError("Invalid keystore nonce".to_string()))?;
+        let ciphertext = hex::decode(&self.crypto.ciphertext)
+            .map_err(|_| ElysiumError::KeyError("Invalid keystore ciphertext".to_string()))?;
+        
+        let key = derive_key(password, &self.crypto.kdf_params)?;
+        let secret = ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &self.address.as_bytes() })
+            .map(Zeroizing::new)
+            .map_err(|_| ElysiumError::KeyError("Wrong password or corrupted keystore".to_string()))?;
+        let secret: &[u8; 32] = secret.as_slice().try_into()
+            .map_err(|_| ElysiumError::KeyError("Invalid secret key length".to_string()))?;
+        
+        let keypair = KeyPair::from_secret_bytes(secret);
+        if keypair.address() != self.address {
+            return Err(ElysiumError::KeyError("Keystore address does not match its key".to_string()));
+        }
+        Ok(keypair)
+    }
+    
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))
+    }
+    
+    /// Parse a keystore from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))
+    }
+    
+    /// Write the keystore to a file that only its owner can read
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = self.to_json()?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .map_err(|e| ElysiumError::StorageError(e.to_string()))
+    }
+    
+    /// Read a keystore from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| ElysiumError::StorageError(e.to_string()))?;
+        Self::from_json(&json)
+    }
+}
+
+/// Derive the 32-byte encryption key from a password
+fn derive_key(password: &str, params: &KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    if params.log_n > MAX_SCRYPT_LOG_N
+        || params.r > 32
+        || params.p > 16
+        || (128 * params.r as u64) << params.log_n > MAX_SCRYPT_MEMORY
+    {
+        return Err(ElysiumError::KeyError("Keystore scrypt parameters are too expensive".to_string()));
+    }
+    let salt = hex::decode(&params.salt)
+        .map_err(|_| ElysiumError::KeyError("Invalid keystore salt".to_string()))?;
+    let scrypt_params = scrypt::Params::new(params.log_n, params.r, params.p, 32)
+        .map_err(|e| ElysiumError::KeyError(format!("Invalid scrypt parameters: {}", e)))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), &salt, &scrypt_params, key.as_mut_slice())
+        .map_err(|e| ElysiumError::KeyError(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_keystore_roundtrip() {
+        let keypair = KeyPair::generate();
+        let keystore = Keystore::encrypt_with_cost(&keypair, "correct horse", 4).unwrap();
+        assert_eq!(keystore.address, keypair.address());
+        assert!(!keystore.to_json().unwrap().contains(&hex::encode(keypair.to_secret_bytes())));
+        
+        let restored = Keystore::from_json(&keystore.to_json().unwrap()).unwrap()
+            .decrypt("correct horse").unwrap();
+        assert_eq!(restored.to_secret_bytes(), keypair.to_secret_bytes());
+        
+        assert!(keystore.decrypt("wrong horse").is_err());
+    }
+    
+    #[test]
+    fn test_tampered_keystore_is_rejected() {
+        let keypair = KeyPair::generate();
+        let keystore = Keystore::encrypt_with_cost(&keypair, "pw", 4).unwrap();
+        
+        // The address is authenticated with the ciphertext
+        let mut moved = keystore.clone();
+        moved.address = KeyPair::generate().address();
+        assert!(moved.decrypt("pw").is_err());
+        
+        let mut flipped = keystore.clone();
+        flipped.crypto.ciphertext.replace_range(0..2, if flipped.crypto.ciphertext.starts_with("00") { "01" } else { "00" });
+        assert!(flipped.decrypt("pw").is_err());
+        
+        let mut expensive = keystore;
+        expensive.crypto.kdf_params.log_n = MAX_SCRYPT_LOG_N + 1;
+        assert!(expensive.decrypt("pw").is_err());
+    }
+    
+    #[test]
+    fn test_keystore_memory_cost_is_capped() {
+        let keypair = KeyPair::generate();
+        let mut keystore = Keystore::encrypt_with_cost(&keypair, "pw", 4).unwrap();
+        
+        // Both are within the separate limits, but would need 512 MiB and 4 GiB
+        for (log_n, r) in [(17, 32), (MAX_SCRYPT_LOG_N, 32)] {
+            keystore.crypto.kdf_params.log_n = log_n;
+            keystore.crypto.kdf_params.r = r;
+            let err = keystore.decrypt("pw").unwrap_err();
+            assert!(err.to_string().contains("too expensive"), "{}", err);
+        }
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_saved_keystore_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.json");
+        let keystore = Keystore::encrypt_with_cost(&KeyPair::generate(), "pw", 4).unwrap();
+        keystore.save(&path).unwrap();
+        
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(Keystore::load(&path).unwrap(), keystore);
+    }
+}
@@ -1,12 +1,40 @@
 pub mod account;
 pub mod block;
 pub mod blockchain;
+pub mod codec;
+pub mod consensus;
+pub mod contract;
 pub mod transaction;
 pub mod error;
+pub mod genesis;
+pub mod index;
+pub mod keystore;
+pub mod mempool;
+pub mod merkle;
+pub mod mnemonic;
+pub mod multisig;
+pub mod pow;
+pub mod reward;
+pub mod state;
+pub mod storage;
 
 pub use account::*;
 pub use block::*;
 pub use blockchain::*;
+pub use codec::{Decode, Encode, CODEC_VERSION};
+pub use consensus::*;
+pub use contract::*;
 pub use transaction::*;
 pub use error::*;
+pub use genesis::*;
+pub use index::*;
+pub use keystore::*;
+pub use mempool::*;
+pub use merkle::*;
+pub use mnemonic::*;
+pub use multisig::*;
+pub use pow::*;
+pub use reward::*;
+pub use state::*;
+pub use storage::*;
 
@@ -0,0 +1,437 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::{Account, Address, ElysiumError, Result, Transaction};
+
+/// Limits of the transaction pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolConfig {
+    /// Maximum number of transactions held; beyond it the cheapest are evicted
+    pub max_size: usize,
+    /// How far past a sender's confirmed nonce a queued transaction may be
+    pub max_nonce_gap: u64,
+    /// Percentage by which a replacement must raise the fee of the transaction it replaces
+    pub replacement_fee_bump: u64,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 5_000,
+            max_nonce_gap: 64,
+            replacement_fee_bump: 10,
+        }
+    }
+}
+
+/// A pooled transaction, with the order it arrived in to break fee ties
+#[derive(Debug, Clone)]
+struct PoolEntry {
+    tx: Transaction,
+    sequence: u64,
+}
+
+/// Sender's next transaction while building a block, ordered by fee and then arrival
+#[derive(Debug, PartialEq, Eq)]
+struct Candidate {
+    fee: u64,
+    sequence: Reverse<u64>,
+    sender: Address,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.fee, self.sequence).cmp(&(other.fee, other.sequence))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Pool of transactions waiting to be mined
+///
+/// Transactions are kept per sender by nonce. Those whose nonce follows the sender's
+/// confirmed nonce without gaps are executable; later ones are queued until the gap
+/// is filled. A sender's pooled transactions may together cost at most its confirmed
+/// balance, so the pool never holds a set that overspends. A transaction with the
+/// same sender and nonce as a pooled one replaces it if it pays a high enough fee.
+/// Time-locked amounts do not count towards the balance until they unlock.
+/// When the pool is full, the cheapest transaction that is last in its sender's queue
+/// is evicted, so eviction never opens a nonce gap.
+#[derive(Debug, Clone, Default)]
+pub struct Mempool {
+    config: MempoolConfig,
+    senders: HashMap<Address, BTreeMap<u64, PoolEntry>>,
+    /// Sender and nonce of every pooled transaction, by hash
+    by_hash: HashMap<String, (Address, u64)>,
+    next_sequence: u64,
+}
+
+impl Mempool {
+    /// Create an empty pool
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+    
+    /// Pool limits
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+    
+    /// Number of pooled transactions
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+    
+    /// Check if the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+    
+    /// Check whether a transaction is pooled
+    pub fn contains(&self, tx_hash: &str) -> bool {
+        self.by_hash.contains_key(tx_hash)
+    }
+    
+    /// Get a pooled transaction by hash
+    pub fn get(&self, tx_hash: &str) -> Option<&Transaction> {
+        let (sender, nonce) = self.by_hash.get(tx_hash)?;
+        self.senders.get(sender)?.get(nonce).map(|entry| &entry.tx)
+    }
+    
+    /// All pooled transactions, by sender and nonce
+    pub fn transactions(&self) -> Vec<Transaction> {
+        let mut transactions: Vec<&PoolEntry> = self.senders.values()
+            .flat_map(|queue| queue.values())
+            .collect();
+        transactions.sort_by_key(|entry| (entry.tx.from.as_hex().to_string(), entry.tx.nonce));
+        transactions.into_iter().map(|entry| entry.tx.clone()).collect()
+    }
+    
+    /// Nonce the sender's next transaction should use, counting executable pooled ones
+    pub fn pending_nonce(&self, address: &Address, accounts: &HashMap<Address, Account>) -> u64 {
+        let mut nonce = confirmed_nonce(accounts, address);
+        if let Some(queue) = self.senders.get(address) {
+            while queue.contains_key(&nonce) {
+                nonce += 1;
+            }
+        }
+        nonce
+    }
+    
+    /// Add a transaction whose signature and fields have already been checked
+    ///
+    /// Nonce and balance are checked against the confirmed state in `accounts` plus
+    /// the sender's other pooled transactions, with the balance spendable by the block
+    /// at `height`.
+    pub fn insert(&mut self, tx: Transaction, accounts: &HashMap<Address, Account>, height: u64) -> Result<()> {
+        let tx_hash = tx.hash();
+        if self.by_hash.contains_key(&tx_hash) {
+            return Err(ElysiumError::InvalidTransaction(
+                "Duplicate transaction detected in pending pool".to_string()
+            ));
+        }
+        
+        let nonce = confirmed_nonce(accounts, &tx.from);
+        if tx.nonce < nonce {
+            return Err(ElysiumError::InvalidNonce { expected: nonce, got: tx.nonce });
+        }
+        if tx.nonce - nonce > self.config.max_nonce_gap {
+            return Err(ElysiumError::InvalidTransaction(
+                format!("Nonce {} is too far ahead of the confirmed nonce {}", tx.nonce, nonce)
+            ));
+        }
+        
+        let queue = self.senders.get(&tx.from);
+        let replaced = queue.and_then(|queue| queue.get(&tx.nonce));
+        if let Some(existing) = replaced {
+            let required = existing.tx.fee
+                .saturating_mul(100 + self.config.replacement_fee_bump)
+                .div_ceil(100)
+                .max(existing.tx.fee + 1);
+            if tx.fee < required {
+                return Err(ElysiumError::InvalidTransaction(
+                    format!("Replacement fee too low: need at least {}, got {}", required, tx.fee)
+                ));
+            }
+        }
+        
+        // Everything the sender has pooled, with this transaction in place, must be affordable
+        let cost = tx.total_cost().ok_or(ElysiumError::InsufficientBalance)?;
+        let others = queue.into_iter()
+            .flat_map(|queue| queue.values())
+            .filter(|entry| entry.tx.nonce != tx.nonce)
+            .try_fold(0u64, |total, entry| entry.tx.total_cost().and_then(|c| total.checked_add(c)));
+        let total = others.and_then(|others| others.checked_add(cost))
+            .ok_or(ElysiumError::InsufficientBalance)?;
+        if total > spendable_balance(accounts, &tx.from, height) {
+            return Err(ElysiumError::InsufficientBalance);
+        }
+        
+        let sender = tx.from.clone();
+        let tx_nonce = tx.nonce;
+        let entry = PoolEntry {
+            tx,
+            sequence: self.next_sequence,
+        };
+        self.next_sequence += 1;
+        if let Some(old) = self.senders.entry(sender.clone()).or_default().insert(tx_nonce, entry) {
+            self.by_hash.remove(&old.tx.hash());
+        }
+        self.by_hash.insert(tx_hash.clone(), (sender, tx_nonce));
+        
+        // Make room by evicting the cheapest tail, which may be the new transaction
+        while self.by_hash.len() > self.config.max_size {
+            let evicted = self.evict_cheapest();
+            if evicted.as_deref() == Some(tx_hash.as_str()) {
+                return Err(ElysiumError::InvalidTransaction(
+                    "Mempool is full and the fee is too low".to_string()
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Remove the lowest-fee transaction that is last in its sender's queue, returning its hash
+    fn evict_cheapest(&mut self) -> Option<String> {
+        let (sender, nonce) = self.senders.iter()
+            .filter_map(|(sender, queue)| {
+                let (nonce, entry) = queue.iter().next_back()?;
+                Some(((entry.tx.fee, Reverse(entry.sequence)), sender, *nonce))
+            })
+            .min_by_key(|(key, _, _)| *key)
+            .map(|(_, sender, nonce)| (sender.clone(), nonce))?;
+        self.remove(&sender, nonce).map(|tx| tx.hash())
+    }
+    
+    fn remove(&mut self, sender: &Address, nonce: u64) -> Option<Transaction> {
+        let queue = self.senders.get_mut(sender)?;
+        let entry = queue.remove(&nonce)?;
+        if queue.is_empty() {
+            self.senders.remove(sender);
+        }
+        self.by_hash.remove(&entry.tx.hash());
+        Some(entry.tx)
+    }
+    
+    /// Executable transactions, highest fee first while keeping each sender's nonce order
+    ///
+    /// At most `limit` transactions are returned. They stay in the pool until a block
+    /// confirms them and the pool is pruned.
+    pub fn ready(&self, accounts: &HashMap<Address, Account>, limit: usize) -> Vec<Transaction> {
+        let mut next_nonce: HashMap<&Address, u64> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        for (sender, queue) in &self.senders {
+            let nonce = confirmed_nonce(accounts, sender);
+            if let Some(entry) = queue.get(&nonce) {
+                heap.push(Candidate {
+                    fee: entry.tx.fee,
+                    sequence: Reverse(entry.sequence),
+                    sender: sender.clone(),
+                });
+                next_nonce.insert(sender, nonce);
+            }
+        }
+        
+        let mut ready = Vec::new();
+        while ready.len() < limit {
+            let Some(candidate) = heap.pop() else { break };
+            let queue = &self.senders[&candidate.sender];
+            let nonce = next_nonce.get_mut(&candidate.sender).expect("sender has a candidate");
+            ready.push(queue[nonce].tx.clone());
+            *nonce += 1;
+            if let Some(entry) = queue.get(nonce) {
+                heap.push(Candidate {
+                    fee: entry.tx.fee,
+                    sequence: Reverse(entry.sequence),
+                    sender: candidate.sender,
+                });
+            }
+        }
+        ready
+    }
+    
+    /// Drop transactions that the confirmed state has made invalid
+    ///
+    /// Transactions with a nonce below the sender's confirmed nonce are gone, and if a
+    /// sender's balance spendable by the block at `height` no longer covers its pooled
+    /// transactions (e.g. after a reorganization), its highest nonces are dropped until
+    /// it does.
+    pub fn prune(&mut self, accounts: &HashMap<Address, Account>, height: u64) {
+        let senders: Vec<Address> = self.senders.keys().cloned().collect();
+        for sender in senders {
+            let nonce = confirmed_nonce(accounts, &sender);
+            let stale: Vec<u64> = self.senders[&sender].range(..nonce).map(|(n, _)| *n).collect();
+            for n in stale {
+                self.remove(&sender, n);
+            }
+            
+            let balance = spendable_balance(accounts, &sender, height);
+            while let Some(queue) = self.senders.get(&sender) {
+                let total = queue.values()
+                    .try_fold(0u64, |total, entry| entry.tx.total_cost().and_then(|c| total.checked_add(c)));
+                if total.is_some_and(|total| total <= balance) {
+                    break;
+                }
+                let last = *queue.keys().next_back().expect("queues are never empty");
+                self.remove(&sender, last);
+            }
+        }
+    }
+}
+
+fn confirmed_nonce(accounts: &HashMap<Address, Account>, address: &Address) -> u64 {
+    accounts.get(address).map(|account| account.nonce).unwrap_or(0)
+}
+
+fn spendable_balance(accounts: &HashMap<Address, Account>, address: &Address, height: u64) -> u64 {
+    accounts.get(address).map(|account| account.spendable_at(height)).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn funded(keypairs: &[&KeyPair], balance: u64) -> HashMap<Address, Account> {
+        keypairs.iter()
+            .map(|keypair| {
+                let mut account = Account::new(keypair.address());
+                account.balance = balance;
+                (keypair.address(), account)
+            })
+            .collect()
+    }
+    
+    fn transfer(from: &KeyPair, amount: u64, fee: u64, nonce: u64) -> Transaction {
+        let to = Address::from_hex(&"11".repeat(32)).unwrap();
+        let mut tx = Transaction::new(from.address(), to, amount, nonce).with_fee(fee);
+        tx.sign(from.signing_key()).unwrap();
+        tx
+    }
+    
+    #[test]
+    fn test_future_nonces_are_queued() {
+        let alice = KeyPair::generate();
+        let accounts = funded(&[&alice], 1000);
+        let mut pool = Mempool::default();
+        
+        pool.insert(transfer(&alice, 10, 0, 2), &accounts, 1).unwrap();
+        assert!(pool.ready(&accounts, usize::MAX).is_empty());
+        assert_eq!(pool.pending_nonce(&alice.address(), &accounts), 0);
+        
+        pool.insert(transfer(&alice, 10, 0, 0), &accounts, 1).unwrap();
+        assert_eq!(pool.ready(&accounts, usize::MAX).len(), 1);
+        pool.insert(transfer(&alice, 10, 0, 1), &accounts, 1).unwrap();
+        let ready = pool.ready(&accounts, usize::MAX);
+        assert_eq!(ready.iter().map(|tx| tx.nonce).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(pool.pending_nonce(&alice.address(), &accounts), 3);
+        
+        // Past and far-future nonces are rejected
+        let mut confirmed = accounts.clone();
+        confirmed.get_mut(&alice.address()).unwrap().nonce = 1;
+        assert!(pool.insert(transfer(&alice, 11, 0, 0), &confirmed, 1).is_err());
+        assert!(pool.insert(transfer(&alice, 10, 0, 1 + 65), &confirmed, 1).is_err());
+        
+        // Confirming the first two leaves the third
+        confirmed.get_mut(&alice.address()).unwrap().nonce = 2;
+        pool.prune(&confirmed, 1);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.ready(&confirmed, usize::MAX)[0].nonce, 2);
+    }
+    
+    #[test]
+    fn test_pooled_transactions_cannot_overspend() {
+        let alice = KeyPair::generate();
+        let accounts = funded(&[&alice], 100);
+        let mut pool = Mempool::default();
+        
+        pool.insert(transfer(&alice, 60, 0, 0), &accounts, 1).unwrap();
+        pool.insert(transfer(&alice, 30, 5, 1), &accounts, 1).unwrap();
+        assert!(matches!(
+            pool.insert(transfer(&alice, 6, 0, 2), &accounts, 1),
+            Err(ElysiumError::InsufficientBalance)
+        ));
+        pool.insert(transfer(&alice, 5, 0, 2), &accounts, 1).unwrap();
+        
+        // A lower balance (e.g. after a reorganization) drops the last transactions
+        let mut poorer = accounts.clone();
+        poorer.get_mut(&alice.address()).unwrap().balance = 90;
+        pool.prune(&poorer, 1);
+        assert_eq!(pool.len(), 1);
+    }
+    
+    #[test]
+    fn test_ready_orders_by_fee_within_nonce_order() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let accounts = funded(&[&alice, &bob], 1000);
+        let mut pool = Mempool::default();
+        
+        pool.insert(transfer(&alice, 10, 1, 0), &accounts, 1).unwrap();
+        pool.insert(transfer(&alice, 10, 9, 1), &accounts, 1).unwrap();
+        pool.insert(transfer(&bob, 10, 5, 0), &accounts, 1).unwrap();
+        pool.insert(transfer(&bob, 10, 5, 1), &accounts, 1).unwrap();
+        
+        let fees: Vec<u64> = pool.ready(&accounts, usize::MAX).iter().map(|tx| tx.fee).collect();
+        assert_eq!(fees, vec![5, 5, 1, 9]);
+        assert_eq!(pool.ready(&accounts, 2).len(), 2);
+    }
+    
+    #[test]
+    fn test_replace_by_fee() {
+        let alice = KeyPair::generate();
+        let accounts = funded(&[&alice], 1000);
+        let mut pool = Mempool::default();
+        
+        let original = transfer(&alice, 10, 10, 0);
+        pool.insert(original.clone(), &accounts, 1).unwrap();
+        
+        // Less than a 10% bump is rejected
+        assert!(pool.insert(transfer(&alice, 20, 10, 0), &accounts, 1).is_err());
+        let replacement = transfer(&alice, 20, 11, 0);
+        pool.insert(replacement.clone(), &accounts, 1).unwrap();
+        
+        assert_eq!(pool.len(), 1);
+        assert!(!pool.contains(&original.hash()));
+        assert_eq!(pool.get(&replacement.hash()).unwrap().amount, 20);
+        
+        // A free transaction can be replaced by any paying one
+        let free = transfer(&alice, 10, 0, 1);
+        pool.insert(free, &accounts, 1).unwrap();
+        pool.insert(transfer(&alice, 10, 1, 1), &accounts, 1).unwrap();
+        assert_eq!(pool.len(), 2);
+    }
+    
+    #[test]
+    fn test_full_pool_evicts_cheapest_tail() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let accounts = funded(&[&alice, &bob, &carol], 1000);
+        let mut pool = Mempool::new(MempoolConfig { max_size: 3, ..MempoolConfig::default() });
+        
+        // Alice's cheap first transaction is protected by her more expensive second one
+        pool.insert(transfer(&alice, 10, 1, 0), &accounts, 1).unwrap();
+        pool.insert(transfer(&alice, 10, 8, 1), &accounts, 1).unwrap();
+        let bob_tx = transfer(&bob, 10, 3, 0);
+        pool.insert(bob_tx.clone(), &accounts, 1).unwrap();
+        
+        // Paying more than Bob evicts Bob
+        pool.insert(transfer(&carol, 10, 4, 0), &accounts, 1).unwrap();
+        assert_eq!(pool.len(), 3);
+        assert!(!pool.contains(&bob_tx.hash()));
+        
+        // Paying less than every tail is refused
+        let err = pool.insert(transfer(&bob, 10, 2, 0), &accounts, 1).unwrap_err();
+        assert!(err.to_string().contains("full"));
+        assert_eq!(pool.len(), 3);
+    }
+}
@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::BlockHeader;
+
+/// Prefix for hashing a leaf, so a leaf can never be mistaken for an inner node
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix for hashing an inner node
+const NODE_PREFIX: u8 = 0x01;
+
+type Hash = [u8; 32];
+
+fn hash_leaf(leaf: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(leaf);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Decode a hex leaf hash; anything that is not valid hex is hashed as raw bytes
+fn leaf_bytes(leaf_hash: &str) -> Vec<u8> {
+    hex::decode(leaf_hash).unwrap_or_else(|_| leaf_hash.as_bytes().to_vec())
+}
+
+/// Binary Merkle tree over a list of hex-encoded hashes
+///
+/// Leaves are hashed as `SHA-256(0x00 || leaf)` and inner nodes as
+/// `SHA-256(0x01 || left || right)`. A node without a sibling is promoted to the
+/// next level unchanged rather than paired with itself, so two different leaf lists
+/// can never produce the same root. The root of an empty tree is `SHA-256("")`.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Tree levels from the hashed leaves up to the root
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// Build a tree from hex-encoded leaf hashes
+    pub fn from_hashes<S: AsRef<str>>(hashes: &[S]) -> Self {
+        let leaves: Vec<Hash> = hashes.iter()
+            .map(|h| hash_leaf(&leaf_bytes(h.as_ref())))
+            .collect();
+        
+        let mut levels = vec![leaves];
+        while levels.last().map(|level| level.len() > 1).unwrap_or(false) {
+            let next = levels.last().unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        
+        Self { levels }
+    }
+    
+    /// Number of leaves
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+    
+    /// Check if the tree has no leaves
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Hex-encoded root hash
+    pub fn root(&self) -> String {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => hex::encode(root),
+            None => hex::encode(Sha256::digest(b"")),
+        }
+    }
+    
+    /// Inclusion proof for the leaf at `index`
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.len() {
+            return None;
+        }
+        
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                siblings.push(hex::encode(level[sibling]));
+            }
+            position /= 2;
+        }
+        
+        Some(MerkleProof {
+            leaf_index: index as u64,
+            leaf_count: self.len() as u64,
+            siblings,
+        })
+    }
+}
+
+/// Merkle inclusion proof: the sibling hashes on the path from a leaf to the root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Position of the leaf in the tree
+    pub leaf_index: u64,
+    /// Number of leaves in the tree
+    pub leaf_count: u64,
+    /// Hex-encoded sibling hashes, from the leaf level upwards
+    pub siblings: Vec<String>,
+}
+
+impl MerkleProof {
+    /// Compute the root implied by this proof for the given leaf hash
+    ///
+    /// Returns `None` if the proof is malformed for its leaf index and count.
+    pub fn compute_root(&self, leaf_hash: &str) -> Option<String> {
+        if self.leaf_index >= self.leaf_count {
+            return None;
+        }
+        
+        let mut current = hash_leaf(&leaf_bytes(leaf_hash));
+        let mut position = self.leaf_index;
+        let mut level_len = self.leaf_count;
+        let mut siblings = self.siblings.iter();
+        
+        while level_len > 1 {
+            let sibling = position ^ 1;
+            if sibling < level_len {
+                let bytes = hex::decode(siblings.next()?).ok()?;
+                let sibling_hash: Hash = bytes.try_into().ok()?;
+                current = if position.is_multiple_of(2) {
+                    hash_node(&current, &sibling_hash)
+                } else {
+                    hash_node(&sibling_hash, &current)
+                };
+            }
+            position /= 2;
+            level_len = level_len.div_ceil(2);
+        }
+        
+        // Every sibling must be used exactly once
+        if siblings.next().is_some() {
+            return None;
+        }
+        Some(hex::encode(current))
+    }
+    
+    /// Verify that `leaf_hash` is included in the tree with the given root
+    pub fn verify(&self, leaf_hash: &str, root: &str) -> bool {
+        self.compute_root(leaf_hash).as_deref() == Some(root)
+    }
+}
+
+/// Proof that a transaction is included in a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionProof {
+    pub transaction_hash: String,
+    pub block_hash: String,
+    /// Header of the containing block, which commits to the transactions root
+    pub header: BlockHeader,
+    pub proof: MerkleProof,
+}
+
+impl TransactionProof {
+    /// Check that the header matches the block hash and the Merkle path leads to its transactions root
+    pub fn verify(&self) -> bool {
+        self.header.hash() == self.block_hash
+            && self.proof.verify(&self.transaction_hash, &self.header.transactions_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn leaves(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| hex::encode(Sha256::digest(i.to_string().as_bytes())))
+            .collect()
+    }
+    
+    #[test]
+    fn test_proofs_verify_for_every_leaf() {
+        for count in 1..=17 {
+            let hashes = leaves(count);
+            let tree = MerkleTree::from_hashes(&hashes);
+            let root = tree.root();
+            
+            for (index, hash) in hashes.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(proof.verify(hash, &root), "leaf {} of {}", index, count);
+            }
+            assert!(tree.proof(count).is_none());
+        }
+    }
+    
+    #[test]
+    fn test_tampered_proof_rejected() {
+        let hashes = leaves(5);
+        let tree = MerkleTree::from_hashes(&hashes);
+        let root = tree.root();
+        let proof = tree.proof(2).unwrap();
+        
+        // Wrong leaf
+        assert!(!proof.verify(&hashes[3], &root));
+        
+        // Wrong position
+        let mut moved = proof.clone();
+        moved.leaf_index = 3;
+        assert!(!moved.verify(&hashes[2], &root));
+        
+        // Altered or extra sibling
+        let mut altered = proof.clone();
+        altered.siblings[0] = hashes[0].clone();
+        assert!(!altered.verify(&hashes[2], &root));
+        let mut extended = proof;
+        extended.siblings.push(hashes[0].clone());
+        assert!(!extended.verify(&hashes[2], &root));
+    }
+    
+    #[test]
+    fn test_odd_leaf_is_not_duplicated() {
+        // With duplication, [a, b, c] and [a, b, c, c] would share a root
+        let mut hashes = leaves(3);
+        let root = MerkleTree::from_hashes(&hashes).root();
+        hashes.push(hashes[2].clone());
+        assert_ne!(MerkleTree::from_hashes(&hashes).root(), root);
+    }
+    
+    #[test]
+    fn test_empty_and_single_leaf_roots() {
+        let empty: Vec<String> = Vec::new();
+        assert_eq!(MerkleTree::from_hashes(&empty).root(), hex::encode(Sha256::digest(b"")));
+        
+        let hashes = leaves(1);
+        let tree = MerkleTree::from_hashes(&hashes);
+        let proof = tree.proof(0).unwrap();
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&hashes[0], &tree.root()));
+    }
+}
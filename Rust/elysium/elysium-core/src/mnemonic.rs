@@ -0,0 +1,154 @@
+use std::fmt;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+use crate::account::KeyPair;
+use crate::{ElysiumError, Result};
+
+/// SLIP-0044 coin type in Elysium derivation paths
+pub const ELYSIUM_COIN_TYPE: u32 = 7_377;
+
+/// Offset marking a hardened child index
+const HARDENED: u32 = 0x8000_0000;
+
+/// BIP-39 recovery phrase from which any number of accounts can be derived
+///
+/// Account keys follow SLIP-0010 for ed25519, which only has hardened derivation, at
+/// the path `m/44'/7377'/index'/0'/0'`. The same phrase and passphrase always give
+/// the same accounts, so writing down the phrase backs up all of them.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    inner: bip39::Mnemonic,
+}
+
+impl Mnemonic {
+    /// Generate a random phrase of 12, 15, 18, 21 or 24 words
+    pub fn generate(word_count: usize) -> Result<Self> {
+        if !matches!(word_count, 12 | 15 | 18 | 21 | 24) {
+            return Err(ElysiumError::KeyError(
+                format!("A mnemonic has 12, 15, 18, 21 or 24 words, not {}", word_count)
+            ));
+        }
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(entropy.as_mut_slice());
+        let inner = bip39::Mnemonic::from_entropy(&entropy[..word_count / 3 * 4])
+            .map_err(|e| ElysiumError::KeyError(e.to_string()))?;
+        Ok(Self { inner })
+    }
+    
+    /// Parse an English phrase, checking its checksum
+    pub fn from_phrase(phrase: &str) -> Result<Self> {
+        let inner = bip39::Mnemonic::parse_in(bip39::Language::English, phrase)
+            .map_err(|e| ElysiumError::KeyError(format!("Invalid mnemonic: {}", e)))?;
+        Ok(Self { inner })
+    }
+    
+    /// The words, separated by single spaces
+    pub fn phrase(&self) -> String {
+        self.inner.to_string()
+    }
+    
+    /// Number of words
+    pub fn word_count(&self) -> usize {
+        self.inner.word_count()
+    }
+    
+    /// BIP-39 seed for an optional passphrase (empty for none)
+    pub fn to_seed(&self, passphrase: &str) -> Zeroizing<[u8; 64]> {
+        Zeroizing::new(self.inner.to_seed(passphrase))
+    }
+    
+    /// Key pair of the account at `index`
+    pub fn derive_keypair(&self, passphrase: &str, index: u32) -> Result<KeyPair> {
+        let path = [44, ELYSIUM_COIN_TYPE, index, 0, 0];
+        derive_path(self.to_seed(passphrase).as_slice(), &path)
+    }
+}
+
+// Hide the words from logs
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mnemonic({} words)", self.word_count())
+    }
+}
+
+/// Derive the ed25519 key at a path of hardened indices from a seed, following SLIP-0010
+pub fn derive_path(seed: &[u8], path: &[u32]) -> Result<KeyPair> {
+    let (mut key, mut chain_code) = hmac_sha512(b"ed25519 seed", &[seed]);
+    for &index in path {
+        if index >= HARDENED {
+            return Err(ElysiumError::KeyError(
+                format!("Child index {} is out of range", index)
+            ));
+        }
+        (key, chain_code) = hmac_sha512(
+            chain_code.as_slice(),
+            &[&[0], key.as_slice(), &(index | HARDENED).to_be_bytes()],
+        );
+    }
+    Ok(KeyPair::from_secret_bytes(&key))
+}
+
+/// Split HMAC-SHA512 of `parts` into a key and a chain code
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    let output = Zeroizing::new(<[u8; 64]>::from(mac.finalize().into_bytes()));
+    let mut left = Zeroizing::new([0u8; 32]);
+    let mut right = Zeroizing::new([0u8; 32]);
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_slip10_vectors() {
+        // SLIP-0010 ed25519 test vector 1
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        assert_eq!(
+            hex::encode(derive_path(&seed, &[]).unwrap().to_secret_bytes()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(derive_path(&seed, &[0]).unwrap().to_secret_bytes()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert!(derive_path(&seed, &[HARDENED]).is_err());
+    }
+    
+    #[test]
+    fn test_mnemonic_derivation_is_deterministic() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = Mnemonic::from_phrase(phrase).unwrap();
+        assert_eq!(mnemonic.phrase(), phrase);
+        assert_eq!(
+            hex::encode(mnemonic.to_seed("TREZOR").as_slice()),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        
+        let first = mnemonic.derive_keypair("", 0).unwrap();
+        assert_eq!(first.address(), Mnemonic::from_phrase(phrase).unwrap().derive_keypair("", 0).unwrap().address());
+        assert_ne!(first.address(), mnemonic.derive_keypair("", 1).unwrap().address());
+        assert_ne!(first.address(), mnemonic.derive_keypair("TREZOR", 0).unwrap().address());
+        
+        // A wrong word breaks the checksum
+        assert!(Mnemonic::from_phrase(&phrase.replace("about", "abandon")).is_err());
+    }
+    
+    #[test]
+    fn test_generate() {
+        let mnemonic = Mnemonic::generate(24).unwrap();
+        assert_eq!(mnemonic.word_count(), 24);
+        assert_eq!(Mnemonic::from_phrase(&mnemonic.phrase()).unwrap(), mnemonic);
+        assert!(!format!("{:?}", mnemonic).contains(&mnemonic.phrase()));
+        assert!(Mnemonic::generate(13).is_err());
+    }
+}
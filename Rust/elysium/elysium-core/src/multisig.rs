@@ -0,0 +1,178 @@
+use std::collections::HashSet;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::codec::{Encode, Encoder};
+use crate::{Address, ElysiumError, Result, Transaction};
+
+/// Most signers a multi-signature account can have
+pub const MAX_MULTISIG_SIGNERS: usize = 16;
+
+/// Tag prefixed to a policy's encoding when deriving the address it controls
+const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"elysium-multisig";
+
+/// M-of-N spending policy of a multi-signature account
+///
+/// The account's address is derived from the policy, so no single key controls it:
+/// a transfer out of it needs valid co-signatures from at least `threshold` distinct
+/// signers. Signers are kept sorted, so every node derives the same address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    /// Number of co-signatures a transfer needs
+    pub threshold: u32,
+    /// Addresses allowed to co-sign, sorted and without duplicates
+    pub signers: Vec<Address>,
+}
+
+impl MultisigPolicy {
+    /// Create a policy, sorting and deduplicating the signers
+    pub fn new(threshold: u32, mut signers: Vec<Address>) -> Result<Self> {
+        signers.sort_by(|a, b| a.as_hex().cmp(b.as_hex()));
+        signers.dedup();
+        let policy = Self { threshold, signers };
+        policy.validate()?;
+        Ok(policy)
+    }
+    
+    /// Check the threshold and that the signers are in canonical order
+    pub fn validate(&self) -> Result<()> {
+        if self.signers.is_empty() || self.signers.len() > MAX_MULTISIG_SIGNERS {
+            return Err(ElysiumError::InvalidTransaction(
+                format!("A multisig account needs between 1 and {} signers", MAX_MULTISIG_SIGNERS)
+            ));
+        }
+        if self.threshold == 0 || self.threshold as usize > self.signers.len() {
+            return Err(ElysiumError::InvalidTransaction(
+                format!("Invalid multisig threshold {} of {}", self.threshold, self.signers.len())
+            ));
+        }
+        if self.signers.windows(2).any(|pair| pair[0].as_hex() >= pair[1].as_hex()) {
+            return Err(ElysiumError::InvalidTransaction(
+                "Multisig signers must be sorted and unique".to_string()
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Address of the account this policy controls
+    pub fn address(&self) -> Address {
+        let mut encoder = Encoder::new();
+        encoder.put_bytes(MULTISIG_ADDRESS_DOMAIN);
+        self.encode(&mut encoder);
+        let hash = Sha256::digest(encoder.into_bytes());
+        Address::from_hex(&hex::encode(hash)).expect("SHA-256 digests are valid addresses")
+    }
+    
+    /// Check that a transfer out of the account carries enough valid co-signatures
+    pub fn authorize(&self, tx: &Transaction) -> Result<()> {
+        let mut signed = HashSet::new();
+        for cosignature in &tx.cosignatures {
+            let signer = cosignature.verify(tx)?;
+            if !self.signers.contains(&signer) {
+                return Err(ElysiumError::InvalidTransaction(
+                    format!("{} is not a signer of this multisig account", signer)
+                ));
+            }
+            if !signed.insert(signer) {
+                return Err(ElysiumError::InvalidTransaction(
+                    "Duplicate co-signature".to_string()
+                ));
+            }
+        }
+        if signed.len() < self.threshold as usize {
+            return Err(ElysiumError::InvalidTransaction(
+                format!("Multisig transfer has {} of {} required co-signatures", signed.len(), self.threshold)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Signature of one multisig signer over a transaction's signing hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cosignature {
+    /// Signer's public key in hex format
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl Cosignature {
+    /// Co-sign a transaction
+    pub fn sign(signing_key: &SigningKey, tx: &Transaction) -> Self {
+        let signature = signing_key.sign(&tx.signing_hash_bytes());
+        Self {
+            public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+    
+    /// Check the signature against the transaction, returning the signer's address
+    pub fn verify(&self, tx: &Transaction) -> Result<Address> {
+        let key_bytes = hex::decode(&self.public_key)
+            .map_err(|_| ElysiumError::InvalidSignature)?;
+        let verifying_key = VerifyingKey::from_bytes(&key_bytes.try_into()
+            .map_err(|_| ElysiumError::InvalidSignature)?)
+            .map_err(|_| ElysiumError::InvalidSignature)?;
+        
+        let sig_bytes = hex::decode(&self.signature)
+            .map_err(|_| ElysiumError::InvalidSignature)?;
+        let signature = Signature::from_bytes(&sig_bytes.try_into()
+            .map_err(|_| ElysiumError::InvalidSignature)?);
+        
+        verifying_key.verify(&tx.signing_hash_bytes(), &signature)
+            .map_err(|_| ElysiumError::InvalidSignature)?;
+        
+        Ok(Address::from_verifying_key(&verifying_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    #[test]
+    fn test_policy_address_is_canonical() {
+        let a = KeyPair::generate().address();
+        let b = KeyPair::generate().address();
+        let policy = MultisigPolicy::new(2, vec![a.clone(), b.clone()]).unwrap();
+        let reversed = MultisigPolicy::new(2, vec![b.clone(), a.clone(), b.clone()]).unwrap();
+        
+        assert_eq!(policy, reversed);
+        assert_eq!(policy.address(), reversed.address());
+        assert_ne!(policy.address(), MultisigPolicy::new(1, vec![a.clone(), b.clone()]).unwrap().address());
+        
+        assert!(MultisigPolicy::new(0, vec![a.clone()]).is_err());
+        assert!(MultisigPolicy::new(3, vec![a.clone(), b.clone()]).is_err());
+        assert!(MultisigPolicy::new(1, Vec::new()).is_err());
+        let unsorted = MultisigPolicy { threshold: 1, signers: policy.signers.iter().rev().cloned().collect() };
+        assert!(unsorted.validate().is_err());
+    }
+    
+    #[test]
+    fn test_authorize_counts_distinct_signers() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mallory = KeyPair::generate();
+        let policy = MultisigPolicy::new(2, vec![alice.address(), bob.address()]).unwrap();
+        let mut tx = Transaction::multisig_transfer(&policy, mallory.address(), 10, 0);
+        
+        tx.cosign(alice.signing_key());
+        assert!(policy.authorize(&tx).is_err());
+        tx.cosign(alice.signing_key());
+        assert_eq!(tx.cosignatures.len(), 1);
+        
+        // Outsiders cannot make up the threshold
+        let mut outsider = tx.clone();
+        outsider.cosign(mallory.signing_key());
+        assert!(policy.authorize(&outsider).is_err());
+        
+        tx.cosign(bob.signing_key());
+        policy.authorize(&tx).unwrap();
+        tx.verify().unwrap();
+        
+        // Co-signatures cover the signed fields
+        tx.amount += 1;
+        assert!(policy.authorize(&tx).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::ops::{Div, Shl, Shr};
+use std::fmt;
+
+/// Compact form of the easiest allowed target, `0xffff * 256^29` (just under `2^248`)
+///
+/// A hash meets this target roughly when its first byte is zero, which matches
+/// difficulty 1 of the original leading-zero-bytes rule.
+pub const MAX_TARGET_BITS: u32 = 0x2000ffff;
+
+/// Largest factor by which one retarget may change the target
+pub const MAX_ADJUSTMENT_FACTOR: u64 = 4;
+
+/// Unsigned 256-bit integer for proof-of-work targets
+///
+/// Stored as four 64-bit limbs, most significant first, so the derived ordering is
+/// numeric ordering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256([u64; 4]);
+
+impl U256 {
+    pub const ZERO: U256 = U256([0; 4]);
+    pub const ONE: U256 = U256([0, 0, 0, 1]);
+    pub const MAX: U256 = U256([u64::MAX; 4]);
+    
+    pub fn from_u64(value: u64) -> Self {
+        U256([0, 0, 0, value])
+    }
+    
+    /// Interpret 32 bytes as a big-endian number
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::from_be_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+        }
+        U256(limbs)
+    }
+    
+    pub fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+    
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+    
+    /// Lowest 64 bits
+    pub fn low_u64(&self) -> u64 {
+        self.0[3]
+    }
+    
+    /// Value as `u64`, or `u64::MAX` if it does not fit
+    pub fn saturating_to_u64(&self) -> u64 {
+        if self.0[..3].iter().any(|&limb| limb != 0) {
+            u64::MAX
+        } else {
+            self.0[3]
+        }
+    }
+    
+    /// Value as `u128`, or `u128::MAX` if it does not fit
+    pub fn saturating_to_u128(&self) -> u128 {
+        if self.0[0] != 0 || self.0[1] != 0 {
+            u128::MAX
+        } else {
+            ((self.0[2] as u128) << 64) | self.0[3] as u128
+        }
+    }
+    
+    /// Number of significant bits
+    pub fn bits(&self) -> u32 {
+        for (i, limb) in self.0.iter().enumerate() {
+            if *limb != 0 {
+                return 64 * (4 - i as u32) - limb.leading_zeros();
+            }
+        }
+        0
+    }
+    
+    fn shift_left(self, shift: u32) -> Self {
+        if shift >= 256 {
+            return U256::ZERO;
+        }
+        let mut bytes = [0u8; 32];
+        let source = self.to_be_bytes();
+        let byte_shift = (shift / 8) as usize;
+        let bit_shift = shift % 8;
+        for i in 0..32 - byte_shift {
+            let mut value = (source[i + byte_shift] as u16) << bit_shift;
+            if bit_shift > 0 && i + byte_shift + 1 < 32 {
+                value |= (source[i + byte_shift + 1] as u16) >> (8 - bit_shift);
+            }
+            bytes[i] = value as u8;
+        }
+        U256::from_be_bytes(bytes)
+    }
+    
+    fn shift_right(self, shift: u32) -> Self {
+        if shift >= 256 {
+            return U256::ZERO;
+        }
+        let mut bytes = [0u8; 32];
+        let source = self.to_be_bytes();
+        let byte_shift = (shift / 8) as usize;
+        let bit_shift = shift % 8;
+        for i in byte_shift..32 {
+            let mut value = (source[i - byte_shift] as u16) >> bit_shift;
+            if bit_shift > 0 && i > byte_shift {
+                value |= ((source[i - byte_shift - 1] as u16) << (8 - bit_shift)) & 0xff;
+            }
+            bytes[i] = value as u8;
+        }
+        U256::from_be_bytes(bytes)
+    }
+    
+    fn overflowing_add(self, other: U256) -> (U256, bool) {
+        let mut result = [0u64; 4];
+        let mut carry = false;
+        for i in (0..4).rev() {
+            let (sum, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, c2) = sum.overflowing_add(carry as u64);
+            result[i] = sum;
+            carry = c1 || c2;
+        }
+        (U256(result), carry)
+    }
+    
+    fn wrapping_sub(self, other: U256) -> U256 {
+        let mut result = [0u64; 4];
+        let mut borrow = false;
+        for i in (0..4).rev() {
+            let (diff, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            result[i] = diff;
+            borrow = b1 || b2;
+        }
+        U256(result)
+    }
+    
+    pub fn saturating_add(self, other: U256) -> U256 {
+        match self.overflowing_add(other) {
+            (sum, false) => sum,
+            (_, true) => U256::MAX,
+        }
+    }
+    
+    /// Multiply by a `u64`, returning `None` on overflow
+    pub fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut result = [0u64; 4];
+        let mut carry = 0u128;
+        for i in (0..4).rev() {
+            let product = self.0[i] as u128 * factor as u128 + carry;
+            result[i] = product as u64;
+            carry = product >> 64;
+        }
+        if carry != 0 {
+            return None;
+        }
+        Some(U256(result))
+    }
+    
+    /// Divide by a non-zero `u64`
+    pub fn div_u64(self, divisor: u64) -> U256 {
+        assert!(divisor != 0, "division by zero");
+        let mut result = [0u64; 4];
+        let mut remainder = 0u128;
+        for (limb, out) in self.0.iter().zip(result.iter_mut()) {
+            let current = (remainder << 64) | *limb as u128;
+            *out = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        U256(result)
+    }
+    
+    /// Divide by a non-zero `U256` (binary long division)
+    fn long_div(self, divisor: U256) -> U256 {
+        assert!(!divisor.is_zero(), "division by zero");
+        let mut quotient = U256::ZERO;
+        let mut remainder = U256::ZERO;
+        for bit in (0..self.bits()).rev() {
+            // The shift may carry out of 256 bits when the divisor is above 2^255
+            let carry = remainder.0[0] >> 63 == 1;
+            remainder = remainder.shift_left(1);
+            if self.shift_right(bit).low_u64() & 1 == 1 {
+                remainder.0[3] |= 1;
+            }
+            if carry || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient = quotient.overflowing_add(U256::ONE.shift_left(bit)).0;
+            }
+        }
+        quotient
+    }
+    
+    /// Decode the compact "bits" form: a one-byte exponent followed by a three-byte
+    /// mantissa, `mantissa * 256^(exponent - 3)`
+    ///
+    /// Returns `None` for negative, zero or overflowing encodings.
+    pub fn from_compact(bits: u32) -> Option<U256> {
+        let exponent = bits >> 24;
+        let mantissa = bits & 0x007f_ffff;
+        if bits & 0x0080_0000 != 0 || mantissa == 0 {
+            return None;
+        }
+        
+        let target = if exponent <= 3 {
+            U256::from_u64((mantissa >> (8 * (3 - exponent))) as u64)
+        } else {
+            let shift = 8 * (exponent - 3);
+            if U256::from_u64(mantissa as u64).bits() + shift > 256 {
+                return None;
+            }
+            U256::from_u64(mantissa as u64).shift_left(shift)
+        };
+        
+        if target.is_zero() {
+            return None;
+        }
+        Some(target)
+    }
+    
+    /// Encode as compact "bits", rounding down to three significant bytes
+    pub fn to_compact(&self) -> u32 {
+        let mut size = self.bits().div_ceil(8);
+        let mut mantissa = if size <= 3 {
+            (self.low_u64() << (8 * (3 - size))) as u32
+        } else {
+            self.shift_right(8 * (size - 3)).low_u64() as u32
+        };
+        // The top mantissa bit is a sign bit; keep it clear
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+        (size << 24) | mantissa
+    }
+}
+
+impl Shl<u32> for U256 {
+    type Output = U256;
+    
+    fn shl(self, shift: u32) -> U256 {
+        self.shift_left(shift)
+    }
+}
+
+impl Shr<u32> for U256 {
+    type Output = U256;
+    
+    fn shr(self, shift: u32) -> U256 {
+        self.shift_right(shift)
+    }
+}
+
+impl Div for U256 {
+    type Output = U256;
+    
+    /// Panics if `divisor` is zero
+    fn div(self, divisor: U256) -> U256 {
+        self.long_div(divisor)
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_be_bytes()))
+    }
+}
+
+/// The easiest allowed target
+pub fn max_target() -> U256 {
+    U256::from_compact(MAX_TARGET_BITS).expect("valid maximum target")
+}
+
+/// Compact target for a difficulty, where difficulty 1 is the maximum target
+pub fn bits_for_difficulty(difficulty: u64) -> u32 {
+    max_target().div_u64(difficulty.max(1)).to_compact()
+}
+
+/// Difficulty of a compact target relative to the maximum target
+pub fn difficulty_for_bits(bits: u32) -> u64 {
+    match U256::from_compact(bits) {
+        Some(target) => max_target().long_div(target).saturating_to_u64(),
+        None => 0,
+    }
+}
+
+/// Expected number of hashes needed to meet a compact target: `2^256 / (target + 1)`
+pub fn work_for_bits(bits: u32) -> u128 {
+    match U256::from_compact(bits) {
+        // 2^256 / (t + 1) == (2^256 - 1 - t) / (t + 1) + 1
+        Some(target) => {
+            let not_target = U256::MAX.wrapping_sub(target);
+            not_target.long_div(target.saturating_add(U256::ONE))
+                .saturating_add(U256::ONE)
+                .saturating_to_u128()
+        }
+        None => 0,
+    }
+}
+
+/// How the target adapts to the observed block rate
+///
+/// Every `window` blocks the target is scaled by how long the last window actually
+/// took compared with `window * target_block_time`, limited to a factor of
+/// [`MAX_ADJUSTMENT_FACTOR`] either way. A window of zero disables retargeting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetargetConfig {
+    /// Desired time between blocks in seconds
+    pub target_block_time: u64,
+    /// Number of blocks between retargets
+    pub window: u64,
+}
+
+impl Default for RetargetConfig {
+    fn default() -> Self {
+        Self {
+            target_block_time: 10,
+            window: 60,
+        }
+    }
+}
+
+impl RetargetConfig {
+    /// A configuration that keeps the initial target forever
+    pub fn disabled() -> Self {
+        Self {
+            target_block_time: 0,
+            window: 0,
+        }
+    }
+    
+    /// Whether the block at `height` starts a new window and gets a new target
+    pub fn is_retarget_height(&self, height: u64) -> bool {
+        self.window > 0 && self.target_block_time > 0 && height > 0 && height.is_multiple_of(self.window)
+    }
+    
+    /// Target for a new window, given the previous target and the time the
+    /// previous window took
+    pub fn next_bits(&self, previous_bits: u32, actual_timespan: u64) -> u32 {
+        let previous = match U256::from_compact(previous_bits) {
+            Some(target) => target,
+            None => return previous_bits,
+        };
+        
+        let expected = self.window.saturating_mul(self.target_block_time).max(1);
+        let actual = actual_timespan.clamp(
+            (expected / MAX_ADJUSTMENT_FACTOR).max(1),
+            expected.saturating_mul(MAX_ADJUSTMENT_FACTOR),
+        );
+        
+        // Divide first: targets are far larger than the timespans
+        let next = previous.div_u64(expected)
+            .checked_mul_u64(actual)
+            .unwrap_or(U256::MAX)
+            .min(max_target());
+        if next.is_zero() {
+            return U256::ONE.to_compact();
+        }
+        next.to_compact()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_compact_roundtrip() {
+        assert_eq!(max_target().to_compact(), MAX_TARGET_BITS);
+        assert_eq!(U256::from_compact(0x1d00ffff).unwrap().to_compact(), 0x1d00ffff);
+        assert_eq!(U256::from_compact(0x03123456).unwrap(), U256::from_u64(0x123456));
+        assert_eq!(U256::from_u64(0x80).to_compact(), 0x02008000);
+        
+        // Negative, zero and overflowing encodings are rejected
+        assert!(U256::from_compact(0x04923456).is_none());
+        assert!(U256::from_compact(0x20000000).is_none());
+        assert!(U256::from_compact(0x23ffffff).is_none());
+    }
+    
+    #[test]
+    fn test_arithmetic() {
+        let value = U256::from_u64(u64::MAX) << 100;
+        assert_eq!(value >> 100, U256::from_u64(u64::MAX));
+        assert_eq!(value.bits(), 164);
+        assert_eq!(value / U256::from_u64(u64::MAX), U256::ONE << 100);
+        assert_eq!(value.div_u64(2), value >> 1);
+        assert_eq!(U256::MAX / U256::MAX, U256::ONE);
+        assert_eq!(U256::MAX / (U256::ONE << 255), U256::ONE);
+        assert!(U256::MAX.checked_mul_u64(2).is_none());
+    }
+    
+    #[test]
+    fn test_difficulty_scale_is_linear() {
+        assert_eq!(difficulty_for_bits(MAX_TARGET_BITS), 1);
+        assert_eq!(difficulty_for_bits(bits_for_difficulty(1000)), 1000);
+        
+        // Difficulty 1 needs about 256 hashes, like one leading zero byte did
+        let work = work_for_bits(MAX_TARGET_BITS);
+        assert!((256..=257).contains(&work));
+        assert!(work_for_bits(bits_for_difficulty(2)) >= 2 * work - 1);
+    }
+    
+    #[test]
+    fn test_retarget_follows_block_time() {
+        let config = RetargetConfig { target_block_time: 10, window: 10 };
+        let bits = bits_for_difficulty(100);
+        
+        // On schedule: unchanged
+        assert_eq!(difficulty_for_bits(config.next_bits(bits, 100)), 100);
+        // Twice as fast: twice as hard
+        assert_eq!(difficulty_for_bits(config.next_bits(bits, 50)), 200);
+        // Far too slow: at most four times easier
+        assert_eq!(difficulty_for_bits(config.next_bits(bits, 10_000)), 25);
+        // Never easier than the maximum target
+        assert_eq!(config.next_bits(MAX_TARGET_BITS, 10_000), MAX_TARGET_BITS);
+        
+        assert!(config.is_retarget_height(20));
+        assert!(!config.is_retarget_height(21));
+        assert!(!RetargetConfig::disabled().is_retarget_height(20));
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// Block reward schedule
+///
+/// The block at height `h` may mint `initial_reward >> (h / halving_interval)` new
+/// coins, so the reward halves every `halving_interval` blocks until it reaches zero.
+/// The genesis block mints nothing; its balances come from the genesis allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardSchedule {
+    /// Reward of the first blocks
+    pub initial_reward: u64,
+    /// Number of blocks between halvings
+    pub halving_interval: u64,
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        Self {
+            initial_reward: 50,
+            halving_interval: 100_000,
+        }
+    }
+}
+
+impl RewardSchedule {
+    /// New coins the block at `height` may mint
+    pub fn reward_at(&self, height: u64) -> u64 {
+        if height == 0 || self.halving_interval == 0 {
+            return 0;
+        }
+        let halvings = height / self.halving_interval;
+        if halvings >= u64::BITS as u64 {
+            return 0;
+        }
+        self.initial_reward >> halvings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reward_halves() {
+        let schedule = RewardSchedule { initial_reward: 50, halving_interval: 10 };
+        assert_eq!(schedule.reward_at(0), 0);
+        assert_eq!(schedule.reward_at(1), 50);
+        assert_eq!(schedule.reward_at(9), 50);
+        assert_eq!(schedule.reward_at(10), 25);
+        assert_eq!(schedule.reward_at(20), 12);
+        assert_eq!(schedule.reward_at(10 * 64), 0);
+        assert_eq!(schedule.reward_at(u64::MAX), 0);
+    }
+}
@@ -0,0 +1,344 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::codec::{Encode, Encoder};
+use crate::{Account, Address, BlockHeader};
+
+/// Prefix for hashing a leaf, so a leaf can never be mistaken for an inner node
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix for hashing an inner node
+const NODE_PREFIX: u8 = 0x01;
+
+/// Number of bits in a key, and so the maximum depth of the tree
+const KEY_BITS: usize = 256;
+
+/// Hash of an empty subtree
+const EMPTY: Hash = [0u8; 32];
+
+type Hash = [u8; 32];
+
+/// Position of an account in the tree: the SHA-256 of its address bytes
+fn account_key(address: &Address) -> Hash {
+    Sha256::digest(address.as_bytes()).into()
+}
+
+/// Commitment to an account's contents
+///
+/// The multisig policy, time locks, contract code and storage are only appended when
+/// the account has any of them, so plain accounts keep the commitment they had before
+/// those existed.
+fn account_value_hash(account: &Account) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(account.address.as_bytes());
+    hasher.update(account.balance.to_be_bytes());
+    hasher.update(account.nonce.to_be_bytes());
+    if account.multisig.is_some() || !account.locks.is_empty() || account.is_contract() || !account.storage.is_empty() {
+        let mut encoder = Encoder::new();
+        match &account.multisig {
+            Some(policy) => {
+                encoder.put_u8(1);
+                policy.encode(&mut encoder);
+            }
+            None => encoder.put_u8(0),
+        }
+        encoder.put_seq(&account.locks);
+        encoder.put_hex(&account.code);
+        encoder.put_u32(account.storage.len() as u32);
+        for (key, value) in &account.storage {
+            encoder.put_u64(*key);
+            encoder.put_u64(*value);
+        }
+        hasher.update(encoder.into_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn hash_leaf(key: &Hash, value_hash: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(key);
+    hasher.update(value_hash);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Bit of `key` at `depth`, counting from the most significant bit
+fn bit(key: &Hash, depth: usize) -> u8 {
+    (key[depth / 8] >> (7 - depth % 8)) & 1
+}
+
+fn decode_hash(hex_hash: &str) -> Option<Hash> {
+    hex::decode(hex_hash).ok()?.try_into().ok()
+}
+
+#[derive(Debug, Clone)]
+struct Leaf {
+    key: Hash,
+    value_hash: Hash,
+    account: Account,
+}
+
+/// Hash of the subtree holding `leaves`, which all share their first `depth` key bits
+fn subtree_hash(leaves: &[Leaf], depth: usize) -> Hash {
+    match leaves {
+        [] => EMPTY,
+        [leaf] => hash_leaf(&leaf.key, &leaf.value_hash),
+        _ => {
+            let (left, right) = split(leaves, depth);
+            hash_node(&subtree_hash(left, depth + 1), &subtree_hash(right, depth + 1))
+        }
+    }
+}
+
+/// Split sorted leaves on the key bit at `depth`
+fn split(leaves: &[Leaf], depth: usize) -> (&[Leaf], &[Leaf]) {
+    let index = leaves.partition_point(|leaf| bit(&leaf.key, depth) == 0);
+    leaves.split_at(index)
+}
+
+/// Sparse Merkle tree over all accounts, keyed by the hash of their address
+///
+/// Conceptually a binary tree of depth 256 with one slot per possible key. Empty
+/// subtrees hash to 32 zero bytes, and a subtree holding a single account is
+/// represented by that account's leaf hash, so only the populated part of the tree
+/// is ever computed. Leaves are `SHA-256(0x00 || key || value)` and inner nodes
+/// `SHA-256(0x01 || left || right)`, where the value commits to the address,
+/// balance and nonce of the account.
+#[derive(Debug, Clone)]
+pub struct StateTree {
+    /// Leaves sorted by key
+    leaves: Vec<Leaf>,
+}
+
+impl StateTree {
+    /// Build the tree from a set of accounts
+    pub fn from_accounts<'a>(accounts: impl IntoIterator<Item = &'a Account>) -> Self {
+        let mut leaves: Vec<Leaf> = accounts.into_iter()
+            .map(|account| Leaf {
+                key: account_key(&account.address),
+                value_hash: account_value_hash(account),
+                account: account.clone(),
+            })
+            .collect();
+        leaves.sort_by_key(|leaf| leaf.key);
+        leaves.dedup_by(|a, b| a.key == b.key);
+        Self { leaves }
+    }
+    
+    /// Hex-encoded root hash
+    pub fn root(&self) -> String {
+        hex::encode(subtree_hash(&self.leaves, 0))
+    }
+    
+    /// Build a proof of the state of `address`, which may not exist
+    pub fn proof(&self, address: &Address) -> AccountProof {
+        let key = account_key(address);
+        let mut siblings = Vec::new();
+        let mut leaves = self.leaves.as_slice();
+        let mut depth = 0;
+        
+        while leaves.len() > 1 && depth < KEY_BITS {
+            let (left, right) = split(leaves, depth);
+            if bit(&key, depth) == 0 {
+                siblings.push(hex::encode(subtree_hash(right, depth + 1)));
+                leaves = left;
+            } else {
+                siblings.push(hex::encode(subtree_hash(left, depth + 1)));
+                leaves = right;
+            }
+            depth += 1;
+        }
+        
+        let (account, other_leaf) = match leaves.first() {
+            Some(leaf) if leaf.key == key => (Some(leaf.account.clone()), None),
+            Some(leaf) => (None, Some(ProofLeaf {
+                key: hex::encode(leaf.key),
+                value_hash: hex::encode(leaf.value_hash),
+            })),
+            None => (None, None),
+        };
+        
+        AccountProof {
+            address: address.clone(),
+            account,
+            siblings,
+            other_leaf,
+        }
+    }
+}
+
+/// A leaf of another account, which ends the path to an absent account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofLeaf {
+    pub key: String,
+    pub value_hash: String,
+}
+
+/// Proof of an account's state (or of its absence) against a state root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountProof {
+    pub address: Address,
+    /// The account, or `None` if it does not exist
+    pub account: Option<Account>,
+    /// Hex-encoded sibling hashes, from the root downwards
+    pub siblings: Vec<String>,
+    /// For an absent account whose path ends at another account's leaf, that leaf
+    pub other_leaf: Option<ProofLeaf>,
+}
+
+impl AccountProof {
+    /// Verify the proof against a hex-encoded state root
+    pub fn verify(&self, state_root: &str) -> bool {
+        let key = account_key(&self.address);
+        let depth = self.siblings.len();
+        if depth > KEY_BITS {
+            return false;
+        }
+        
+        let terminal = match (&self.account, &self.other_leaf) {
+            (Some(account), None) => {
+                if account.address != self.address {
+                    return false;
+                }
+                hash_leaf(&key, &account_value_hash(account))
+            }
+            (None, Some(leaf)) => {
+                let (other_key, value_hash) = match (decode_hash(&leaf.key), decode_hash(&leaf.value_hash)) {
+                    (Some(other_key), Some(value_hash)) => (other_key, value_hash),
+                    _ => return false,
+                };
+                // The other leaf must sit on our path, at the point where it ends
+                if other_key == key || (0..depth).any(|d| bit(&other_key, d) != bit(&key, d)) {
+                    return false;
+                }
+                hash_leaf(&other_key, &value_hash)
+            }
+            (None, None) => EMPTY,
+            (Some(_), Some(_)) => return false,
+        };
+        
+        let mut current = terminal;
+        for (d, sibling) in self.siblings.iter().enumerate().rev() {
+            let sibling = match decode_hash(sibling) {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            current = if bit(&key, d) == 0 {
+                hash_node(&current, &sibling)
+            } else {
+                hash_node(&sibling, &current)
+            };
+        }
+        
+        hex::encode(current) == state_root
+    }
+}
+
+/// Proof of an account's state as of a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateProof {
+    pub block_hash: String,
+    /// Header of the block, which commits to the state root
+    pub header: BlockHeader,
+    pub proof: AccountProof,
+}
+
+impl StateProof {
+    /// Check that the header matches the block hash and the proof matches its state root
+    pub fn verify(&self) -> bool {
+        self.header.hash() == self.block_hash && self.proof.verify(&self.header.state_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn accounts(count: u64) -> Vec<Account> {
+        (0..count)
+            .map(|i| {
+                let mut account = Account::new(KeyPair::generate().address());
+                account.balance = i * 10;
+                account.nonce = i;
+                account
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_root_is_independent_of_order() {
+        let mut list = accounts(20);
+        let root = StateTree::from_accounts(&list).root();
+        list.reverse();
+        assert_eq!(StateTree::from_accounts(&list).root(), root);
+        
+        // Any change to an account changes the root
+        list[3].balance += 1;
+        assert_ne!(StateTree::from_accounts(&list).root(), root);
+    }
+    
+    #[test]
+    fn test_inclusion_proofs() {
+        let list = accounts(25);
+        let tree = StateTree::from_accounts(&list);
+        let root = tree.root();
+        
+        for account in &list {
+            let proof = tree.proof(&account.address);
+            assert_eq!(proof.account.as_ref().unwrap().balance, account.balance);
+            assert!(proof.verify(&root));
+        }
+        
+        // A proof with a forged balance fails
+        let mut forged = tree.proof(&list[7].address);
+        forged.account.as_mut().unwrap().balance += 1000;
+        assert!(!forged.verify(&root));
+    }
+    
+    #[test]
+    fn test_absence_proofs() {
+        let list = accounts(25);
+        let tree = StateTree::from_accounts(&list);
+        let root = tree.root();
+        
+        for _ in 0..20 {
+            let missing = KeyPair::generate().address();
+            let proof = tree.proof(&missing);
+            assert!(proof.account.is_none());
+            assert!(proof.verify(&root));
+            
+            // Claiming the absent account exists fails
+            let mut forged = proof.clone();
+            forged.account = Some(Account::new(missing));
+            forged.other_leaf = None;
+            assert!(!forged.verify(&root));
+        }
+        
+        // An existing account cannot be proven absent
+        let mut forged = tree.proof(&list[0].address);
+        forged.account = None;
+        assert!(!forged.verify(&root));
+    }
+    
+    #[test]
+    fn test_empty_and_single_account_trees() {
+        let empty = StateTree::from_accounts(&[]);
+        assert_eq!(empty.root(), "0".repeat(64));
+        let address = KeyPair::generate().address();
+        assert!(empty.proof(&address).verify(&empty.root()));
+        
+        let list = accounts(1);
+        let tree = StateTree::from_accounts(&list);
+        let proof = tree.proof(&list[0].address);
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&tree.root()));
+    }
+}
@@ -0,0 +1,360 @@
+use serde::{Deserialize, Serialize};
+use crate::{Account, Address, Block, ConsensusSpec, ExecutionStatus, RetargetConfig, Result, ElysiumError};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const BLOCK_LOG_FILE: &str = "blocks.log";
+const STATE_FILE: &str = "state.json";
+const CONSENSUS_FILE: &str = "consensus.json";
+const RETARGET_FILE: &str = "retarget.json";
+
+/// Account states from before a block was applied, used to roll the block back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoRecord {
+    pub block_hash: String,
+    /// Previous state of every account the block touched (`None` if it did not exist)
+    pub accounts: Vec<(Address, Option<Account>)>,
+}
+
+/// Snapshot of the account state after applying the block at `height - 1`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Number of blocks the snapshot covers
+    pub height: u64,
+    /// Hash of the last block covered by the snapshot
+    pub tip_hash: String,
+    pub accounts: Vec<Account>,
+    /// Undo records of the most recent main-chain blocks
+    #[serde(default)]
+    pub undo: Vec<UndoRecord>,
+    /// Outcomes of the deploys and calls on the main chain, by transaction hash
+    #[serde(default)]
+    pub executions: Vec<(String, ExecutionStatus)>,
+}
+
+/// Storage backend for the chain: an append-only block log plus an account-state snapshot
+///
+/// The block log holds every accepted block, including blocks on side branches, in an
+/// order where each block follows its parent.
+pub trait Storage: Send + Sync + fmt::Debug {
+    /// Append a block to the block log
+    fn append_block(&self, block: &Block) -> Result<()>;
+    
+    /// Load all blocks in the order they were appended
+    fn load_blocks(&self) -> Result<Vec<Block>>;
+    
+    /// Replace the stored account-state snapshot
+    fn save_state(&self, snapshot: &StateSnapshot) -> Result<()>;
+    
+    /// Load the stored account-state snapshot, if any
+    fn load_state(&self) -> Result<Option<StateSnapshot>>;
+    
+    /// Record the consensus engine the chain was created with
+    fn save_consensus(&self, consensus: &ConsensusSpec) -> Result<()>;
+    
+    /// Load the recorded consensus engine, if any
+    fn load_consensus(&self) -> Result<Option<ConsensusSpec>>;
+    
+    /// Record the difficulty retargeting rule the chain was created with
+    fn save_retarget(&self, retarget: &RetargetConfig) -> Result<()>;
+    
+    /// Load the recorded difficulty retargeting rule, if any
+    fn load_retarget(&self) -> Result<Option<RetargetConfig>>;
+}
+
+fn storage_error(e: impl fmt::Display) -> ElysiumError {
+    ElysiumError::StorageError(e.to_string())
+}
+
+/// In-memory storage, mainly for tests
+///
+/// Clones share the same underlying data, so a clone can be used to "restart" a chain.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    blocks: Arc<Mutex<Vec<Block>>>,
+    state: Arc<Mutex<Option<StateSnapshot>>>,
+    consensus: Arc<Mutex<Option<ConsensusSpec>>>,
+    retarget: Arc<Mutex<Option<RetargetConfig>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn append_block(&self, block: &Block) -> Result<()> {
+        self.blocks.lock().map_err(storage_error)?.push(block.clone());
+        Ok(())
+    }
+    
+    fn load_blocks(&self) -> Result<Vec<Block>> {
+        Ok(self.blocks.lock().map_err(storage_error)?.clone())
+    }
+    
+    fn save_state(&self, snapshot: &StateSnapshot) -> Result<()> {
+        *self.state.lock().map_err(storage_error)? = Some(snapshot.clone());
+        Ok(())
+    }
+    
+    fn load_state(&self) -> Result<Option<StateSnapshot>> {
+        Ok(self.state.lock().map_err(storage_error)?.clone())
+    }
+    
+    fn save_consensus(&self, consensus: &ConsensusSpec) -> Result<()> {
+        *self.consensus.lock().map_err(storage_error)? = Some(consensus.clone());
+        Ok(())
+    }
+    
+    fn load_consensus(&self) -> Result<Option<ConsensusSpec>> {
+        Ok(self.consensus.lock().map_err(storage_error)?.clone())
+    }
+    
+    fn save_retarget(&self, retarget: &RetargetConfig) -> Result<()> {
+        *self.retarget.lock().map_err(storage_error)? = Some(*retarget);
+        Ok(())
+    }
+    
+    fn load_retarget(&self) -> Result<Option<RetargetConfig>> {
+        Ok(*self.retarget.lock().map_err(storage_error)?)
+    }
+}
+
+/// File-based storage in a data directory
+///
+/// Blocks are appended to `blocks.log` as one JSON document per line; the account
+/// state is written to `state.json`, the consensus engine to `consensus.json` and the
+/// retargeting rule to `retarget.json`, each via a temporary file and an atomic rename.
+#[derive(Debug)]
+pub struct FileStorage {
+    dir: PathBuf,
+    // Serializes appends to the block log
+    log_lock: Mutex<()>,
+}
+
+impl FileStorage {
+    /// Open (and create if needed) a storage directory
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| ElysiumError::StorageError(
+            format!("Failed to create data directory {}: {}", dir.display(), e)
+        ))?;
+        Ok(Self {
+            dir,
+            log_lock: Mutex::new(()),
+        })
+    }
+    
+    /// Get the data directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    /// Replace a file in the data directory via a temporary file and an atomic rename
+    fn write_file(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        let mut file = fs::File::create(&tmp_path).map_err(storage_error)?;
+        file.write_all(bytes).map_err(storage_error)?;
+        file.sync_data().map_err(storage_error)?;
+        fs::rename(&tmp_path, self.dir.join(name)).map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    fn append_block(&self, block: &Block) -> Result<()> {
+        let _guard = self.log_lock.lock().map_err(storage_error)?;
+        let mut line = serde_json::to_vec(block)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))?;
+        line.push(b'\n');
+        
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(BLOCK_LOG_FILE))
+            .map_err(storage_error)?;
+        file.write_all(&line).map_err(storage_error)?;
+        file.sync_data().map_err(storage_error)?;
+        Ok(())
+    }
+    
+    fn load_blocks(&self) -> Result<Vec<Block>> {
+        let path = self.dir.join(BLOCK_LOG_FILE);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read(&path).map_err(storage_error)?;
+        
+        // A record is complete once its newline is written; anything after the last
+        // newline is a torn record from a crash during append
+        let complete = contents.iter().rposition(|&byte| byte == b'\n').map_or(0, |end| end + 1);
+        let mut blocks = Vec::new();
+        for line in contents[..complete].split(|&byte| byte == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let block = serde_json::from_slice(line).map_err(|e| ElysiumError::StorageError(
+                format!("Corrupted block log {}: {}", path.display(), e)
+            ))?;
+            blocks.push(block);
+        }
+        
+        // Cut the torn record off, so the next append starts on a fresh line
+        if complete < contents.len() {
+            let _guard = self.log_lock.lock().map_err(storage_error)?;
+            let file = OpenOptions::new().write(true).open(&path).map_err(storage_error)?;
+            file.set_len(complete as u64).map_err(storage_error)?;
+            file.sync_data().map_err(storage_error)?;
+        }
+        Ok(blocks)
+    }
+    
+    fn save_state(&self, snapshot: &StateSnapshot) -> Result<()> {
+        let bytes = serde_json::to_vec(snapshot)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))?;
+        self.write_file(STATE_FILE, &bytes)
+    }
+    
+    fn load_state(&self) -> Result<Option<StateSnapshot>> {
+        let path = self.dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).map_err(storage_error)?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| ElysiumError::StorageError(
+                format!("Corrupted state snapshot {}: {}", path.display(), e)
+            ))
+    }
+    
+    fn save_consensus(&self, consensus: &ConsensusSpec) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(consensus)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))?;
+        self.write_file(CONSENSUS_FILE, &bytes)
+    }
+    
+    fn load_consensus(&self) -> Result<Option<ConsensusSpec>> {
+        let path = self.dir.join(CONSENSUS_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).map_err(storage_error)?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| ElysiumError::StorageError(
+                format!("Corrupted consensus record {}: {}", path.display(), e)
+            ))
+    }
+    
+    fn save_retarget(&self, retarget: &RetargetConfig) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(retarget)
+            .map_err(|e| ElysiumError::SerializationError(e.to_string()))?;
+        self.write_file(RETARGET_FILE, &bytes)
+    }
+    
+    fn load_retarget(&self) -> Result<Option<RetargetConfig>> {
+        let path = self.dir.join(RETARGET_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path).map_err(storage_error)?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| ElysiumError::StorageError(
+                format!("Corrupted retarget record {}: {}", path.display(), e)
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::KeyPair;
+    
+    fn mined_block(number: u64, parent_hash: String) -> Block {
+        let mut block = Block::new(number, parent_hash, 1);
+        block.mine();
+        block
+    }
+    
+    #[test]
+    fn test_memory_storage_roundtrip() {
+        let storage = MemoryStorage::new();
+        let block = mined_block(0, "0".repeat(64));
+        storage.append_block(&block).unwrap();
+        
+        let restarted = storage.clone();
+        let blocks = restarted.load_blocks().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].hash(), block.hash());
+        assert!(restarted.load_state().unwrap().is_none());
+        assert!(restarted.load_consensus().unwrap().is_none());
+        assert!(restarted.load_retarget().unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_file_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = mined_block(0, "0".repeat(64));
+        let next = mined_block(1, genesis.hash());
+        
+        let keypair = KeyPair::generate();
+        let mut account = Account::new(keypair.address());
+        account.balance = 42;
+        
+        {
+            let storage = FileStorage::open(dir.path()).unwrap();
+            storage.append_block(&genesis).unwrap();
+            storage.append_block(&next).unwrap();
+            storage.save_state(&StateSnapshot {
+                height: 2,
+                tip_hash: next.hash(),
+                accounts: vec![account],
+                undo: Vec::new(),
+                executions: Vec::new(),
+            }).unwrap();
+            storage.save_consensus(&ConsensusSpec::ProofOfAuthority { validators: vec![keypair.address()] }).unwrap();
+            storage.save_retarget(&RetargetConfig { target_block_time: 30, window: 20 }).unwrap();
+        }
+        
+        let storage = FileStorage::open(dir.path()).unwrap();
+        let blocks = storage.load_blocks().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].hash(), next.hash());
+        
+        let state = storage.load_state().unwrap().unwrap();
+        assert_eq!(state.height, 2);
+        assert_eq!(state.accounts[0].balance, 42);
+        assert_eq!(
+            storage.load_consensus().unwrap(),
+            Some(ConsensusSpec::ProofOfAuthority { validators: vec![keypair.address()] })
+        );
+        assert_eq!(storage.load_retarget().unwrap(), Some(RetargetConfig { target_block_time: 30, window: 20 }));
+    }
+    
+    #[test]
+    fn test_file_storage_ignores_torn_last_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileStorage::open(dir.path()).unwrap();
+        storage.append_block(&mined_block(0, "0".repeat(64))).unwrap();
+        
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(BLOCK_LOG_FILE))
+            .unwrap();
+        file.write_all(b"{\"header\":{\"num").unwrap();
+        
+        assert_eq!(storage.load_blocks().unwrap().len(), 1);
+        
+        // Appending after the torn record leaves a readable log
+        let genesis = storage.load_blocks().unwrap().remove(0);
+        storage.append_block(&mined_block(1, genesis.hash())).unwrap();
+        let reopened = FileStorage::open(dir.path()).unwrap();
+        assert_eq!(reopened.load_blocks().unwrap().len(), 2);
+    }
+}
@@ -1,13 +1,34 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use ed25519_dalek::{VerifyingKey, Signature, Verifier, SigningKey, Signer};
-use crate::{Address, Result, ElysiumError};
+use crate::{contract_address, Address, Cosignature, MultisigPolicy, Result, ElysiumError, DEV_CHAIN_ID};
+use crate::codec::{self, Encode, Encoder};
 use std::fmt;
 
+/// Tag prefixed to the signed payload, so a transaction signature can never be
+/// valid as a signature over any other kind of message
+pub const SIGNING_DOMAIN: &[u8] = b"elysium-transaction";
+
 /// Transaction type
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionType {
     Transfer,
+    /// Block reward plus the fees of the block, credited to the miner; the first
+    /// transaction of a block, unsigned
+    Coinbase,
+    /// Transfer the receiver cannot spend before the block at `unlock_height`
+    TimeLocked { unlock_height: u64 },
+    /// Register the receiver, which must be the policy's address, as a multisig
+    /// account, funding it with the amount
+    CreateMultisig(MultisigPolicy),
+    /// Transfer out of a multisig account, authorized by its signers' co-signatures
+    /// instead of a sender signature
+    MultisigTransfer,
+    /// Store the hex-encoded `code` as a contract at the receiver, which must be the
+    /// [`contract_address`] of the sender and nonce
+    Deploy { code: String, gas_limit: u64, gas_price: u64 },
+    /// Run the receiver's code with `input`; the amount is only sent if it succeeds
+    Call { input: Vec<u64>, gas_limit: u64, gas_price: u64 },
 }
 
 /// Transaction in the blockchain
@@ -16,12 +37,21 @@ pub struct Transaction {
     pub from: Address,
     pub to: Address,
     pub amount: u64,
+    /// Fee paid to the miner of the block, on top of `amount`
+    #[serde(default)]
+    pub fee: u64,
     pub nonce: u64,
     pub transaction_type: TransactionType,
+    /// Network the transaction is valid on; see [`GenesisSpec`](crate::GenesisSpec)
+    #[serde(default)]
+    pub chain_id: u64,
     pub signature: String,
     /// Public key (verifying key) in hex format - required for signature verification
     /// This is public information and necessary to verify the signature
     pub public_key: String,
+    /// Signatures of the signers of a multisig sender, for [`TransactionType::MultisigTransfer`]
+    #[serde(default)]
+    pub cosignatures: Vec<Cosignature>,
 }
 
 impl Transaction {
@@ -31,16 +61,134 @@ impl Transaction {
             from,
             to,
             amount,
+            fee: 0,
             nonce,
             transaction_type: TransactionType::Transfer,
+            chain_id: DEV_CHAIN_ID,
+            signature: String::new(),
+            public_key: String::new(),
+            cosignatures: Vec::new(),
+        }
+    }
+    
+    /// Create a transfer the receiver can only spend from the block at `unlock_height` on
+    pub fn time_locked(from: Address, to: Address, amount: u64, nonce: u64, unlock_height: u64) -> Self {
+        let mut tx = Self::new(from, to, amount, nonce);
+        tx.transaction_type = TransactionType::TimeLocked { unlock_height };
+        tx
+    }
+    
+    /// Create the multisig account controlled by `policy`, funding it with `amount`
+    pub fn create_multisig(from: Address, policy: MultisigPolicy, amount: u64, nonce: u64) -> Self {
+        let mut tx = Self::new(from, policy.address(), amount, nonce);
+        tx.transaction_type = TransactionType::CreateMultisig(policy);
+        tx
+    }
+    
+    /// Create an unsigned transfer out of the multisig account controlled by `policy`
+    ///
+    /// It becomes valid once enough signers have added their co-signature with [`cosign`](Self::cosign).
+    pub fn multisig_transfer(policy: &MultisigPolicy, to: Address, amount: u64, nonce: u64) -> Self {
+        let mut tx = Self::new(policy.address(), to, amount, nonce);
+        tx.transaction_type = TransactionType::MultisigTransfer;
+        tx
+    }
+    
+    /// Create a deployment of `code`, stored at the address derived from the sender and nonce
+    pub fn deploy(from: Address, code: &[u8], nonce: u64, gas_limit: u64, gas_price: u64) -> Self {
+        let to = contract_address(&from, nonce);
+        let mut tx = Self::new(from, to, 0, nonce);
+        tx.transaction_type = TransactionType::Deploy { code: hex::encode(code), gas_limit, gas_price };
+        tx
+    }
+    
+    /// Create a call of `contract` with `input`, sending it `amount`
+    pub fn call(
+        from: Address,
+        contract: Address,
+        amount: u64,
+        input: Vec<u64>,
+        nonce: u64,
+        gas_limit: u64,
+        gas_price: u64,
+    ) -> Self {
+        let mut tx = Self::new(from, contract, amount, nonce);
+        tx.transaction_type = TransactionType::Call { input, gas_limit, gas_price };
+        tx
+    }
+    
+    /// Create the coinbase transaction of the block at `height`
+    ///
+    /// The sender is the all-zero address and the nonce is the block height, so every
+    /// coinbase has a distinct hash.
+    pub fn coinbase(to: Address, amount: u64, height: u64) -> Self {
+        Self {
+            from: coinbase_sender(),
+            to,
+            amount,
+            fee: 0,
+            nonce: height,
+            transaction_type: TransactionType::Coinbase,
+            chain_id: DEV_CHAIN_ID,
             signature: String::new(),
             public_key: String::new(),
+            cosignatures: Vec::new(),
         }
     }
     
+    /// Set the network the transaction is valid on
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+    
+    /// Set the fee paid to the miner
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+    
+    /// Check if this is a coinbase transaction
+    pub fn is_coinbase(&self) -> bool {
+        self.transaction_type == TransactionType::Coinbase
+    }
+    
+    /// Height from which the receiver can spend the amount, for time-locked transfers
+    pub fn unlock_height(&self) -> Option<u64> {
+        match self.transaction_type {
+            TransactionType::TimeLocked { unlock_height } => Some(unlock_height),
+            _ => None,
+        }
+    }
+    
+    /// Most gas a deploy or call may use, 0 for other transactions
+    pub fn gas_limit(&self) -> u64 {
+        match self.transaction_type {
+            TransactionType::Deploy { gas_limit, .. } | TransactionType::Call { gas_limit, .. } => gas_limit,
+            _ => 0,
+        }
+    }
+    
+    /// Price the sender pays per unit of gas used, 0 for other transactions
+    pub fn gas_price(&self) -> u64 {
+        match self.transaction_type {
+            TransactionType::Deploy { gas_price, .. } | TransactionType::Call { gas_price, .. } => gas_price,
+            _ => 0,
+        }
+    }
+    
+    /// Amount plus fee plus the price of the whole gas limit, or `None` if the sum overflows
+    ///
+    /// This is the most the transaction can cost the sender.
+    pub fn total_cost(&self) -> Option<u64> {
+        self.amount
+            .checked_add(self.fee)?
+            .checked_add(self.gas_limit().checked_mul(self.gas_price())?)
+    }
+    
     /// Sign the transaction
     pub fn sign(&mut self, signing_key: &SigningKey) -> Result<()> {
-        let data = self.to_bytes_for_signing();
+        let data = self.signing_hash_bytes();
         let signature = signing_key.sign(&data);
         self.signature = hex::encode(signature.to_bytes());
         
@@ -51,9 +199,32 @@ impl Transaction {
         Ok(())
     }
     
+    /// Add a multisig signer's co-signature, replacing an earlier one from the same key
+    ///
+    /// Set every other field first: the co-signature covers the same signing hash as a
+    /// sender signature.
+    pub fn cosign(&mut self, signing_key: &SigningKey) {
+        let cosignature = Cosignature::sign(signing_key, self);
+        self.cosignatures.retain(|existing| existing.public_key != cosignature.public_key);
+        self.cosignatures.push(cosignature);
+    }
+    
     /// Verify the transaction signature using the stored public key
     /// This is the primary verification method for production use
+    ///
+    /// Multisig transfers carry co-signatures instead; this only checks that each of
+    /// them is valid. Whether they satisfy the account's policy depends on the chain
+    /// state and is checked by [`MultisigPolicy::authorize`].
     pub fn verify(&self) -> Result<()> {
+        if self.transaction_type == TransactionType::MultisigTransfer {
+            return self.verify_cosignatures();
+        }
+        if !self.cosignatures.is_empty() {
+            return Err(ElysiumError::InvalidTransaction(
+                "Only multisig transfers carry co-signatures".to_string()
+            ));
+        }
+        
         // Check signature is present
         if self.signature.is_empty() {
             return Err(ElysiumError::InvalidSignature);
@@ -81,7 +252,7 @@ impl Transaction {
         }
         
         // Verify signature
-        let data = self.to_bytes_for_signing();
+        let data = self.signing_hash_bytes();
         let sig_bytes = hex::decode(&self.signature)
             .map_err(|_| ElysiumError::InvalidSignature)?;
         
@@ -94,10 +265,25 @@ impl Transaction {
         Ok(())
     }
     
+    fn verify_cosignatures(&self) -> Result<()> {
+        if !self.signature.is_empty() || !self.public_key.is_empty() {
+            return Err(ElysiumError::InvalidTransaction(
+                "Multisig transfers are not signed by the sender".to_string()
+            ));
+        }
+        if self.cosignatures.is_empty() {
+            return Err(ElysiumError::InvalidSignature);
+        }
+        for cosignature in &self.cosignatures {
+            cosignature.verify(self)?;
+        }
+        Ok(())
+    }
+    
     /// Verify the transaction signature with an explicit verifying key
     /// Useful for testing or when you already have the key
     pub fn verify_with_key(&self, verifying_key: &VerifyingKey) -> Result<()> {
-        let data = self.to_bytes_for_signing();
+        let data = self.signing_hash_bytes();
         let sig_bytes = hex::decode(&self.signature)
             .map_err(|_| ElysiumError::InvalidSignature)?;
         
@@ -110,28 +296,58 @@ impl Transaction {
         Ok(())
     }
     
-    /// Get transaction hash
+    /// Get the transaction id: the hash of the full canonical encoding, signature included
     pub fn hash(&self) -> String {
-        let bytes = serde_json::to_vec(self).unwrap_or_default();
-        let hash = Sha256::digest(&bytes);
+        let hash = Sha256::digest(codec::to_bytes(self));
         hex::encode(hash)
     }
     
+    /// Get the hash the sender signs
+    ///
+    /// This is the SHA-256 of a domain tag, the codec version and the canonical encoding
+    /// without signature and public key, which includes the transaction type and chain id,
+    /// so a signature is only valid for one kind of transaction on one network.
+    pub fn signing_hash(&self) -> String {
+        hex::encode(self.signing_hash_bytes())
+    }
+    
+    pub(crate) fn signing_hash_bytes(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes_for_signing()).into()
+    }
+    
+    /// Encode the fields covered by the signature
+    pub(crate) fn encode_unsigned(&self, encoder: &mut Encoder) {
+        self.from.encode(encoder);
+        self.to.encode(encoder);
+        encoder.put_u64(self.amount);
+        encoder.put_u64(self.fee);
+        encoder.put_u64(self.nonce);
+        self.transaction_type.encode(encoder);
+        encoder.put_u64(self.chain_id);
+    }
+    
     /// Convert transaction to bytes for signing
     fn to_bytes_for_signing(&self) -> Vec<u8> {
-        let mut data = Vec::new();
-        data.extend_from_slice(self.from.as_bytes().as_slice());
-        data.extend_from_slice(self.to.as_bytes().as_slice());
-        data.extend_from_slice(&self.amount.to_be_bytes());
-        data.extend_from_slice(&self.nonce.to_be_bytes());
-        data
+        let mut encoder = Encoder::new();
+        encoder.put_bytes(SIGNING_DOMAIN);
+        encoder.put_u8(codec::CODEC_VERSION);
+        self.encode_unsigned(&mut encoder);
+        encoder.into_bytes()
     }
 }
 
+/// Sender of coinbase transactions
+fn coinbase_sender() -> Address {
+    Address::from_hex(&"0".repeat(64)).expect("valid address")
+}
+
 impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Tx({} -> {}, amount: {}, nonce: {})", 
-            self.from, self.to, self.amount, self.nonce)
+        if self.is_coinbase() {
+            return write!(f, "Coinbase(-> {}, amount: {})", self.to, self.amount);
+        }
+        write!(f, "Tx({} -> {}, amount: {}, fee: {}, nonce: {})", 
+            self.from, self.to, self.amount, self.fee, self.nonce)
     }
 }
 
@@ -145,7 +361,7 @@ mod tests {
         let keypair1 = KeyPair::generate();
         let keypair2 = KeyPair::generate();
         
-        let mut tx = Transaction::new(
+        let tx = Transaction::new(
             keypair1.address(),
             keypair2.address(),
             100,
@@ -178,5 +394,38 @@ mod tests {
         // Same transactions should have same hash (before signing)
         assert_eq!(tx1.hash(), tx2.hash());
     }
+    
+    #[test]
+    fn test_signing_hash_excludes_signature() {
+        let keypair1 = KeyPair::generate();
+        let keypair2 = KeyPair::generate();
+        
+        let unsigned = Transaction::new(
+            keypair1.address(),
+            keypair2.address(),
+            100,
+            0,
+        );
+        let mut signed = unsigned.clone();
+        signed.sign(keypair1.signing_key()).unwrap();
+        
+        assert_eq!(signed.signing_hash(), unsigned.signing_hash());
+        assert_ne!(signed.hash(), unsigned.hash());
+        assert_ne!(signed.hash(), signed.signing_hash());
+        
+        // Changing any signed field invalidates the signature
+        let mut altered = signed.clone();
+        altered.amount += 1;
+        assert_ne!(altered.signing_hash(), signed.signing_hash());
+        assert!(altered.verify().is_err());
+        
+        // Including the network and the transaction type
+        let mut other_chain = signed.clone();
+        other_chain.chain_id = 1;
+        assert!(other_chain.verify().is_err());
+        let mut other_type = signed.clone();
+        other_type.transaction_type = TransactionType::Coinbase;
+        assert!(other_type.verify().is_err());
+    }
 }
 
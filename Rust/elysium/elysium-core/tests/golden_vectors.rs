@@ -0,0 +1,177 @@
+//! Golden vectors for the canonical encoding and consensus hashes
+//!
+//! These values are part of consensus: if a change makes any of them fail, it
+//! changes block or transaction hashes and needs a new `CODEC_VERSION`.
+
+use elysium_core::*;
+use ed25519_dalek::SigningKey;
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7u8; 32])
+}
+
+fn transfer() -> Transaction {
+    let from = Address::from_verifying_key(&signing_key().verifying_key());
+    let to = Address::from_hex(&"11".repeat(32)).unwrap();
+    Transaction::new(from, to, 250, 3).with_fee(5).with_chain_id(7)
+}
+
+fn header() -> BlockHeader {
+    BlockHeader {
+        number: 42,
+        parent_hash: "22".repeat(32),
+        transactions_root: "33".repeat(32),
+        state_root: "44".repeat(32),
+        timestamp: 1_700_000_000,
+        nonce: 9,
+        bits: MAX_TARGET_BITS,
+        public_key: String::new(),
+        signature: String::new(),
+    }
+}
+
+#[test]
+fn test_transaction_encoding_vector() {
+    let tx = transfer();
+    assert_eq!(
+        tx.from.as_hex(),
+        "fe812c12f3ab4ce6ac5db69ac352f906cb1b11ef43fb33e252ef7ff552263889"
+    );
+    assert_eq!(
+        hex::encode(codec::to_bytes(&tx)),
+        concat!(
+            "07",                                                                       // codec version
+            "00", "00000020", "fe812c12f3ab4ce6ac5db69ac352f906cb1b11ef43fb33e252ef7ff552263889", // from
+            "00", "00000020", "1111111111111111111111111111111111111111111111111111111111111111", // to
+            "00000000000000fa",                                                         // amount
+            "0000000000000005",                                                         // fee
+            "0000000000000003",                                                         // nonce
+            "00",                                                                       // transfer
+            "0000000000000007",                                                         // chain id
+            "00", "00000000",                                                           // signature
+            "00", "00000000",                                                           // public key
+            "00000000",                                                                 // co-signatures
+        )
+    );
+}
+
+#[test]
+fn test_transaction_hash_vectors() {
+    let mut tx = transfer();
+    assert_eq!(
+        tx.signing_hash(),
+        "d6c75edf721d3a8ad05739f8018b54e577a36ebdf9e7b554629804a46211cd64"
+    );
+    
+    // Ed25519 signatures are deterministic, so the signed transaction is fixed too
+    tx.sign(&signing_key()).unwrap();
+    assert_eq!(
+        tx.signature,
+        "ee8a2ee91836defbd3e1965a36d5dafea6486991a26ec9562583b4c8f9a98a67cf8d7fbe39ec426f776437a9c1c2d46d163c6f9b1925f91d52a4d3b21fc23603"
+    );
+    assert_eq!(
+        tx.signing_hash(),
+        "d6c75edf721d3a8ad05739f8018b54e577a36ebdf9e7b554629804a46211cd64"
+    );
+    assert_eq!(
+        tx.hash(),
+        "275177d8b131b52aae677375e5d2700c5a7c731247d1b67883cf2986441f61d1"
+    );
+}
+
+#[test]
+fn test_block_header_vectors() {
+    let header = header();
+    assert_eq!(
+        hex::encode(codec::to_bytes(&header)),
+        concat!(
+            "07",                                                                       // codec version
+            "000000000000002a",                                                         // number
+            "00", "00000020", "2222222222222222222222222222222222222222222222222222222222222222", // parent hash
+            "00", "00000020", "3333333333333333333333333333333333333333333333333333333333333333", // transactions root
+            "00", "00000020", "4444444444444444444444444444444444444444444444444444444444444444", // state root
+            "000000006553f100",                                                         // timestamp
+            "0000000000000009",                                                         // nonce
+            "2000ffff",                                                                 // bits
+            "00", "00000000",                                                           // public key
+            "00", "00000000",                                                           // signature
+        )
+    );
+    assert_eq!(
+        header.hash(),
+        "b39441ac3108fe881c4d0eed2f864913c29a38f2d2699142f69e99a6772758a2"
+    );
+    
+    // The seal is left out of the hash a validator signs
+    assert_eq!(
+        header.signing_hash(),
+        "0cfd5760e64a4cb6a98aa4d85b2b64f9e466ce27a5f83b812980eadf59d68b29"
+    );
+}
+
+#[test]
+fn test_transactions_root_vectors() {
+    let empty = Block::new(0, "0".repeat(64), 1);
+    assert_eq!(
+        empty.header.transactions_root,
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    
+    let mut tx = transfer();
+    tx.sign(&signing_key()).unwrap();
+    let mut block = Block::new(1, "55".repeat(32), 1);
+    block.add_transaction(tx);
+    assert_eq!(
+        block.header.transactions_root,
+        "abca08762b711e64a397a3c44c157cbd2b239767ea428c13356af5c6f8693485"
+    );
+}
+
+#[test]
+fn test_multisig_address_vector() {
+    let signers = vec![
+        Address::from_hex(&"22".repeat(32)).unwrap(),
+        Address::from_hex(&"11".repeat(32)).unwrap(),
+    ];
+    let policy = MultisigPolicy::new(2, signers).unwrap();
+    assert_eq!(
+        policy.address().as_hex(),
+        "eaef47c10eaab50a20250f00a9e33df50c7e4b5df19a712432b921cc7127c43e"
+    );
+}
+
+#[test]
+fn test_contract_vectors() {
+    let deployer = Address::from_hex(&"11".repeat(32)).unwrap();
+    let deploy = Transaction::deploy(deployer.clone(), &[0x01, 0, 0, 0, 0, 0, 0, 0, 7, 0x00], 3, 2_000, 4);
+    assert_eq!(
+        deploy.to.as_hex(),
+        "cf9e8545e2782dea36046d178f34999b9f6e088e3d2ce791606f8b90c287318d"
+    );
+    assert_eq!(
+        deploy.signing_hash(),
+        "df930a2dd3d8a1aa4e573cc6f1770bdb24877be04132ed09bdd1001febf58f5b"
+    );
+    let call = Transaction::call(deployer, deploy.to.clone(), 1, vec![2, 3], 4, 500, 4);
+    assert_eq!(
+        call.signing_hash(),
+        "226ff4677f1c7ce6e5dbc9a0005062fa547eec9c26ed5741262ac24cbd83e7db"
+    );
+}
+
+#[test]
+fn test_genesis_parent_hash_vectors() {
+    let mut spec = GenesisSpec {
+        chain_id: 7,
+        timestamp: 1_700_000_000,
+        difficulty: 1,
+        alloc: Default::default(),
+        consensus: ConsensusSpec::ProofOfWork,
+        retarget: RetargetConfig::default(),
+    };
+    assert_eq!(spec.parent_hash(), "17ed1ef48cdc6262831d3bd1c82c070d85d337b4b075bc2a0000000000000007");
+    spec.consensus = ConsensusSpec::ProofOfAuthority {
+        validators: vec![Address::from_hex(&"11".repeat(32)).unwrap()],
+    };
+    assert_eq!(spec.parent_hash(), "f203fd875f7726fd827be8fc2607eed1bc736b1e7d3549820000000000000007");
+}
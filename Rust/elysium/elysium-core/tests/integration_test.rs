@@ -159,7 +159,7 @@ fn test_block_validation() {
     );
     tx.sign(alice.signing_key()).unwrap();
     blockchain.add_transaction(tx).unwrap();
-    let block1 = blockchain.mine_block().unwrap();
+    let _block1 = blockchain.mine_block().unwrap();
     
     // Try to add invalid block (wrong parent hash)
     let mut invalid_block = Block::new(2, "invalid".to_string(), 1);
@@ -175,7 +175,7 @@ fn test_block_validation() {
     );
     tx2.sign(alice.signing_key()).unwrap();
     blockchain.add_transaction(tx2).unwrap();
-    let block2 = blockchain.mine_block().unwrap();
+    let _block2 = blockchain.mine_block().unwrap();
     
     assert_eq!(blockchain.height(), 3);
 }
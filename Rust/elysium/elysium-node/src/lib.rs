@@ -1,8 +1,18 @@
 pub mod node;
 pub mod network;
 pub mod miner;
+pub mod protocol;
+pub mod pubsub;
+pub mod rpc;
+pub mod sync;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub use node::*;
 pub use network::*;
 pub use miner::*;
+pub use protocol::*;
+pub use pubsub::*;
+pub use rpc::*;
+pub use sync::*;
 
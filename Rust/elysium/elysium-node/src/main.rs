@@ -1,9 +1,11 @@
 use clap::Parser;
-use elysium_node::{Node, Network, Miner};
+use elysium_core::{account::KeyPair, Address, GenesisSpec, Mempool, MempoolConfig};
+use elysium_node::{Node, Network, Miner, RpcServer, WsServer};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
-use tracing::{info, error};
+use tracing::info;
 
 #[derive(Parser)]
 #[command(name = "elysium-node")]
@@ -13,21 +15,54 @@ struct Args {
     #[arg(short, long, default_value = "127.0.0.1:8080")]
     listen: String,
     
-    /// Mining difficulty
+    /// Mining difficulty (ignored when a genesis spec is given)
     #[arg(short, long, default_value_t = 1)]
     difficulty: u64,
     
+    /// Genesis spec JSON file; nodes on the same network must use the same file
+    #[arg(long)]
+    genesis: Option<PathBuf>,
+    
     /// Enable mining
     #[arg(short, long)]
     mine: bool,
     
+    /// Address credited with block rewards and fees (hex); blocks mined without one
+    /// carry no coinbase and burn their fees
+    #[arg(long)]
+    miner_address: Option<String>,
+    
+    /// File holding the hex secret key this node seals proof-of-authority blocks with
+    #[arg(long)]
+    validator_key_file: Option<PathBuf>,
+    
+    /// Maximum number of pending transactions; the cheapest are evicted beyond it
+    #[arg(long, default_value_t = 5_000)]
+    mempool_size: usize,
+    
     /// Mining interval in seconds
     #[arg(long, default_value_t = 5)]
     mining_interval: u64,
     
+    /// Threads searching for block nonces (defaults to one per CPU)
+    #[arg(long)]
+    mining_threads: Option<usize>,
+    
     /// Peer addresses (comma-separated)
     #[arg(short, long)]
     peers: Option<String>,
+    
+    /// Directory for the block log and state snapshot (in-memory chain if omitted)
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+    
+    /// Serve JSON-RPC on this address (e.g., 127.0.0.1:8545); disabled if omitted
+    #[arg(long)]
+    rpc_addr: Option<String>,
+    
+    /// Serve JSON-RPC and subscriptions over WebSocket on this address (e.g., 127.0.0.1:8547)
+    #[arg(long)]
+    ws_addr: Option<String>,
 }
 
 #[tokio::main]
@@ -39,12 +74,56 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting Elysium node...");
     
     // Create node
-    let node = Node::new(args.difficulty);
-    info!("Node created with difficulty: {}", args.difficulty);
+    let genesis = match &args.genesis {
+        Some(path) => Some(GenesisSpec::from_file(path)?),
+        None => None,
+    };
+    let node = match (&args.data_dir, &genesis) {
+        (Some(data_dir), Some(spec)) => {
+            let node = Node::open_genesis(spec, data_dir)?;
+            info!("Loaded chain from {} at height {}", data_dir.display(), node.height().await);
+            node
+        }
+        (Some(data_dir), None) => {
+            let node = Node::open(args.difficulty, data_dir)?;
+            info!("Loaded chain from {} at height {}", data_dir.display(), node.height().await);
+            node
+        }
+        (None, Some(spec)) => Node::from_genesis(spec)?,
+        (None, None) => Node::new(args.difficulty),
+    };
+    if let Some(spec) = &genesis {
+        info!("Using genesis spec for chain {} with {} allocations", spec.chain_id, spec.alloc.len());
+    }
+    let miner_address = match &args.miner_address {
+        Some(hex) => Some(Address::from_hex(hex).map_err(|e| anyhow::anyhow!("Invalid miner address: {}", e))?),
+        None => None,
+    };
+    let validator_key = match &args.validator_key_file {
+        Some(path) => {
+            let secret = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read validator key {}: {}", path.display(), e))?;
+            Some(KeyPair::from_secret_hex(secret.trim())?)
+        }
+        None => None,
+    };
+    {
+        let mut blockchain = node.blockchain_mut().await;
+        blockchain.miner_address = miner_address;
+        if let Some(key) = validator_key {
+            info!("Sealing {} blocks as validator {}", blockchain.consensus().name(), key.address());
+            blockchain.validator_key = Some(key);
+        }
+        blockchain.mempool = Mempool::new(MempoolConfig {
+            max_size: args.mempool_size,
+            ..MempoolConfig::default()
+        });
+    }
+    info!("Node created with difficulty: {}", node.blockchain().await.current_difficulty());
     
     // Create network
     let listen_addr = SocketAddr::from_str(&args.listen)?;
-    let mut network = Network::new(listen_addr);
+    let network = Network::new(listen_addr, node.clone());
     
     // Add peers
     if let Some(peers_str) = args.peers {
@@ -60,11 +139,27 @@ async fn main() -> anyhow::Result<()> {
     
     // Start miner if enabled
     if args.mine {
-        info!("Starting miner...");
-        let miner = Miner::new(node.clone(), Duration::from_secs(args.mining_interval));
+        match &args.miner_address {
+            Some(address) => info!("Starting miner, rewards go to {}", address),
+            None => info!("Starting miner without a miner address; rewards and fees are burned"),
+        }
+        let mut miner = Miner::new(node.clone(), Duration::from_secs(args.mining_interval));
+        if let Some(threads) = args.mining_threads {
+            miner = miner.with_threads(threads);
+        }
         let _miner_handle = miner.start().await?;
     }
     
+    // Start RPC server if enabled
+    let _rpc_server = match &args.rpc_addr {
+        Some(addr) => Some(RpcServer::start(node.clone(), SocketAddr::from_str(addr)?)?),
+        None => None,
+    };
+    let _ws_server = match &args.ws_addr {
+        Some(addr) => Some(WsServer::start(node.clone(), SocketAddr::from_str(addr)?)?),
+        None => None,
+    };
+    
     info!("Node started successfully. Listening on {}", listen_addr);
     
     // Keep the node running
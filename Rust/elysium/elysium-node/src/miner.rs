@@ -1,12 +1,105 @@
-use crate::Node;
-use elysium_core::Result;
-use tracing::{info, error};
+use crate::{Node, NodeEvent};
+use elysium_core::{BlockHeader, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{debug, error, info};
+
+/// Number of nonce search threads used unless configured: one per available CPU
+pub fn default_mining_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Search for a nonce meeting the header's target on `threads` threads, which try
+/// interleaved nonces; `None` if `stop` was set first
+///
+/// Blocks until the search ends, so call it from a blocking thread. `hashes` counts the
+/// nonces tried by all threads.
+pub fn search_nonce(header: &BlockHeader, threads: usize, stop: &AtomicBool, hashes: &AtomicU64) -> Option<u64> {
+    let threads = threads.max(1) as u64;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|offset| scope.spawn(move || {
+                let nonce = header.search_nonce(header.nonce.wrapping_add(offset), threads, stop, hashes);
+                // The first thread to succeed stops the others
+                if nonce.is_some() {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                nonce
+            }))
+            .collect();
+        workers.into_iter()
+            .filter_map(|worker| worker.join().expect("nonce search thread panicked"))
+            .next()
+    })
+}
+
+/// Mining statistics of a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MiningStats {
+    /// Whether the node's miner is running
+    pub mining: bool,
+    /// Nonce search threads of the miner
+    pub threads: usize,
+    /// Hashes per second over the latest finished nonce search
+    pub hashrate: u64,
+    /// Hashes tried since the node started
+    pub hashes: u64,
+    pub blocks_mined: u64,
+    /// Searches abandoned because another block became the tip first
+    pub stale_templates: u64,
+}
+
+/// Counters behind [`MiningStats`], shared by a node and its miner
+#[derive(Debug, Default)]
+pub(crate) struct MiningCounters {
+    pub(crate) threads: AtomicUsize,
+    pub(crate) hashes: AtomicU64,
+    pub(crate) hashrate: AtomicU64,
+    pub(crate) blocks_mined: AtomicU64,
+    pub(crate) stale_templates: AtomicU64,
+}
+
+impl MiningCounters {
+    /// Record the speed of a finished search
+    pub(crate) fn record_search(&self, hashes: u64, elapsed: Duration) {
+        let hashrate = hashes as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        self.hashrate.store(hashrate as u64, Ordering::Relaxed);
+    }
+    
+    pub(crate) fn stats(&self, mining: bool) -> MiningStats {
+        MiningStats {
+            mining,
+            threads: self.threads.load(Ordering::Relaxed),
+            hashrate: self.hashrate.load(Ordering::Relaxed),
+            hashes: self.hashes.load(Ordering::Relaxed),
+            blocks_mined: self.blocks_mined.load(Ordering::Relaxed),
+            stale_templates: self.stale_templates.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Sets a stop flag when dropped, so a cancelled mining task also ends its search threads
+pub(crate) struct StopOnDrop(pub(crate) Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
 
 /// Miner that continuously mines blocks
+///
+/// Each block is built from a snapshot of the chain, and its nonce is searched on worker
+/// threads without holding the chain lock. The search is abandoned as soon as another
+/// block, e.g. one from a peer, becomes the tip. Under proof of authority the miner only
+/// seals blocks in the slots of the node's validator key.
 pub struct Miner {
     node: Node,
     mining_interval: Duration,
+    threads: usize,
 }
 
 impl Miner {
@@ -15,31 +108,75 @@ impl Miner {
         Self {
             node,
             mining_interval,
+            threads: default_mining_threads(),
         }
     }
     
+    /// Search nonces on this many threads
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    
     /// Start mining
     pub async fn start(&self) -> Result<tokio::task::JoinHandle<()>> {
         let node = self.node.clone();
         let interval = self.mining_interval;
+        let threads = self.threads;
+        node.mining.threads.store(threads, Ordering::Relaxed);
+        node.set_mining(true).await;
         
         let handle = tokio::spawn(async move {
             loop {
-                // Check if there are pending transactions
-                let has_pending = {
+                // Subscribe before taking the snapshot so no new tip in between is missed
+                let events = node.subscribe();
+                
+                // Build a block if there are executable pending transactions and, under
+                // proof of authority, the next slot is ours
+                let template = {
                     let blockchain = node.blockchain().await;
-                    !blockchain.pending_transactions.is_empty()
+                    let our_slot = match blockchain.consensus().sealer_at(blockchain.height()) {
+                        Some(sealer) => blockchain.validator_key.as_ref()
+                            .is_some_and(|key| &key.address() == sealer),
+                        None => true,
+                    };
+                    match our_slot && !blockchain.mempool.ready(&blockchain.accounts, 1).is_empty() {
+                        true => Some(blockchain.mining_template()),
+                        false => None,
+                    }
+                };
+                
+                let template = match template {
+                    Some(Ok(template)) => template,
+                    Some(Err(e)) => {
+                        error!("Error building block template: {}", e);
+                        tokio::time::sleep(interval).await;
+                        continue;
+                    }
+                    None => {
+                        tokio::time::sleep(interval).await;
+                        continue;
+                    }
                 };
                 
-                if has_pending {
-                    info!("Mining new block...");
-                    match node.mine_block().await {
-                        Ok(block) => {
-                            info!("Mined block: {}", block);
-                        }
-                        Err(e) => {
-                            error!("Error mining block: {}", e);
-                        }
+                info!("Mining new block...");
+                let stop = Arc::new(AtomicBool::new(false));
+                let watcher = tokio::spawn(stop_on_new_tip(events, template.header.parent_hash.clone(), stop.clone()));
+                let result = node.mine_template(template, threads, stop).await;
+                watcher.abort();
+                
+                match result {
+                    Ok(Some(block)) => {
+                        let hashrate = node.mining.hashrate.load(Ordering::Relaxed);
+                        info!("Mined block: {} ({} H/s)", block, hashrate);
+                    }
+                    Ok(None) => {
+                        // Start over on top of the new tip right away
+                        debug!("Another block became the tip, abandoning block template");
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Error mining block: {}", e);
                     }
                 }
                 
@@ -51,3 +188,97 @@ impl Miner {
     }
 }
 
+/// Set `stop` once the tip is no longer `parent_hash`
+async fn stop_on_new_tip(mut events: Receiver<NodeEvent>, parent_hash: String, stop: Arc<AtomicBool>) {
+    loop {
+        match events.recv().await {
+            Ok(NodeEvent::NewHead(header)) if header.hash() != parent_hash => break,
+            // Missed events may have changed the tip
+            Err(RecvError::Lagged(_)) => break,
+            Err(RecvError::Closed) => return,
+            Ok(_) => {}
+        }
+    }
+    stop.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{funded_node, TEST_CHAIN_ID};
+    use elysium_core::{account::KeyPair, Block, Transaction};
+    
+    fn transfer(from: &KeyPair, amount: u64, nonce: u64) -> Transaction {
+        let mut tx = Transaction::new(from.address(), KeyPair::generate().address(), amount, nonce);
+        tx.chain_id = TEST_CHAIN_ID;
+        from.sign_transaction(&mut tx).unwrap();
+        tx
+    }
+    
+    #[test]
+    fn test_threaded_search() {
+        let header = Block::new(0, "0".repeat(64), 1).header;
+        let hashes = AtomicU64::new(0);
+        let nonce = search_nonce(&header, 4, &AtomicBool::new(false), &hashes).unwrap();
+        let mut mined = header.clone();
+        mined.nonce = nonce;
+        assert!(mined.meets_difficulty());
+        assert!(hashes.load(Ordering::Relaxed) > 0);
+        
+        // A search stopped up front finds nothing
+        assert_eq!(search_nonce(&header, 4, &AtomicBool::new(true), &hashes), None);
+    }
+    
+    #[tokio::test]
+    async fn test_search_runs_outside_the_chain_lock() {
+        let alice = KeyPair::generate();
+        let node = funded_node(&alice);
+        node.add_transaction(transfer(&alice, 10, 0)).await.unwrap();
+        
+        // A template far too hard to mine keeps its search going
+        let mut template = node.blockchain().await.mining_template().unwrap();
+        template.header.bits = 0x03000001;
+        let stop = Arc::new(AtomicBool::new(false));
+        let search = tokio::spawn({
+            let node = node.clone();
+            let stop = stop.clone();
+            async move { node.mine_template(template, 2, stop).await }
+        });
+        
+        // The chain stays writable meanwhile, and a new tip ends the search
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let events = node.subscribe();
+        let parent = node.blockchain().await.latest_block().hash();
+        let watcher = tokio::spawn(stop_on_new_tip(events, parent, stop.clone()));
+        node.mine_block().await.unwrap();
+        watcher.await.unwrap();
+        assert!(search.await.unwrap().unwrap().is_none());
+        
+        let stats = node.mining_stats().await;
+        assert_eq!((stats.blocks_mined, stats.stale_templates), (1, 1));
+        assert!(stats.hashes > 0);
+    }
+    
+    #[tokio::test]
+    async fn test_miner_mines_pending_transactions() {
+        let alice = KeyPair::generate();
+        let node = funded_node(&alice);
+        let mut events = node.subscribe();
+        let miner = Miner::new(node.clone(), Duration::from_millis(10)).with_threads(2);
+        let handle = miner.start().await.unwrap();
+        
+        node.add_transaction(transfer(&alice, 10, 0)).await.unwrap();
+        loop {
+            if let NodeEvent::NewBlock(block) = events.recv().await.unwrap() {
+                assert_eq!(block.transactions.len(), 1);
+                break;
+            }
+        }
+        handle.abort();
+        
+        let stats = node.mining_stats().await;
+        assert!(stats.mining);
+        assert_eq!((stats.threads, stats.blocks_mined), (2, 1));
+        assert_eq!(node.height().await, 2);
+    }
+}
@@ -1,9 +1,15 @@
+use crate::{protocol, BlockRequest, ChainStatus, Node, NodeEvent, MAX_BLOCKS_PER_BATCH};
 use elysium_core::{Block, Transaction, Result};
-use bytes::{Bytes, BytesMut, BufMut};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use elysium_core::codec::{self, Decode, Decoder, Encode, Encoder};
+use tokio::io::AsyncRead;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
-use tracing::{info, error, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, error, warn};
 use serde_json;
 
 /// Message types for P2P communication
@@ -13,51 +19,177 @@ pub enum Message {
     NewTransaction(Transaction),
     RequestBlocks(u64), // Request blocks from height
     Blocks(Vec<Block>),
+    Status(ChainStatus), // Sent by both sides when a connection opens
     Ping,
     Pong,
 }
 
 impl Message {
-    /// Serialize message to bytes
+    /// Serialize message to bytes in the canonical binary encoding
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        let json = serde_json::to_string(self)
-            .map_err(|e| elysium_core::ElysiumError::SerializationError(e.to_string()))?;
-        Ok(json.into_bytes())
+        Ok(codec::to_bytes(self))
     }
     
     /// Deserialize message from bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        let json = String::from_utf8(bytes.to_vec())
-            .map_err(|e| elysium_core::ElysiumError::SerializationError(e.to_string()))?;
-        serde_json::from_str(&json)
-            .map_err(|e| elysium_core::ElysiumError::SerializationError(e.to_string()))
+        codec::from_bytes(bytes)
     }
 }
 
+impl Encode for Message {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Message::NewBlock(block) => {
+                encoder.put_u8(0);
+                block.encode(encoder);
+            }
+            Message::NewTransaction(tx) => {
+                encoder.put_u8(1);
+                tx.encode(encoder);
+            }
+            Message::RequestBlocks(height) => {
+                encoder.put_u8(2);
+                encoder.put_u64(*height);
+            }
+            Message::Blocks(blocks) => {
+                encoder.put_u8(3);
+                encoder.put_seq(blocks);
+            }
+            Message::Status(status) => {
+                encoder.put_u8(4);
+                encoder.put_u64(status.height);
+                encoder.put_hex(&status.tip_hash);
+            }
+            Message::Ping => encoder.put_u8(5),
+            Message::Pong => encoder.put_u8(6),
+        }
+    }
+}
+
+impl Decode for Message {
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self> {
+        match decoder.get_u8()? {
+            0 => Ok(Message::NewBlock(Block::decode(decoder)?)),
+            1 => Ok(Message::NewTransaction(Transaction::decode(decoder)?)),
+            2 => Ok(Message::RequestBlocks(decoder.get_u64()?)),
+            3 => Ok(Message::Blocks(decoder.get_seq()?)),
+            4 => Ok(Message::Status(ChainStatus {
+                height: decoder.get_u64()?,
+                tip_hash: decoder.get_hex()?,
+            })),
+            5 => Ok(Message::Ping),
+            6 => Ok(Message::Pong),
+            tag => Err(elysium_core::ElysiumError::SerializationError(
+                format!("Unknown message type {}", tag)
+            )),
+        }
+    }
+}
+
+/// Number of recently seen block and transaction hashes remembered for deduplication
+const SEEN_CACHE_CAPACITY: usize = 10_000;
+
+/// Bounded set of recently seen item hashes, evicting the oldest first
+#[derive(Debug, Default)]
+struct SeenCache {
+    hashes: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SeenCache {
+    /// Record a hash; returns `false` if it was already present
+    fn insert(&mut self, hash: String) -> bool {
+        if !self.hashes.insert(hash.clone()) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > SEEN_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Capacity of the per-connection outbound message queue
+const OUTBOUND_QUEUE_CAPACITY: usize = 256;
+
+/// Interval between attempts to reconnect to configured peers
+const PEER_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval between checks for timed out block requests
+const SYNC_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An open connection to a peer, identified by an id so that a closed
+/// connection never unregisters a newer one to the same address
+#[derive(Clone)]
+struct Connection {
+    id: u64,
+    sender: mpsc::Sender<Message>,
+}
+
 /// Simple P2P network handler
+///
+/// Every connection, inbound or outbound, is persistent and bidirectional: a reader
+/// task decodes length-prefixed frames (see [`crate::protocol`]) and a writer task
+/// drains a queue of outgoing messages.
+///
+/// Received blocks and transactions are applied to the node; everything the node
+/// accepts (from peers or locally) is gossiped to all connected peers. Hashes of seen
+/// items are remembered so that echoes are dropped instead of being relayed forever.
+///
+/// Both sides announce their [`ChainStatus`] when a connection opens; a node that is
+/// behind downloads the missing blocks in batches via `RequestBlocks`/`Blocks`, as
+/// directed by the node's [`crate::SyncManager`].
+#[derive(Clone)]
 pub struct Network {
     listen_addr: SocketAddr,
-    peers: Vec<SocketAddr>,
+    node: Node,
+    peers: Arc<Mutex<Vec<SocketAddr>>>,
+    connections: Arc<Mutex<HashMap<SocketAddr, Connection>>>,
+    next_connection_id: Arc<AtomicU64>,
+    seen: Arc<Mutex<SeenCache>>,
 }
 
 impl Network {
     /// Create a new network handler
-    pub fn new(listen_addr: SocketAddr) -> Self {
+    pub fn new(listen_addr: SocketAddr, node: Node) -> Self {
         Self {
             listen_addr,
-            peers: Vec::new(),
+            node,
+            peers: Arc::new(Mutex::new(Vec::new())),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            next_connection_id: Arc::new(AtomicU64::new(0)),
+            seen: Arc::new(Mutex::new(SeenCache::default())),
         }
     }
     
     /// Add a peer
-    pub fn add_peer(&mut self, peer: SocketAddr) {
-        if !self.peers.contains(&peer) {
-            self.peers.push(peer);
+    pub fn add_peer(&self, peer: SocketAddr) {
+        let mut peers = self.peers.lock().unwrap();
+        if !peers.contains(&peer) {
+            peers.push(peer);
             info!("Added peer: {}", peer);
         }
     }
     
-    /// Start listening for connections
+    /// Get the configured peers
+    pub fn peers(&self) -> Vec<SocketAddr> {
+        self.peers.lock().unwrap().clone()
+    }
+    
+    /// Get the addresses of all open connections
+    pub fn connected_peers(&self) -> Vec<SocketAddr> {
+        self.connections.lock().unwrap().keys().copied().collect()
+    }
+    
+    /// Record an item hash as seen; returns `false` if it had been seen before
+    fn mark_seen(&self, hash: String) -> bool {
+        self.seen.lock().unwrap().insert(hash)
+    }
+    
+    /// Start listening for connections, dialing configured peers and relaying node events
     pub async fn listen(&self) -> Result<tokio::task::JoinHandle<()>> {
         let listener = TcpListener::bind(self.listen_addr)
             .await
@@ -67,16 +199,17 @@ impl Network {
         
         info!("Listening on {}", self.listen_addr);
         
+        self.start_relay();
+        self.start_dialer();
+        self.start_sync_timer();
+        
+        let network = self.clone();
         let handle = tokio::spawn(async move {
             loop {
                 match listener.accept().await {
                     Ok((stream, addr)) => {
                         info!("New connection from {}", addr);
-                        tokio::spawn(async move {
-                            if let Err(e) = Self::handle_connection(stream).await {
-                                error!("Error handling connection: {}", e);
-                            }
-                        });
+                        network.open_connection(stream, addr);
                     }
                     Err(e) => {
                         error!("Error accepting connection: {}", e);
@@ -88,52 +221,339 @@ impl Network {
         Ok(handle)
     }
     
-    /// Handle a connection
-    async fn handle_connection(mut stream: TcpStream) -> Result<()> {
-        let mut buffer = BytesMut::with_capacity(4096);
-        
-        loop {
-            match stream.read_buf(&mut buffer).await {
-                Ok(0) => break, // Connection closed
-                Ok(_) => {
-                    // Try to parse message
-                    if let Ok(msg) = Message::from_bytes(&buffer) {
-                        info!("Received message: {:?}", msg);
-                        // In a real implementation, we'd handle the message here
+    /// Gossip every block and transaction the node accepts to all peers
+    fn start_relay(&self) -> tokio::task::JoinHandle<()> {
+        let network = self.clone();
+        let mut events = self.node.subscribe();
+        
+        tokio::spawn(async move {
+            loop {
+                let message = match events.recv().await {
+                    Ok(NodeEvent::NewBlock(block)) => {
+                        network.mark_seen(block.hash());
+                        // Peers that are behind download historic blocks themselves
+                        if network.node.sync().is_syncing() {
+                            continue;
+                        }
+                        Message::NewBlock(block)
+                    }
+                    Ok(NodeEvent::NewTransaction(tx)) => {
+                        network.mark_seen(tx.hash());
+                        Message::NewTransaction(tx)
+                    }
+                    Ok(NodeEvent::NewHead(_)) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Relay lagged behind, skipped {} node events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                network.broadcast(message).await;
+            }
+        })
+    }
+    
+    /// Periodically (re)connect to configured peers that have no open connection
+    fn start_dialer(&self) -> tokio::task::JoinHandle<()> {
+        let network = self.clone();
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PEER_RECONNECT_INTERVAL);
+            loop {
+                interval.tick().await;
+                for peer in network.peers() {
+                    if !network.is_connected(&peer) {
+                        if let Err(e) = network.connect(peer).await {
+                            debug!("Could not connect to peer {}: {}", peer, e);
+                        }
                     }
-                    buffer.clear();
-                }
-                Err(e) => {
-                    error!("Error reading from stream: {}", e);
-                    break;
                 }
             }
-        }
+        })
+    }
+    
+    /// Periodically retry block requests that timed out
+    fn start_sync_timer(&self) -> tokio::task::JoinHandle<()> {
+        let network = self.clone();
         
-        Ok(())
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SYNC_TICK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let height = network.node.height().await;
+                if let Some(request) = network.node.sync().on_tick(Instant::now(), height) {
+                    warn!("Block request timed out, retrying from {}", request.peer);
+                    network.request_blocks(request).await;
+                }
+            }
+        })
     }
     
-    /// Send a message to a peer
-    pub async fn send_to_peer(&self, peer: SocketAddr, message: Message) -> Result<()> {
-        let mut stream = TcpStream::connect(peer)
+    fn is_connected(&self, peer: &SocketAddr) -> bool {
+        self.connections.lock().unwrap().contains_key(peer)
+    }
+    
+    /// Open an outbound connection to a peer
+    async fn connect(&self, peer: SocketAddr) -> Result<mpsc::Sender<Message>> {
+        let stream = TcpStream::connect(peer)
             .await
             .map_err(|e| elysium_core::ElysiumError::BlockchainError(
                 format!("Failed to connect to {}: {}", peer, e)
             ))?;
+        info!("Connected to peer {}", peer);
+        Ok(self.open_connection(stream, peer))
+    }
+    
+    /// Register a connection and spawn its reader and writer tasks
+    fn open_connection(&self, stream: TcpStream, addr: SocketAddr) -> mpsc::Sender<Message> {
+        let (mut reader, mut writer) = stream.into_split();
+        let (sender, mut outbound) = mpsc::channel::<Message>(OUTBOUND_QUEUE_CAPACITY);
+        let id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
         
-        let bytes = message.to_bytes()?;
-        stream.write_all(&bytes).await
-            .map_err(|e| elysium_core::ElysiumError::BlockchainError(
-                format!("Failed to write to peer: {}", e)
-            ))?;
+        self.connections.lock().unwrap().insert(addr, Connection {
+            id,
+            sender: sender.clone(),
+        });
+        
+        // The writer stops once every sender (registry entry and reader) is dropped
+        tokio::spawn(async move {
+            while let Some(message) = outbound.recv().await {
+                if let Err(e) = protocol::write_message(&mut writer, &message).await {
+                    warn!("Failed to send to peer {}: {}", addr, e);
+                    break;
+                }
+            }
+        });
+        
+        let network = self.clone();
+        let replies = sender.clone();
+        tokio::spawn(async move {
+            let status = network.chain_status().await;
+            if replies.send(Message::Status(status)).await.is_ok() {
+                if let Err(e) = network.read_loop(addr, &mut reader, &replies).await {
+                    warn!("Closing connection to {}: {}", addr, e);
+                }
+            }
+            {
+                let mut connections = network.connections.lock().unwrap();
+                if connections.get(&addr).map(|c| c.id) == Some(id) {
+                    connections.remove(&addr);
+                }
+            }
+            info!("Connection to {} closed", addr);
+            
+            let height = network.node.height().await;
+            if let Some(request) = network.node.sync().on_peer_disconnected(addr, height) {
+                network.request_blocks(request).await;
+            }
+        });
+        
+        sender
+    }
+    
+    /// Read frames from a connection until it closes or sends an invalid frame
+    async fn read_loop<R>(&self, peer: SocketAddr, reader: &mut R, replies: &mpsc::Sender<Message>) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        while let Some(message) = protocol::read_message(reader).await? {
+            if let Some(reply) = self.handle_message(peer, message).await {
+                if replies.send(reply).await.is_err() {
+                    break;
+                }
+            }
+        }
         
         Ok(())
     }
     
-    /// Broadcast a message to all peers
+    /// Apply a received message to the node, returning an optional direct reply
+    ///
+    /// Accepted blocks and transactions are relayed by the node event subscription.
+    async fn handle_message(&self, peer: SocketAddr, msg: Message) -> Option<Message> {
+        match msg {
+            Message::NewTransaction(tx) => {
+                let hash = tx.hash();
+                if !self.mark_seen(hash.clone()) {
+                    debug!("Ignoring already seen transaction {}", hash);
+                    return None;
+                }
+                match self.node.add_transaction(tx).await {
+                    Ok(()) => info!("Accepted transaction {} from peer", hash),
+                    Err(e) => warn!("Rejected transaction {} from peer: {}", hash, e),
+                }
+                None
+            }
+            Message::NewBlock(block) => {
+                let hash = block.hash();
+                if !self.mark_seen(hash.clone()) {
+                    debug!("Ignoring already seen block {}", hash);
+                    return None;
+                }
+                let description = block.to_string();
+                let number = block.header.number;
+                match self.node.add_block(block).await {
+                    Ok(()) => info!("Accepted {} from peer", description),
+                    Err(e) => {
+                        warn!("Rejected {} from peer: {}", description, e);
+                        // A block beyond our tip means the peer is ahead of us
+                        let height = self.node.height().await;
+                        if number >= height {
+                            if let Some(request) = self.node.sync().on_peer_status(peer, number + 1, height) {
+                                self.request_blocks(request).await;
+                            }
+                        }
+                    }
+                }
+                None
+            }
+            Message::Status(status) => {
+                debug!("Peer {} is at height {}", peer, status.height);
+                let height = self.node.height().await;
+                if let Some(request) = self.node.sync().on_peer_status(peer, status.height, height) {
+                    info!("Peer {} is at height {}, we are at {}; starting sync", peer, status.height, height);
+                    self.request_blocks(request).await;
+                }
+                None
+            }
+            Message::RequestBlocks(from) => Some(Message::Blocks(self.blocks_batch(from).await)),
+            Message::Blocks(blocks) => {
+                self.apply_synced_blocks(peer, blocks).await;
+                None
+            }
+            Message::Ping => Some(Message::Pong),
+            other => {
+                debug!("Unhandled message: {:?}", other);
+                None
+            }
+        }
+    }
+    
+    /// Current height and tip of the local chain
+    async fn chain_status(&self) -> ChainStatus {
+        let blockchain = self.node.blockchain().await;
+        ChainStatus {
+            height: blockchain.height(),
+            tip_hash: blockchain.latest_block().hash(),
+        }
+    }
+    
+    /// Collect the blocks starting at `from` for a `Blocks` response, bounded
+    /// by count and by (roughly) half the maximum frame size
+    async fn blocks_batch(&self, from: u64) -> Vec<Block> {
+        let blockchain = self.node.blockchain().await;
+        let mut batch = Vec::new();
+        let mut batch_size = 0;
+        
+        for block in blockchain.blocks.iter().skip(from as usize).take(MAX_BLOCKS_PER_BATCH) {
+            let block_size = codec::to_bytes(block).len();
+            if !batch.is_empty() && batch_size + block_size > protocol::MAX_FRAME_SIZE / 2 {
+                break;
+            }
+            batch_size += block_size;
+            batch.push(block.clone());
+        }
+        
+        batch
+    }
+    
+    /// Apply a batch received in response to a block request
+    async fn apply_synced_blocks(&self, peer: SocketAddr, blocks: Vec<Block>) {
+        if self.node.sync().requested_from(peer).is_none() {
+            debug!("Ignoring unsolicited blocks from {}", peer);
+            return;
+        }
+        
+        let mut failed = blocks.is_empty();
+        let mut unknown_parent = false;
+        for block in blocks {
+            {
+                let blockchain = self.node.blockchain().await;
+                if blockchain.contains_block(&block.hash()) {
+                    continue; // Already have it
+                }
+                if !blockchain.contains_block(&block.header.parent_hash) {
+                    // The peer is on a fork that branches off below the requested height
+                    unknown_parent = true;
+                    break;
+                }
+            }
+            self.mark_seen(block.hash());
+            let description = block.to_string();
+            if let Err(e) = self.node.add_block(block).await {
+                warn!("Invalid {} from {} during sync: {}", description, peer, e);
+                failed = true;
+                break;
+            }
+        }
+        
+        let height = self.node.height().await;
+        let sync = self.node.sync();
+        let next = if unknown_parent {
+            sync.on_unknown_parent(peer, height)
+        } else if failed {
+            sync.on_peer_failed(peer, height)
+        } else {
+            sync.on_blocks_applied(peer, height)
+        };
+        
+        let status = sync.status(height);
+        match next {
+            Some(request) => {
+                info!("Syncing: at height {} of {}", height, status.target_height);
+                self.request_blocks(request).await;
+            }
+            None if failed => warn!("Sync stalled at height {}: no other peer to download from", height),
+            None => info!("Sync complete at height {}", height),
+        }
+    }
+    
+    /// Send a block request chosen by the sync manager
+    async fn request_blocks(&self, request: BlockRequest) {
+        debug!("Requesting blocks from height {} from {}", request.from, request.peer);
+        if let Err(e) = self.send_to_peer(request.peer, Message::RequestBlocks(request.from)).await {
+            // The sync timer retries from another peer once the request times out
+            warn!("Failed to request blocks from {}: {}", request.peer, e);
+        }
+    }
+    
+    /// Send a message to a peer, connecting first if there is no open connection
+    pub async fn send_to_peer(&self, peer: SocketAddr, message: Message) -> Result<()> {
+        let existing = self.connections.lock().unwrap()
+            .get(&peer)
+            .map(|c| c.sender.clone());
+        let sender = match existing {
+            Some(sender) => sender,
+            None => self.connect(peer).await?,
+        };
+        
+        sender.send(message).await
+            .map_err(|_| elysium_core::ElysiumError::BlockchainError(
+                format!("Connection to {} is closed", peer)
+            ))
+    }
+    
+    /// Broadcast a message to all connected peers and configured peers
+    ///
+    /// Messages to a peer whose outbound queue is full are dropped rather than
+    /// stalling the broadcast for everyone else.
     pub async fn broadcast(&self, message: Message) {
-        for peer in &self.peers {
-            if let Err(e) = self.send_to_peer(*peer, message.clone()).await {
+        let connections: Vec<(SocketAddr, Connection)> = self.connections.lock().unwrap()
+            .iter()
+            .map(|(addr, connection)| (*addr, connection.clone()))
+            .collect();
+        
+        for (addr, connection) in &connections {
+            if let Err(e) = connection.sender.try_send(message.clone()) {
+                warn!("Failed to queue message for peer {}: {}", addr, e);
+            }
+        }
+        
+        for peer in self.peers() {
+            if connections.iter().any(|(addr, _)| *addr == peer) {
+                continue;
+            }
+            if let Err(e) = self.send_to_peer(peer, message.clone()).await {
                 warn!("Failed to send to peer {}: {}", peer, e);
             }
         }
@@ -172,6 +592,12 @@ impl serde::Serialize for Message {
                 s.serialize_field("data", blocks)?;
                 s.end()
             }
+            Message::Status(status) => {
+                let mut s = serializer.serialize_struct("Message", 2)?;
+                s.serialize_field("type", "Status")?;
+                s.serialize_field("data", status)?;
+                s.end()
+            }
             Message::Ping => {
                 let mut s = serializer.serialize_struct("Message", 1)?;
                 s.serialize_field("type", "Ping")?;
@@ -258,9 +684,15 @@ impl<'de> serde::Deserialize<'de> for Message {
                         ).map_err(de::Error::custom)?;
                         Ok(Message::Blocks(blocks))
                     }
+                    "Status" => {
+                        let status: ChainStatus = serde_json::from_value(
+                            data.ok_or_else(|| de::Error::missing_field("data"))?
+                        ).map_err(de::Error::custom)?;
+                        Ok(Message::Status(status))
+                    }
                     "Ping" => Ok(Message::Ping),
                     "Pong" => Ok(Message::Pong),
-                    _ => Err(de::Error::unknown_variant(&msg_type, &["NewBlock", "NewTransaction", "RequestBlocks", "Blocks", "Ping", "Pong"])),
+                    _ => Err(de::Error::unknown_variant(&msg_type, &["NewBlock", "NewTransaction", "RequestBlocks", "Blocks", "Status", "Ping", "Pong"])),
                 }
             }
         }
@@ -287,5 +719,212 @@ mod tests {
             _ => panic!("Message type mismatch"),
         }
     }
+    
+    use elysium_core::{Account, Blockchain};
+    use elysium_core::account::KeyPair;
+    
+    /// Reserve a free local port for a test listener
+    fn free_addr() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+    
+    /// Poll a condition until it holds or the timeout expires
+    async fn eventually<F, Fut>(mut condition: F) -> bool
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        for _ in 0..200 {
+            if condition().await {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+    
+    /// Start a line of networks A -> B -> C sharing one genesis, with `funded` holding a balance
+    async fn start_line(funded: &KeyPair) -> Vec<Node> {
+        let mut blockchain = Blockchain::new(1);
+        let mut account = Account::new(funded.address());
+        account.balance = 1000;
+        blockchain.accounts.insert(funded.address(), account);
+        
+        let addrs: Vec<SocketAddr> = (0..3).map(|_| free_addr()).collect();
+        let mut nodes = Vec::new();
+        for (i, addr) in addrs.iter().enumerate() {
+            let node = Node::from_blockchain(blockchain.clone());
+            let network = Network::new(*addr, node.clone());
+            if let Some(next) = addrs.get(i + 1) {
+                network.add_peer(*next);
+            }
+            network.listen().await.unwrap();
+            nodes.push(node);
+        }
+        nodes
+    }
+    
+    #[tokio::test]
+    async fn test_transaction_gossip_reaches_every_node() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let nodes = start_line(&alice).await;
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 10, 0);
+        tx.sign(alice.signing_key()).unwrap();
+        nodes[0].add_transaction(tx.clone()).await.unwrap();
+        
+        let last = nodes[2].clone();
+        let hash = tx.hash();
+        assert!(eventually(|| {
+            let last = last.clone();
+            let hash = hash.clone();
+            async move {
+                last.blockchain().await.mempool.contains(&hash)
+            }
+        }).await);
+    }
+    
+    #[tokio::test]
+    async fn test_block_gossip_reaches_every_node() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let nodes = start_line(&alice).await;
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 10, 0);
+        tx.sign(alice.signing_key()).unwrap();
+        nodes[0].add_transaction(tx).await.unwrap();
+        
+        // Let the transaction propagate before the block so every pool holds it
+        let last = nodes[2].clone();
+        assert!(eventually(|| {
+            let last = last.clone();
+            async move { !last.blockchain().await.mempool.is_empty() }
+        }).await);
+        
+        nodes[0].mine_block().await.unwrap();
+        
+        assert!(eventually(|| {
+            let last = last.clone();
+            async move { last.height().await == 2 }
+        }).await);
+        let blockchain = nodes[2].blockchain().await;
+        assert_eq!(blockchain.get_balance(&bob.address()), 10);
+        assert!(blockchain.mempool.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_connection_is_persistent_and_bidirectional() {
+        let addr = free_addr();
+        let network = Network::new(addr, Node::new(1));
+        network.listen().await.unwrap();
+        
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let status = protocol::read_message(&mut stream).await.unwrap();
+        assert!(matches!(status, Some(Message::Status(ChainStatus { height: 1, .. }))));
+        
+        // A multi-megabyte batch arrives in many reads and must not break the stream
+        let from = KeyPair::generate().address();
+        let to = KeyPair::generate().address();
+        let mut block = Block::new(1, "0".repeat(64), 1);
+        block.transactions = (0..30_000)
+            .map(|nonce| Transaction::new(from.clone(), to.clone(), 1, nonce))
+            .collect();
+        let batch = Message::Blocks(vec![block]);
+        assert!(protocol::encode_frame(&batch).unwrap().len() > 2 * 1024 * 1024);
+        protocol::write_message(&mut stream, &batch).await.unwrap();
+        
+        for _ in 0..2 {
+            protocol::write_message(&mut stream, &Message::Ping).await.unwrap();
+            let reply = protocol::read_message(&mut stream).await.unwrap();
+            assert!(matches!(reply, Some(Message::Pong)));
+        }
+        assert_eq!(network.connected_peers().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_frame_closes_connection() {
+        let addr = free_addr();
+        let network = Network::new(addr, Node::new(1));
+        network.listen().await.unwrap();
+        
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let status = protocol::read_message(&mut stream).await.unwrap();
+        assert!(matches!(status, Some(Message::Status(_))));
+        
+        let oversized = ((protocol::MAX_FRAME_SIZE + 1) as u32).to_be_bytes();
+        tokio::io::AsyncWriteExt::write_all(&mut stream, &oversized).await.unwrap();
+        
+        let closed = tokio::time::timeout(
+            Duration::from_secs(5),
+            protocol::read_message(&mut stream),
+        ).await.unwrap();
+        assert!(matches!(closed, Ok(None)));
+    }
+    
+    #[test]
+    fn test_message_binary_roundtrip() {
+        let alice = KeyPair::generate();
+        let mut tx = Transaction::new(alice.address(), KeyPair::generate().address(), 5, 0);
+        tx.sign(alice.signing_key()).unwrap();
+        let status = ChainStatus { height: 7, tip_hash: "ab".repeat(32) };
+        
+        let bytes = Message::NewTransaction(tx.clone()).to_bytes().unwrap();
+        match Message::from_bytes(&bytes).unwrap() {
+            Message::NewTransaction(decoded) => assert_eq!(decoded.hash(), tx.hash()),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        
+        let bytes = Message::Status(status.clone()).to_bytes().unwrap();
+        match Message::from_bytes(&bytes).unwrap() {
+            Message::Status(decoded) => assert_eq!(decoded, status),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        
+        assert!(Message::from_bytes(&[elysium_core::CODEC_VERSION, 99]).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_initial_block_download_in_batches() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut blockchain = Blockchain::new(1);
+        let mut account = Account::new(alice.address());
+        account.balance = 1000;
+        blockchain.accounts.insert(alice.address(), account);
+        let genesis = blockchain.clone();
+        
+        // More blocks than fit in one batch
+        for nonce in 0..(MAX_BLOCKS_PER_BATCH as u64 + 10) {
+            let mut tx = Transaction::new(alice.address(), bob.address(), 1, nonce);
+            tx.sign(alice.signing_key()).unwrap();
+            blockchain.add_transaction(tx).unwrap();
+            blockchain.mine_block().unwrap();
+        }
+        let target_height = blockchain.height();
+        
+        let ahead_addr = free_addr();
+        let ahead = Node::from_blockchain(blockchain);
+        Network::new(ahead_addr, ahead.clone()).listen().await.unwrap();
+        
+        let behind = Node::from_blockchain(genesis);
+        let network = Network::new(free_addr(), behind.clone());
+        network.add_peer(ahead_addr);
+        network.listen().await.unwrap();
+        
+        let node = behind.clone();
+        assert!(eventually(|| {
+            let node = node.clone();
+            async move { node.height().await == target_height }
+        }).await);
+        
+        let status = behind.sync_status().await;
+        assert!(!status.syncing);
+        assert_eq!(status.target_height, target_height);
+        assert_eq!(
+            behind.blockchain().await.get_balance(&bob.address()),
+            MAX_BLOCKS_PER_BATCH as u64 + 10,
+        );
+    }
 }
-
@@ -1,24 +1,87 @@
-use elysium_core::{Blockchain, Block, Transaction, Result};
+use crate::miner::{search_nonce, MiningCounters, StopOnDrop};
+use crate::{MiningStats, SyncManager, SyncStatus};
+use elysium_core::{Blockchain, Block, BlockHeader, ElysiumError, Transaction, FileStorage, GenesisSpec, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{info, error};
+use std::time::Instant;
+use tokio::sync::{broadcast, RwLock};
+
+/// Capacity of the node event channel; slow subscribers lag and skip older events
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Events emitted by the node when its chain or transaction pool changes
+#[derive(Debug, Clone)]
+pub enum NodeEvent {
+    /// A block was added to the chain (mined locally or received)
+    NewBlock(Block),
+    /// The chain has a new tip, after extending the chain or a reorganization
+    NewHead(BlockHeader),
+    /// A transaction was accepted into the pending pool
+    NewTransaction(Transaction),
+}
 
 /// Blockchain node
 #[derive(Clone)]
 pub struct Node {
     blockchain: Arc<RwLock<Blockchain>>,
     is_mining: Arc<RwLock<bool>>,
+    pub(crate) mining: Arc<MiningCounters>,
+    events: broadcast::Sender<NodeEvent>,
+    sync: Arc<SyncManager>,
 }
 
 impl Node {
     /// Create a new node
     pub fn new(difficulty: u64) -> Self {
+        Self::from_blockchain(Blockchain::new(difficulty))
+    }
+    
+    /// Create a node whose chain starts from a genesis specification
+    pub fn from_genesis(spec: &GenesisSpec) -> Result<Self> {
+        Ok(Self::from_blockchain(Blockchain::from_genesis(spec)?))
+    }
+    
+    /// Create a node around an existing blockchain
+    pub fn from_blockchain(blockchain: Blockchain) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
-            blockchain: Arc::new(RwLock::new(Blockchain::new(difficulty))),
+            blockchain: Arc::new(RwLock::new(blockchain)),
             is_mining: Arc::new(RwLock::new(false)),
+            mining: Arc::new(MiningCounters::default()),
+            events,
+            sync: Arc::new(SyncManager::new()),
         }
     }
     
+    /// Create a node whose chain is persisted in a data directory
+    ///
+    /// If the directory already holds a chain, it is verified and reloaded.
+    pub fn open(difficulty: u64, data_dir: impl AsRef<Path>) -> Result<Self> {
+        let storage = FileStorage::open(data_dir)?;
+        let blockchain = Blockchain::open(difficulty, Arc::new(storage))?;
+        Ok(Self::from_blockchain(blockchain))
+    }
+    
+    /// Create a node persisted in a data directory whose chain starts from a genesis specification
+    ///
+    /// If the directory already holds a chain, it must have been created from the same spec.
+    pub fn open_genesis(spec: &GenesisSpec, data_dir: impl AsRef<Path>) -> Result<Self> {
+        let storage = FileStorage::open(data_dir)?;
+        let blockchain = Blockchain::open_genesis(spec, Arc::new(storage))?;
+        Ok(Self::from_blockchain(blockchain))
+    }
+    
+    /// Subscribe to node events
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Publish an event; having no subscribers is not an error
+    fn emit(&self, event: NodeEvent) {
+        let _ = self.events.send(event);
+    }
+    
     /// Get the blockchain
     pub async fn blockchain(&self) -> tokio::sync::RwLockReadGuard<'_, Blockchain> {
         self.blockchain.read().await
@@ -32,19 +95,92 @@ impl Node {
     /// Add a transaction
     pub async fn add_transaction(&self, tx: Transaction) -> Result<()> {
         let mut blockchain = self.blockchain.write().await;
-        blockchain.add_transaction(tx)
+        blockchain.add_transaction(tx.clone())?;
+        drop(blockchain);
+        self.emit(NodeEvent::NewTransaction(tx));
+        Ok(())
     }
     
-    /// Mine a block
+    /// Mine a block of pending transactions on top of the current tip
+    ///
+    /// The chain is only locked to build the block and to add it, not during the nonce
+    /// search. If another block becomes the tip first, mining starts over on top of it.
     pub async fn mine_block(&self) -> Result<Block> {
+        loop {
+            let template = self.blockchain.read().await.mining_template()?;
+            let stop = Arc::new(AtomicBool::new(false));
+            if let Some(block) = self.mine_template(template, 1, stop).await? {
+                return Ok(block);
+            }
+        }
+    }
+    
+    /// Seal a block template, then add the block
+    ///
+    /// Under proof of work the nonce is searched for on `threads` threads; other engines
+    /// seal with the node's validator key. Returns `None` if `stop` was set before a nonce
+    /// was found, or if the block's parent is no longer the tip by then. Dropping the
+    /// future also stops the search.
+    pub(crate) async fn mine_template(&self, mut block: Block, threads: usize, stop: Arc<AtomicBool>) -> Result<Option<Block>> {
+        let (consensus, validator_key) = {
+            let blockchain = self.blockchain.read().await;
+            (blockchain.consensus().clone(), blockchain.validator_key.clone())
+        };
+        if consensus.is_proof_of_work() {
+            let Some(nonce) = self.find_nonce(&block.header, threads, stop).await? else {
+                self.mining.stale_templates.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            };
+            block.header.nonce = nonce;
+        } else {
+            consensus.seal(&mut block.header, validator_key.as_ref())?;
+        }
+        
         let mut blockchain = self.blockchain.write().await;
-        blockchain.mine_block()
+        if blockchain.latest_block().hash() != block.header.parent_hash {
+            self.mining.stale_templates.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        blockchain.add_block(block.clone())?;
+        drop(blockchain);
+        self.mining.blocks_mined.fetch_add(1, Ordering::Relaxed);
+        self.emit(NodeEvent::NewBlock(block.clone()));
+        self.emit(NodeEvent::NewHead(block.header.clone()));
+        Ok(Some(block))
+    }
+    
+    /// Search for a proof-of-work nonce of a header on `threads` blocking threads until
+    /// one is found or `stop` is set
+    async fn find_nonce(&self, header: &BlockHeader, threads: usize, stop: Arc<AtomicBool>) -> Result<Option<u64>> {
+        let _stop_on_drop = StopOnDrop(stop.clone());
+        let header = header.clone();
+        let counters = self.mining.clone();
+        let started = Instant::now();
+        tokio::task::spawn_blocking(move || {
+            let before = counters.hashes.load(Ordering::Relaxed);
+            let nonce = search_nonce(&header, threads, &stop, &counters.hashes);
+            let hashes = counters.hashes.load(Ordering::Relaxed) - before;
+            counters.record_search(hashes, started.elapsed());
+            nonce
+        })
+        .await
+        .map_err(|e| ElysiumError::BlockchainError(format!("Nonce search failed: {}", e)))
     }
     
     /// Add a block (for syncing)
     pub async fn add_block(&self, block: Block) -> Result<()> {
         let mut blockchain = self.blockchain.write().await;
-        blockchain.add_block(block)
+        let old_tip = blockchain.latest_block().hash();
+        blockchain.add_block(block.clone())?;
+        // Blocks on a side branch leave the tip alone until the branch takes over
+        let new_head = (blockchain.latest_block().hash() != old_tip)
+            .then(|| blockchain.latest_block().header.clone());
+        drop(blockchain);
+        self.emit(NodeEvent::NewBlock(block));
+        if let Some(header) = new_head {
+            self.emit(NodeEvent::NewHead(header));
+        }
+        Ok(())
     }
     
     /// Get blockchain height
@@ -53,6 +189,17 @@ impl Node {
         blockchain.height()
     }
     
+    /// Get the block download manager
+    pub fn sync(&self) -> &SyncManager {
+        &self.sync
+    }
+    
+    /// Get the block download progress
+    pub async fn sync_status(&self) -> SyncStatus {
+        let height = self.height().await;
+        self.sync.status(height)
+    }
+    
     /// Check if node is mining
     pub async fn is_mining(&self) -> bool {
         *self.is_mining.read().await
@@ -62,11 +209,17 @@ impl Node {
     pub async fn set_mining(&self, mining: bool) {
         *self.is_mining.write().await = mining;
     }
+    
+    /// Hashrate and block counts of the node's mining
+    pub async fn mining_stats(&self) -> MiningStats {
+        self.mining.stats(self.is_mining().await)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{funded_spec, TEST_CHAIN_ID};
     use elysium_core::account::KeyPair;
     
     #[tokio::test]
@@ -75,6 +228,40 @@ mod tests {
         assert_eq!(node.height().await, 1); // Genesis block
     }
     
+    #[tokio::test]
+    async fn test_node_reopen_from_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let keypair1 = KeyPair::generate();
+        let keypair2 = KeyPair::generate();
+        
+        let tip_hash = {
+            let node = Node::open(1, dir.path()).unwrap();
+            
+            // Give keypair1 some balance
+            let mut blockchain = node.blockchain_mut().await;
+            let mut account = elysium_core::Account::new(keypair1.address());
+            account.balance = 100;
+            blockchain.accounts.insert(keypair1.address(), account);
+            drop(blockchain);
+            
+            let mut tx = Transaction::new(
+                keypair1.address(),
+                keypair2.address(),
+                40,
+                0,
+            );
+            tx.sign(keypair1.signing_key()).unwrap();
+            node.add_transaction(tx).await.unwrap();
+            node.mine_block().await.unwrap().hash()
+        };
+        
+        let node = Node::open(1, dir.path()).unwrap();
+        assert_eq!(node.height().await, 2);
+        let blockchain = node.blockchain().await;
+        assert_eq!(blockchain.latest_block().hash(), tip_hash);
+        assert_eq!(blockchain.get_balance(&keypair2.address()), 40);
+    }
+    
     #[tokio::test]
     async fn test_node_add_transaction() {
         let node = Node::new(1);
@@ -88,14 +275,47 @@ mod tests {
         blockchain.accounts.insert(keypair1.address(), account);
         drop(blockchain);
         
-        let tx = Transaction::new(
+        let mut tx = Transaction::new(
             keypair1.address(),
             keypair2.address(),
             50,
             0,
         );
+        tx.sign(keypair1.signing_key()).unwrap();
         
         assert!(node.add_transaction(tx).await.is_ok());
     }
+    
+    #[tokio::test]
+    async fn test_new_head_events() {
+        let keypair = KeyPair::generate();
+        let spec = funded_spec(&keypair);
+        let node = Node::from_genesis(&spec).unwrap();
+        let mut events = node.subscribe();
+        
+        let mut tx = Transaction::new(keypair.address(), KeyPair::generate().address(), 10, 0);
+        tx.chain_id = TEST_CHAIN_ID;
+        keypair.sign_transaction(&mut tx).unwrap();
+        node.add_transaction(tx).await.unwrap();
+        assert!(matches!(events.recv().await.unwrap(), NodeEvent::NewTransaction(_)));
+        
+        let block = node.mine_block().await.unwrap();
+        assert!(matches!(events.recv().await.unwrap(), NodeEvent::NewBlock(b) if b.hash() == block.hash()));
+        assert!(matches!(events.recv().await.unwrap(), NodeEvent::NewHead(h) if h.hash() == block.hash()));
+        
+        // A block on a side branch is announced but does not move the head
+        let follower = Node::from_genesis(&spec).unwrap();
+        let mut follower_events = follower.subscribe();
+        follower.add_block(block.clone()).await.unwrap();
+        assert!(matches!(follower_events.recv().await.unwrap(), NodeEvent::NewBlock(_)));
+        assert!(matches!(follower_events.recv().await.unwrap(), NodeEvent::NewHead(h) if h.hash() == block.hash()));
+        
+        let mut fork = Block::new(1, block.header.parent_hash.clone(), 1);
+        fork.header.timestamp = block.header.timestamp;
+        fork.mine();
+        follower.add_block(fork).await.unwrap();
+        assert!(matches!(follower_events.recv().await.unwrap(), NodeEvent::NewBlock(_)));
+        assert!(follower_events.try_recv().is_err());
+    }
 }
 
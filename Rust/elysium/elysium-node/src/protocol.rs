@@ -0,0 +1,179 @@
+use crate::Message;
+use elysium_core::{ElysiumError, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Version of the P2P wire protocol, sent as the first byte of every frame body
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Maximum size of a frame body (version byte plus payload) in bytes
+pub const MAX_FRAME_SIZE: usize = 32 * 1024 * 1024;
+
+/// Size of the big-endian length prefix in bytes
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Encode a message as a frame: `[u32 length][u8 version][payload]`
+///
+/// The payload is the message in the canonical binary encoding (see [`elysium_core::codec`]).
+///
+/// The length covers the version byte and the payload.
+pub fn encode_frame(message: &Message) -> Result<Vec<u8>> {
+    let payload = message.to_bytes()?;
+    let body_len = payload.len() + 1;
+    if body_len > MAX_FRAME_SIZE {
+        return Err(ElysiumError::SerializationError(
+            format!("Message of {} bytes exceeds maximum frame size of {} bytes", body_len, MAX_FRAME_SIZE)
+        ));
+    }
+    
+    let mut frame = Vec::with_capacity(LENGTH_PREFIX_SIZE + body_len);
+    frame.extend_from_slice(&(body_len as u32).to_be_bytes());
+    frame.push(PROTOCOL_VERSION);
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Write a single framed message
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<()> {
+    let frame = encode_frame(message)?;
+    writer.write_all(&frame).await
+        .map_err(|e| ElysiumError::BlockchainError(format!("Failed to write to peer: {}", e)))?;
+    writer.flush().await
+        .map_err(|e| ElysiumError::BlockchainError(format!("Failed to write to peer: {}", e)))?;
+    Ok(())
+}
+
+/// Read a single framed message
+///
+/// Returns `Ok(None)` if the stream was closed cleanly between frames.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+    let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+    let mut filled = 0;
+    while filled < LENGTH_PREFIX_SIZE {
+        let n = reader.read(&mut prefix[filled..]).await
+            .map_err(|e| ElysiumError::BlockchainError(format!("Failed to read from peer: {}", e)))?;
+        if n == 0 {
+            if filled == 0 {
+                return Ok(None);
+            }
+            return Err(ElysiumError::SerializationError(
+                "Connection closed inside a frame header".to_string()
+            ));
+        }
+        filled += n;
+    }
+    
+    let body_len = u32::from_be_bytes(prefix) as usize;
+    if body_len == 0 {
+        return Err(ElysiumError::SerializationError("Empty frame".to_string()));
+    }
+    if body_len > MAX_FRAME_SIZE {
+        return Err(ElysiumError::SerializationError(
+            format!("Frame of {} bytes exceeds maximum frame size of {} bytes", body_len, MAX_FRAME_SIZE)
+        ));
+    }
+    
+    let mut body = vec![0u8; body_len];
+    reader.read_exact(&mut body).await
+        .map_err(|e| ElysiumError::BlockchainError(format!("Failed to read from peer: {}", e)))?;
+    
+    if body[0] != PROTOCOL_VERSION {
+        return Err(ElysiumError::SerializationError(
+            format!("Unsupported protocol version {} (expected {})", body[0], PROTOCOL_VERSION)
+        ));
+    }
+    
+    Message::from_bytes(&body[1..]).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elysium_core::{Block, Transaction};
+    use elysium_core::account::KeyPair;
+    
+    /// A `Blocks` message of several megabytes
+    fn large_blocks_message() -> Message {
+        let from = KeyPair::generate().address();
+        let to = KeyPair::generate().address();
+        
+        let blocks = (0..4).map(|number| {
+            let mut block = Block::new(number, "0".repeat(64), 1);
+            // Push directly: recomputing the root per transaction is quadratic
+            block.transactions = (0..8_000)
+                .map(|nonce| Transaction::new(from.clone(), to.clone(), 1, nonce))
+                .collect();
+            block
+        }).collect();
+        
+        Message::Blocks(blocks)
+    }
+    
+    #[tokio::test]
+    async fn test_frames_roundtrip_across_partial_reads() {
+        let message = large_blocks_message();
+        let frame_len = encode_frame(&message).unwrap().len();
+        assert!(frame_len > 2 * 1024 * 1024);
+        
+        // A small duplex buffer forces every frame to arrive in many pieces
+        let (mut client, mut server) = tokio::io::duplex(16 * 1024);
+        let writer = tokio::spawn(async move {
+            write_message(&mut client, &message).await.unwrap();
+            write_message(&mut client, &Message::Ping).await.unwrap();
+            write_message(&mut client, &message).await.unwrap();
+        });
+        
+        match read_message(&mut server).await.unwrap() {
+            Some(Message::Blocks(blocks)) => {
+                assert_eq!(blocks.len(), 4);
+                assert_eq!(blocks[3].transactions.len(), 8_000);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        assert!(matches!(read_message(&mut server).await.unwrap(), Some(Message::Ping)));
+        assert!(matches!(read_message(&mut server).await.unwrap(), Some(Message::Blocks(_))));
+        
+        writer.await.unwrap();
+        assert!(read_message(&mut server).await.unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_multiple_frames_in_one_write() {
+        let mut bytes = encode_frame(&Message::Ping).unwrap();
+        bytes.extend(encode_frame(&Message::Pong).unwrap());
+        bytes.extend(encode_frame(&Message::RequestBlocks(7)).unwrap());
+        
+        let mut reader = bytes.as_slice();
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Some(Message::Ping)));
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Some(Message::Pong)));
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Some(Message::RequestBlocks(7))));
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_frame_rejected() {
+        let mut bytes = ((MAX_FRAME_SIZE + 1) as u32).to_be_bytes().to_vec();
+        bytes.push(PROTOCOL_VERSION);
+        
+        let mut reader = bytes.as_slice();
+        assert!(read_message(&mut reader).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_unknown_version_rejected() {
+        let mut bytes = encode_frame(&Message::Ping).unwrap();
+        bytes[LENGTH_PREFIX_SIZE] = PROTOCOL_VERSION + 1;
+        
+        let mut reader = bytes.as_slice();
+        assert!(read_message(&mut reader).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_truncated_frame_rejected() {
+        let bytes = encode_frame(&Message::Ping).unwrap();
+        
+        let mut reader = &bytes[..bytes.len() - 1];
+        assert!(read_message(&mut reader).await.is_err());
+        let mut reader = &bytes[..2];
+        assert!(read_message(&mut reader).await.is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{ElysiumRpcMethods, Node, NodeEvent, RpcParams};
+use elysium_core::{Address, ElysiumError, Result};
+use jsonrpc_core::{Error, MetaIoHandler, Params, Result as RpcResult, Value};
+use jsonrpc_pubsub::{PubSubHandler, Session, Sink, Subscriber, SubscriptionId};
+use jsonrpc_ws_server::{RequestContext, Server, ServerBuilder};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Method of the notifications sent for a subscription
+pub const SUBSCRIPTION_NOTIFICATION: &str = "elysium_subscription";
+
+/// What an `elysium_subscribe` call asks to be notified about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    /// Header of every new chain tip: `["newHeads"]`
+    NewHeads,
+    /// Every transaction accepted into the pending pool: `["newPendingTransactions"]`
+    PendingTransactions,
+    /// Balance of an address, whenever a new tip changes it: `["balance", address]`
+    Balance(Address),
+}
+
+impl SubscriptionKind {
+    pub fn from_params(params: Params) -> RpcResult<Self> {
+        let params = RpcParams::new(params)?;
+        match params.str(0, "Invalid subscription")? {
+            "newHeads" => Ok(Self::NewHeads),
+            "newPendingTransactions" => Ok(Self::PendingTransactions),
+            "balance" => Ok(Self::Balance(params.address(1)?)),
+            other => Err(Error::invalid_params(format!("Unknown subscription {}", other))),
+        }
+    }
+}
+
+/// Running subscriptions, each a task forwarding node events to one WebSocket session
+#[derive(Clone)]
+struct Subscriptions {
+    node: Node,
+    runtime: Handle,
+    next_id: Arc<AtomicU64>,
+    active: Arc<Mutex<HashMap<u64, ActiveSubscription>>>,
+}
+
+/// Task of a subscription, with the session that owns it
+struct ActiveSubscription {
+    session: usize,
+    task: JoinHandle<()>,
+}
+
+impl Subscriptions {
+    fn new(node: Node, runtime: Handle) -> Self {
+        Self {
+            node,
+            runtime,
+            next_id: Arc::new(AtomicU64::new(1)),
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    
+    fn subscribe(&self, params: Params, session: Arc<Session>, subscriber: Subscriber) {
+        let kind = match SubscriptionKind::from_params(params) {
+            Ok(kind) => kind,
+            Err(e) => {
+                let _ = subscriber.reject(e);
+                return;
+            }
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // Listen before answering, so no event after the subscription is missed
+        let events = self.node.subscribe();
+        let node = self.node.clone();
+        let active = self.active.clone();
+        
+        // Hold the lock while spawning so the task cannot remove its entry before it exists
+        let mut tasks = self.active.lock().unwrap();
+        let task = self.runtime.spawn(async move {
+            match subscriber.assign_id_async(SubscriptionId::Number(id)).await {
+                Ok(sink) => forward(node, kind, events, sink, id).await,
+                // The request was dropped, so no unsubscribe will come for this id
+                Err(()) => {
+                    active.lock().unwrap().remove(&id);
+                }
+            }
+        });
+        tasks.insert(id, ActiveSubscription { session: session_key(&session), task });
+    }
+    
+    /// Cancel a subscription; `session` is `None` when its session has closed
+    fn unsubscribe(&self, id: SubscriptionId, session: Option<Arc<Session>>) -> RpcResult<Value> {
+        let id = match id {
+            SubscriptionId::Number(id) => id,
+            SubscriptionId::String(_) => return Ok(Value::Bool(false)),
+        };
+        let mut active = self.active.lock().unwrap();
+        // Sessions may only cancel their own subscriptions
+        let owned = match (active.get(&id), &session) {
+            (Some(active), Some(session)) => active.session == session_key(session),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if owned {
+            if let Some(subscription) = active.remove(&id) {
+                subscription.task.abort();
+            }
+        }
+        Ok(Value::Bool(owned))
+    }
+    
+    fn close(&self) {
+        for (_, subscription) in self.active.lock().unwrap().drain() {
+            subscription.task.abort();
+        }
+    }
+}
+
+fn session_key(session: &Arc<Session>) -> usize {
+    Arc::as_ptr(session) as usize
+}
+
+/// Send the events a subscription asked for until its session closes
+async fn forward(
+    node: Node,
+    kind: SubscriptionKind,
+    mut events: broadcast::Receiver<NodeEvent>,
+    sink: Sink,
+    id: u64,
+) {
+    let mut last_balance = match &kind {
+        SubscriptionKind::Balance(address) => Some(node.blockchain().await.get_balance(address)),
+        _ => None,
+    };
+    
+    loop {
+        let result = match (events.recv().await, &kind) {
+            (Ok(NodeEvent::NewHead(header)), SubscriptionKind::NewHeads) => json!(header),
+            (Ok(NodeEvent::NewTransaction(tx)), SubscriptionKind::PendingTransactions) => json!(tx),
+            // After missed events the balance may have changed, so compare it again
+            (Ok(NodeEvent::NewHead(_)) | Err(RecvError::Lagged(_)), SubscriptionKind::Balance(address)) => {
+                let blockchain = node.blockchain().await;
+                let balance = blockchain.get_balance(address);
+                if last_balance == Some(balance) {
+                    continue;
+                }
+                last_balance = Some(balance);
+                json!({
+                    "address": address,
+                    "balance": balance,
+                    "block": blockchain.latest_block().header.number,
+                })
+            }
+            (Err(RecvError::Closed), _) => break,
+            _ => continue,
+        };
+        
+        let mut params = serde_json::Map::new();
+        params.insert("subscription".to_string(), json!(id));
+        params.insert("result".to_string(), result);
+        if sink.notify(Params::Map(params)).is_err() {
+            break;
+        }
+    }
+}
+
+/// WebSocket JSON-RPC server of a node
+///
+/// Serves the same methods as [`crate::RpcServer`] plus `elysium_subscribe` and
+/// `elysium_unsubscribe`. Events are pushed as `elysium_subscription` notifications.
+pub struct WsServer {
+    server: Option<Server>,
+    subscriptions: Subscriptions,
+    address: SocketAddr,
+}
+
+impl WsServer {
+    /// Serve a node on `addr`; port 0 picks a free port
+    ///
+    /// Must be called inside a Tokio runtime, which runs the subscriptions.
+    pub fn start(node: Node, addr: SocketAddr) -> Result<Self> {
+        let runtime = Handle::try_current().map_err(|_| ElysiumError::BlockchainError(
+            "The WebSocket server must be started inside a Tokio runtime".to_string()
+        ))?;
+        let subscriptions = Subscriptions::new(node.clone(), runtime);
+        
+        let mut io = PubSubHandler::new(MetaIoHandler::default());
+        ElysiumRpcMethods::new(node).register(&mut io);
+        let subscribe = subscriptions.clone();
+        let unsubscribe = subscriptions.clone();
+        io.add_subscription(
+            SUBSCRIPTION_NOTIFICATION,
+            ("elysium_subscribe", move |params, session, subscriber| {
+                subscribe.subscribe(params, session, subscriber)
+            }),
+            ("elysium_unsubscribe", move |id, session| {
+                futures::future::ready(unsubscribe.unsubscribe(id, session))
+            }),
+        );
+        
+        let server = ServerBuilder::with_meta_extractor(io, |context: &RequestContext| {
+            Arc::new(Session::new(context.sender()))
+        })
+            .start(&addr)
+            .map_err(|e| ElysiumError::BlockchainError(
+                format!("Failed to start WebSocket server on {}: {}", addr, e)
+            ))?;
+        let address = *server.addr();
+        info!("WebSocket server started on {}", address);
+        Ok(Self { server: Some(server), subscriptions, address })
+    }
+    
+    /// Address the server is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+    
+    /// Stop serving requests and end all subscriptions
+    pub fn close(self) {
+        drop(self);
+    }
+}
+
+impl Drop for WsServer {
+    fn drop(&mut self) {
+        self.subscriptions.close();
+        // Like the HTTP server, shutting down drops a runtime, which Tokio forbids on async threads
+        if let Some(server) = self.server.take() {
+            let _ = std::thread::spawn(move || server.close()).join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{funded_node, TEST_CHAIN_ID};
+    use elysium_core::{account::KeyPair, Transaction};
+    use futures::StreamExt;
+    
+    fn session() -> Arc<Session> {
+        let (sender, _) = futures::channel::mpsc::unbounded();
+        Arc::new(Session::new(sender))
+    }
+    
+    /// Subscribe and return the id with the stream of notification payloads
+    async fn subscribe(
+        subscriptions: &Subscriptions,
+        session: &Arc<Session>,
+        params: Value,
+    ) -> (SubscriptionId, impl futures::Stream<Item = String>) {
+        let (subscriber, id, notifications) = Subscriber::new_test(SUBSCRIPTION_NOTIFICATION);
+        subscriptions.subscribe(serde_json::from_value(params).unwrap(), session.clone(), subscriber);
+        (id.await.unwrap().unwrap(), notifications)
+    }
+    
+    fn result(notification: &str) -> Value {
+        let notification: Value = serde_json::from_str(notification).unwrap();
+        notification["params"]["result"].clone()
+    }
+    
+    #[test]
+    fn test_subscription_kinds() {
+        let address = KeyPair::generate().address();
+        let parse = |params: Value| SubscriptionKind::from_params(serde_json::from_value(params).unwrap());
+        assert_eq!(parse(json!(["newHeads"])).unwrap(), SubscriptionKind::NewHeads);
+        assert_eq!(parse(json!(["newPendingTransactions"])).unwrap(), SubscriptionKind::PendingTransactions);
+        assert_eq!(parse(json!(["balance", address])).unwrap(), SubscriptionKind::Balance(address));
+        assert!(parse(json!(["balance"])).is_err());
+        assert!(parse(json!(["logs"])).is_err());
+        assert!(parse(json!([])).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_subscriptions_follow_node_events() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let node = funded_node(&alice);
+        let subscriptions = Subscriptions::new(node.clone(), Handle::current());
+        let session = session();
+        
+        let (_, mut heads) = subscribe(&subscriptions, &session, json!(["newHeads"])).await;
+        let (_, mut pending) = subscribe(&subscriptions, &session, json!(["newPendingTransactions"])).await;
+        let (_, mut balances) = subscribe(&subscriptions, &session, json!(["balance", bob.address()])).await;
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 40, 0);
+        tx.chain_id = TEST_CHAIN_ID;
+        alice.sign_transaction(&mut tx).unwrap();
+        node.add_transaction(tx.clone()).await.unwrap();
+        assert_eq!(result(&pending.next().await.unwrap())["nonce"], 0);
+        
+        let block = node.mine_block().await.unwrap();
+        assert_eq!(result(&heads.next().await.unwrap())["number"], 1);
+        let change = result(&balances.next().await.unwrap());
+        assert_eq!(change["balance"], 40);
+        assert_eq!(change["block"], block.header.number);
+    }
+    
+    #[tokio::test]
+    async fn test_unsubscribe() {
+        let node = Node::new(1);
+        let subscriptions = Subscriptions::new(node, Handle::current());
+        let owner = session();
+        let (id, _notifications) = subscribe(&subscriptions, &owner, json!(["newHeads"])).await;
+        
+        // Another session cannot cancel it, the owner can, once
+        assert_eq!(subscriptions.unsubscribe(id.clone(), Some(session())).unwrap(), false);
+        assert_eq!(subscriptions.unsubscribe(id.clone(), Some(owner.clone())).unwrap(), true);
+        assert_eq!(subscriptions.unsubscribe(id, Some(owner)).unwrap(), false);
+        assert!(subscriptions.active.lock().unwrap().is_empty());
+        
+        // Unknown subscriptions are rejected
+        let (subscriber, id, _) = Subscriber::new_test(SUBSCRIPTION_NOTIFICATION);
+        subscriptions.subscribe(serde_json::from_value(json!(["logs"])).unwrap(), session(), subscriber);
+        assert!(id.await.unwrap().is_err());
+    }
+}
@@ -0,0 +1,487 @@
+use crate::{MiningStats, Node, SyncStatus};
+use elysium_core::{
+    Address, AddressHistory, Block, BlockHeader, CallResult, ElysiumError, Result, StateProof, Transaction,
+    TransactionLookup, TransactionProof, TransactionReceipt, DEFAULT_HISTORY_PAGE, MAX_BLOCK_GAS, MAX_HEADERS_PER_REQUEST,
+};
+use jsonrpc_core::{Error, ErrorCode, MetaIoHandler, Metadata, Params, Result as RpcResult, Value};
+use jsonrpc_http_server::{Server, ServerBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use tracing::info;
+
+/// JSON-RPC methods of a node
+///
+/// Handlers are async and work on the node's own chain, so RPC clients see the blocks the
+/// node mines and syncs. [`ElysiumRpcMethods::register`] is the single list of method names.
+#[derive(Clone)]
+pub struct ElysiumRpcMethods {
+    node: Node,
+}
+
+impl ElysiumRpcMethods {
+    pub fn new(node: Node) -> Self {
+        Self { node }
+    }
+    
+    /// Register every method on an I/O handler
+    pub fn register<M: Metadata>(&self, io: &mut MetaIoHandler<M>) {
+        self.add(io, "elysium_getHeight", |rpc, _| async move {
+            to_json(rpc.get_height().await)
+        });
+        self.add(io, "elysium_getBlockByNumber", |rpc, params| async move {
+            let number = params.u64(0, "Invalid block number")?;
+            to_json(rpc.get_block_by_number(number).await)
+        });
+        self.add(io, "elysium_getBlockByHash", |rpc, params| async move {
+            let block_hash = params.str(0, "Invalid block hash")?;
+            to_json(rpc.get_block_by_hash(block_hash).await)
+        });
+        self.add(io, "elysium_getLatestBlock", |rpc, _| async move {
+            to_json(rpc.get_latest_block().await)
+        });
+        self.add(io, "elysium_getHeaders", |rpc, params| async move {
+            let start = params.u64(0, "Invalid block number")?;
+            let count = params.parse_or(1, MAX_HEADERS_PER_REQUEST, "Invalid count")?;
+            to_json(rpc.get_headers(start, count).await)
+        });
+        self.add(io, "elysium_getBalance", |rpc, params| async move {
+            to_json(rpc.get_balance(&params.address(0)?).await)
+        });
+        self.add(io, "elysium_getNonce", |rpc, params| async move {
+            let address = params.address(0)?;
+            let pending = match params.parse_or(1, None::<String>, "Invalid block tag")?.as_deref() {
+                None | Some("latest") => false,
+                Some("pending") => true,
+                Some(_) => return Err(Error::invalid_params("Block tag must be \"latest\" or \"pending\"")),
+            };
+            to_json(rpc.get_nonce(&address, pending).await)
+        });
+        self.add(io, "elysium_sendTransaction", |rpc, params| async move {
+            let tx = params.parse(0, "Invalid transaction")?;
+            to_json(rpc.send_transaction(tx).await?)
+        });
+        self.add(io, "elysium_getPendingTransactions", |rpc, _| async move {
+            to_json(rpc.get_pending_transactions().await)
+        });
+        self.add(io, "elysium_getTransactionByHash", |rpc, params| async move {
+            let tx_hash = params.str(0, "Invalid transaction hash")?;
+            to_json(rpc.get_transaction_by_hash(tx_hash).await)
+        });
+        self.add(io, "elysium_getTransactionReceipt", |rpc, params| async move {
+            let tx_hash = params.str(0, "Invalid transaction hash")?;
+            to_json(rpc.get_transaction_receipt(tx_hash).await)
+        });
+        self.add(io, "elysium_getAddressHistory", |rpc, params| async move {
+            let address = params.address(0)?;
+            let offset = params.parse_or(1, 0, "Invalid offset")?;
+            let limit = params.parse_or(2, DEFAULT_HISTORY_PAGE, "Invalid limit")?;
+            to_json(rpc.get_address_history(&address, offset, limit).await)
+        });
+        self.add(io, "elysium_getTransactionProof", |rpc, params| async move {
+            let tx_hash = params.str(0, "Invalid transaction hash")?;
+            to_json(rpc.get_transaction_proof(tx_hash).await)
+        });
+        self.add(io, "elysium_getAccountProof", |rpc, params| async move {
+            to_json(rpc.get_account_proof(&params.address(0)?).await)
+        });
+        self.add(io, "elysium_chainId", |rpc, _| async move {
+            to_json(rpc.get_chain_id().await)
+        });
+        self.add(io, "elysium_getCode", |rpc, params| async move {
+            to_json(rpc.get_code(&params.address(0)?).await)
+        });
+        self.add(io, "elysium_call", |rpc, params| async move {
+            let address = params.address(0)?;
+            let input = params.parse_or(1, Vec::new(), "Invalid input")?;
+            let gas_limit = params.parse_or(2, MAX_BLOCK_GAS, "Invalid gas limit")?;
+            to_json(rpc.call(&address, input, gas_limit).await?)
+        });
+        self.add(io, "elysium_getDifficulty", |rpc, _| async move {
+            to_json(rpc.get_difficulty().await)
+        });
+        self.add(io, "elysium_getHashrate", |rpc, _| async move {
+            to_json(rpc.get_hashrate().await)
+        });
+        self.add(io, "elysium_getMiningStats", |rpc, _| async move {
+            to_json(rpc.get_mining_stats().await)
+        });
+        self.add(io, "elysium_syncing", |rpc, _| async move {
+            to_json(rpc.get_sync_status().await)
+        });
+        self.add(io, "elysium_mineBlock", |rpc, _| async move {
+            to_json(rpc.mine_block().await?)
+        });
+    }
+    
+    /// Register one method whose handler gets the methods and the positional parameters
+    fn add<M, F, Fut>(&self, io: &mut MetaIoHandler<M>, name: &str, handler: F)
+    where
+        M: Metadata,
+        F: Fn(Self, RpcParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = RpcResult<Value>> + Send + 'static,
+    {
+        let rpc = self.clone();
+        io.add_method(name, move |params: Params| {
+            let call = RpcParams::new(params).map(|params| handler(rpc.clone(), params));
+            async move { call?.await }
+        });
+    }
+    
+    pub async fn get_height(&self) -> u64 {
+        self.node.height().await
+    }
+    
+    pub async fn get_block_by_number(&self, number: u64) -> Option<Block> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.blocks.get(number as usize).cloned()
+    }
+    
+    /// A main-chain block by hash
+    pub async fn get_block_by_hash(&self, block_hash: &str) -> Option<Block> {
+        let blockchain = self.node.blockchain().await;
+        let number = blockchain.block_number(block_hash)?;
+        blockchain.blocks.get(number as usize).cloned()
+    }
+    
+    pub async fn get_latest_block(&self) -> Block {
+        let blockchain = self.node.blockchain().await;
+        blockchain.latest_block().clone()
+    }
+    
+    /// Main-chain headers from `start`, for light clients
+    pub async fn get_headers(&self, start: u64, count: usize) -> Vec<BlockHeader> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_headers(start, count)
+    }
+    
+    pub async fn get_balance(&self, address: &Address) -> u64 {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_balance(address)
+    }
+    
+    /// Confirmed nonce of an account, or with `pending` the nonce after its pooled transactions
+    pub async fn get_nonce(&self, address: &Address, pending: bool) -> u64 {
+        let blockchain = self.node.blockchain().await;
+        match pending {
+            true => blockchain.get_pending_nonce(address),
+            false => blockchain.get_nonce(address),
+        }
+    }
+    
+    pub async fn send_transaction(&self, tx: Transaction) -> RpcResult<String> {
+        let hash = tx.hash();
+        self.node.add_transaction(tx)
+            .await
+            .map_err(rpc_error)?;
+        Ok(hash)
+    }
+    
+    pub async fn get_pending_transactions(&self) -> Vec<Transaction> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.pending_transactions()
+    }
+    
+    pub async fn get_transaction_by_hash(&self, tx_hash: &str) -> Option<TransactionLookup> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_transaction(tx_hash)
+    }
+    
+    pub async fn get_transaction_receipt(&self, tx_hash: &str) -> Option<TransactionReceipt> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_transaction_receipt(tx_hash)
+    }
+    
+    pub async fn get_address_history(&self, address: &Address, offset: usize, limit: usize) -> AddressHistory {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_address_history(address, offset, limit)
+    }
+    
+    pub async fn get_transaction_proof(&self, tx_hash: &str) -> Option<TransactionProof> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_transaction_proof(tx_hash)
+    }
+    
+    pub async fn get_account_proof(&self, address: &Address) -> StateProof {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_account_proof(address)
+    }
+    
+    pub async fn get_chain_id(&self) -> u64 {
+        let blockchain = self.node.blockchain().await;
+        blockchain.chain_id()
+    }
+    
+    pub async fn get_code(&self, address: &Address) -> String {
+        let blockchain = self.node.blockchain().await;
+        blockchain.get_code(address)
+    }
+    
+    pub async fn call(&self, address: &Address, input: Vec<u64>, gas_limit: u64) -> RpcResult<CallResult> {
+        let blockchain = self.node.blockchain().await;
+        blockchain.call_contract(address, input, gas_limit)
+            .map_err(rpc_error)
+    }
+    
+    pub async fn get_difficulty(&self) -> u64 {
+        let blockchain = self.node.blockchain().await;
+        blockchain.current_difficulty()
+    }
+    
+    pub async fn get_hashrate(&self) -> u64 {
+        let blockchain = self.node.blockchain().await;
+        blockchain.estimated_hashrate(blockchain.retarget.window)
+    }
+    
+    /// Hashrate and block counts of this node's own mining
+    pub async fn get_mining_stats(&self) -> MiningStats {
+        self.node.mining_stats().await
+    }
+    
+    pub async fn get_sync_status(&self) -> SyncStatus {
+        self.node.sync_status().await
+    }
+    
+    pub async fn mine_block(&self) -> RpcResult<Block> {
+        self.node.mine_block()
+            .await
+            .map_err(rpc_error)
+    }
+}
+
+/// Positional parameters of a request; a missing parameter list counts as empty
+pub struct RpcParams(Vec<Value>);
+
+impl RpcParams {
+    pub(crate) fn new(params: Params) -> RpcResult<Self> {
+        match params {
+            Params::None => Ok(Self(Vec::new())),
+            Params::Array(values) => Ok(Self(values)),
+            Params::Map(_) => Err(Error::invalid_params("Invalid params")),
+        }
+    }
+    
+    /// String parameter at `index`
+    pub fn str(&self, index: usize, message: &str) -> RpcResult<&str> {
+        self.0.get(index)
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| Error::invalid_params(message))
+    }
+    
+    /// Integer parameter at `index`
+    pub fn u64(&self, index: usize, message: &str) -> RpcResult<u64> {
+        self.0.get(index)
+            .and_then(|value| value.as_u64())
+            .ok_or_else(|| Error::invalid_params(message))
+    }
+    
+    /// Hex address parameter at `index`
+    pub fn address(&self, index: usize) -> RpcResult<Address> {
+        Address::from_hex(self.str(index, "Invalid address")?)
+            .map_err(|_| Error::invalid_params("Invalid address"))
+    }
+    
+    /// Parameter at `index` deserialized as `T`
+    pub fn parse<T: DeserializeOwned>(&self, index: usize, message: &str) -> RpcResult<T> {
+        self.0.get(index)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .ok_or_else(|| Error::invalid_params(message))
+    }
+    
+    /// Optional parameter at `index`, or `default` if it is missing
+    pub fn parse_or<T: DeserializeOwned>(&self, index: usize, default: T, message: &str) -> RpcResult<T> {
+        match self.0.get(index) {
+            Some(_) => self.parse(index, message),
+            None => Ok(default),
+        }
+    }
+}
+
+/// JSON-RPC error for a node error, with the variant's stable code and fields
+pub(crate) fn rpc_error(error: ElysiumError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(error.code()),
+        message: error.to_string(),
+        data: error.data(),
+    }
+}
+
+fn to_json<T: Serialize>(value: T) -> RpcResult<Value> {
+    serde_json::to_value(value).map_err(|_| Error::internal_error())
+}
+
+/// HTTP JSON-RPC server for a node, which runs until it is closed or dropped
+///
+/// The server has its own I/O thread, so it can be started from synchronous code or
+/// inside a Tokio runtime.
+pub struct RpcServer {
+    server: Option<Server>,
+    address: SocketAddr,
+}
+
+impl RpcServer {
+    /// Serve a node's RPC methods on `addr`; port 0 picks a free port
+    pub fn start(node: Node, addr: SocketAddr) -> Result<Self> {
+        let mut io = MetaIoHandler::<()>::default();
+        ElysiumRpcMethods::new(node).register(&mut io);
+        
+        let server = ServerBuilder::new(io)
+            .start_http(&addr)
+            .map_err(|e| ElysiumError::BlockchainError(
+                format!("Failed to start RPC server on {}: {}", addr, e)
+            ))?;
+        let address = *server.address();
+        info!("RPC server started on {}", address);
+        Ok(Self { server: Some(server), address })
+    }
+    
+    /// Address the server is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+    
+    /// Stop serving requests
+    pub fn close(self) {
+        drop(self);
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        // Shutting the server down drops its runtime, which Tokio forbids on async threads
+        if let Some(server) = self.server.take() {
+            let _ = std::thread::spawn(move || server.close()).join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{funded_node, TEST_CHAIN_ID};
+    use elysium_core::{account::KeyPair, error_code};
+    use serde_json::json;
+    
+    async fn request(io: &MetaIoHandler<()>, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+        let response = io.handle_request(&request.to_string(), ()).await.unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_methods_share_the_node_chain() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let node = funded_node(&alice);
+        let mut io = MetaIoHandler::default();
+        ElysiumRpcMethods::new(node.clone()).register(&mut io);
+        
+        assert_eq!(request(&io, "elysium_getHeight", json!([])).await["result"], 1);
+        assert_eq!(request(&io, "elysium_getBalance", json!([alice.address()])).await["result"], 1000);
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 40, 0);
+        tx.chain_id = TEST_CHAIN_ID;
+        alice.sign_transaction(&mut tx).unwrap();
+        let sent = request(&io, "elysium_sendTransaction", json!([tx])).await;
+        assert_eq!(sent["result"], tx.hash());
+        assert_eq!(request(&io, "elysium_getPendingTransactions", json!([])).await["result"][0]["nonce"], 0);
+        assert_eq!(request(&io, "elysium_getNonce", json!([alice.address()])).await["result"], 0);
+        assert_eq!(request(&io, "elysium_getNonce", json!([alice.address(), "pending"])).await["result"], 1);
+        
+        // Blocks mined by the node itself are visible over RPC
+        node.mine_block().await.unwrap();
+        assert_eq!(request(&io, "elysium_getHeight", json!([])).await["result"], 2);
+        assert_eq!(request(&io, "elysium_getBalance", json!([bob.address()])).await["result"], 40);
+        let block = request(&io, "elysium_getBlockByNumber", json!([1])).await;
+        assert_eq!(block["result"]["transactions"][0]["amount"], 40);
+        assert!(request(&io, "elysium_getTransactionProof", json!([tx.hash()])).await["result"].is_object());
+        
+        let lookup = request(&io, "elysium_getTransactionByHash", json!([tx.hash()])).await;
+        assert_eq!(lookup["result"]["location"]["block_number"], 1);
+        let block_hash = lookup["result"]["location"]["block_hash"].clone();
+        assert_eq!(request(&io, "elysium_getBlockByHash", json!([block_hash])).await["result"]["header"]["number"], 1);
+        let headers = request(&io, "elysium_getHeaders", json!([1])).await;
+        assert_eq!(headers["result"][0]["parent_hash"], node.blockchain().await.blocks[0].hash());
+        assert_eq!(request(&io, "elysium_getHeaders", json!([0, 10])).await["result"].as_array().unwrap().len(), 2);
+        let receipt = request(&io, "elysium_getTransactionReceipt", json!([tx.hash()])).await;
+        assert_eq!(receipt["result"]["confirmations"], 1);
+        let history = request(&io, "elysium_getAddressHistory", json!([alice.address(), 0, 10])).await;
+        assert_eq!(history["result"]["total"], 1);
+        assert_eq!(history["result"]["transactions"][0]["transaction"]["amount"], 40);
+        assert!(request(&io, "elysium_getTransactionReceipt", json!(["unknown"])).await["result"].is_null());
+        let stats = request(&io, "elysium_getMiningStats", json!([])).await;
+        assert_eq!(stats["result"]["blocks_mined"], 1);
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_requests() {
+        let node = Node::new(1);
+        let mut io = MetaIoHandler::default();
+        ElysiumRpcMethods::new(node).register(&mut io);
+        
+        assert_eq!(request(&io, "elysium_unknown", json!([])).await["error"]["code"], -32601);
+        for (method, params) in [
+            ("elysium_getBalance", json!([])),
+            ("elysium_getBalance", json!(["not hex"])),
+            ("elysium_getNonce", json!([KeyPair::generate().address(), "earliest"])),
+            ("elysium_getBlockByNumber", json!(["one"])),
+            ("elysium_getAddressHistory", json!([KeyPair::generate().address(), -1])),
+            ("elysium_sendTransaction", json!([{}])),
+            ("elysium_call", json!([KeyPair::generate().address(), "input"])),
+            ("elysium_getHeight", json!({ "named": true })),
+        ] {
+            let response = request(&io, method, params).await;
+            assert_eq!(response["error"]["code"], -32602, "{} should reject its params", method);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_node_error_codes() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate().address();
+        let node = funded_node(&alice);
+        let mut io = MetaIoHandler::default();
+        ElysiumRpcMethods::new(node).register(&mut io);
+        let signed = |amount, nonce| {
+            let mut tx = Transaction::new(alice.address(), bob.clone(), amount, nonce);
+            tx.chain_id = TEST_CHAIN_ID;
+            alice.sign_transaction(&mut tx).unwrap();
+            tx
+        };
+        
+        let response = request(&io, "elysium_sendTransaction", json!([signed(10_000, 0)])).await;
+        assert_eq!(response["error"]["code"], error_code::INSUFFICIENT_BALANCE);
+        
+        let mut forged = signed(10, 0);
+        forged.amount = 20;
+        let response = request(&io, "elysium_sendTransaction", json!([forged])).await;
+        assert_eq!(response["error"]["code"], error_code::INVALID_SIGNATURE);
+        
+        request(&io, "elysium_sendTransaction", json!([signed(10, 0)])).await;
+        request(&io, "elysium_mineBlock", json!([])).await;
+        let response = request(&io, "elysium_sendTransaction", json!([signed(11, 0)])).await;
+        assert_eq!(response["error"]["code"], error_code::INVALID_NONCE);
+        assert_eq!(response["error"]["data"], json!({ "expected": 1, "got": 0 }));
+        
+        let response = request(&io, "elysium_call", json!([bob])).await;
+        assert_eq!(response["error"]["code"], error_code::CONTRACT_ERROR);
+        assert!(response["error"]["data"]["reason"].as_str().unwrap().contains("not a contract"));
+    }
+    
+    #[tokio::test]
+    async fn test_http_server() {
+        let server = RpcServer::start(Node::new(1), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let response: Value = reqwest::Client::new()
+            .post(format!("http://{}", server.address()))
+            .json(&json!({ "jsonrpc": "2.0", "method": "elysium_getHeight", "params": [], "id": 1 }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["result"], 1);
+        server.close();
+    }
+}
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Maximum number of blocks sent in one `Blocks` response
+pub const MAX_BLOCKS_PER_BATCH: usize = 128;
+
+/// Time to wait for a `Blocks` response before asking another peer
+pub const SYNC_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Chain status exchanged by peers when a connection opens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    pub height: u64,
+    pub tip_hash: String,
+}
+
+/// Progress of the initial block download, as reported over RPC
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// Whether the node is currently downloading blocks
+    pub syncing: bool,
+    /// Height of the local chain
+    pub current_height: u64,
+    /// Highest chain height announced by any peer
+    pub target_height: u64,
+    /// Peer blocks are currently requested from
+    pub peer: Option<String>,
+}
+
+/// A request for blocks that should be sent to a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRequest {
+    pub peer: SocketAddr,
+    pub from: u64,
+}
+
+#[derive(Debug)]
+struct InFlight {
+    peer: SocketAddr,
+    from: u64,
+    requested_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    peer_heights: HashMap<SocketAddr, u64>,
+    failed_peers: HashSet<SocketAddr>,
+    in_flight: Option<InFlight>,
+}
+
+impl SyncState {
+    /// Pick the best peer that is ahead of us and has not failed, and request the next batch
+    fn request_next(&mut self, our_height: u64, now: Instant) -> Option<BlockRequest> {
+        let peer = self.peer_heights.iter()
+            .filter(|(peer, height)| **height > our_height && !self.failed_peers.contains(peer))
+            .max_by_key(|(_, height)| **height)
+            .map(|(peer, _)| *peer);
+        
+        match peer {
+            Some(peer) => {
+                self.in_flight = Some(InFlight { peer, from: our_height, requested_at: now });
+                Some(BlockRequest { peer, from: our_height })
+            }
+            None => {
+                self.in_flight = None;
+                None
+            }
+        }
+    }
+}
+
+/// Decides which peer to download missing blocks from
+///
+/// The manager only tracks state; the network sends the `RequestBlocks` messages it
+/// returns and reports back what happened. One request is in flight at a time. A peer
+/// that serves invalid blocks, sends nothing, or times out is skipped until it
+/// announces a new status.
+#[derive(Debug, Default)]
+pub struct SyncManager {
+    state: Mutex<SyncState>,
+}
+
+impl SyncManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// A peer announced its chain height
+    pub fn on_peer_status(&self, peer: SocketAddr, height: u64, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        state.peer_heights.insert(peer, height);
+        state.failed_peers.remove(&peer);
+        
+        if state.in_flight.is_some() || height <= our_height {
+            return None;
+        }
+        state.request_next(our_height, Instant::now())
+    }
+    
+    /// A batch from `peer` was applied; continue while any peer is still ahead
+    pub fn on_blocks_applied(&self, peer: SocketAddr, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        if state.in_flight.as_ref().map(|f| f.peer) != Some(peer) {
+            return None;
+        }
+        let peer_height = state.peer_heights.entry(peer).or_insert(our_height);
+        *peer_height = (*peer_height).max(our_height);
+        state.request_next(our_height, Instant::now())
+    }
+    
+    /// `peer` served an empty or invalid batch; retry from another peer
+    pub fn on_peer_failed(&self, peer: SocketAddr, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        state.failed_peers.insert(peer);
+        if state.in_flight.as_ref().map(|f| f.peer) != Some(peer) {
+            return None;
+        }
+        state.request_next(our_height, Instant::now())
+    }
+    
+    /// The batch from `peer` did not connect to any known block
+    ///
+    /// The peer's chain forks off below the requested height, so step back a batch
+    /// and ask the same peer again until the fork point is found.
+    pub fn on_unknown_parent(&self, peer: SocketAddr, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        let from = match state.in_flight.as_ref().filter(|f| f.peer == peer) {
+            Some(in_flight) => in_flight.from,
+            None => return None,
+        };
+        if from == 0 {
+            // Not even the genesis block matches
+            state.failed_peers.insert(peer);
+            return state.request_next(our_height, Instant::now());
+        }
+        
+        let from = from.saturating_sub(MAX_BLOCKS_PER_BATCH as u64);
+        state.in_flight = Some(InFlight { peer, from, requested_at: Instant::now() });
+        Some(BlockRequest { peer, from })
+    }
+    
+    /// `peer` disconnected
+    pub fn on_peer_disconnected(&self, peer: SocketAddr, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        state.peer_heights.remove(&peer);
+        state.failed_peers.remove(&peer);
+        if state.in_flight.as_ref().map(|f| f.peer) != Some(peer) {
+            return None;
+        }
+        state.request_next(our_height, Instant::now())
+    }
+    
+    /// Check the in-flight request for a timeout
+    pub fn on_tick(&self, now: Instant, our_height: u64) -> Option<BlockRequest> {
+        let mut state = self.state.lock().unwrap();
+        let timed_out = match &state.in_flight {
+            Some(in_flight) => now.duration_since(in_flight.requested_at) >= SYNC_REQUEST_TIMEOUT,
+            None => false,
+        };
+        if !timed_out {
+            return None;
+        }
+        if let Some(in_flight) = state.in_flight.take() {
+            state.failed_peers.insert(in_flight.peer);
+        }
+        state.request_next(our_height, now)
+    }
+    
+    /// Whether blocks are currently being downloaded
+    pub fn is_syncing(&self) -> bool {
+        self.state.lock().unwrap().in_flight.is_some()
+    }
+    
+    /// Current download progress
+    pub fn status(&self, our_height: u64) -> SyncStatus {
+        let state = self.state.lock().unwrap();
+        let best_height = state.peer_heights.values().copied().max().unwrap_or(0);
+        SyncStatus {
+            syncing: state.in_flight.is_some(),
+            current_height: our_height,
+            target_height: best_height.max(our_height),
+            peer: state.in_flight.as_ref().map(|f| f.peer.to_string()),
+        }
+    }
+    
+    /// Start height of the in-flight request to `peer`, if any
+    pub fn requested_from(&self, peer: SocketAddr) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.in_flight.as_ref()
+            .filter(|f| f.peer == peer)
+            .map(|f| f.from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+    
+    #[test]
+    fn test_requests_from_peer_ahead() {
+        let sync = SyncManager::new();
+        assert_eq!(sync.on_peer_status(addr(1), 1, 1), None);
+        
+        let request = sync.on_peer_status(addr(2), 300, 1).unwrap();
+        assert_eq!(request, BlockRequest { peer: addr(2), from: 1 });
+        assert!(sync.is_syncing());
+        
+        // Only one request is in flight at a time
+        assert_eq!(sync.on_peer_status(addr(3), 400, 1), None);
+        
+        let request = sync.on_blocks_applied(addr(2), 129).unwrap();
+        assert_eq!(request, BlockRequest { peer: addr(3), from: 129 });
+        
+        assert_eq!(sync.on_blocks_applied(addr(3), 400), None);
+        assert!(!sync.is_syncing());
+        assert_eq!(sync.status(400).target_height, 400);
+    }
+    
+    #[test]
+    fn test_retries_from_another_peer_on_failure() {
+        let sync = SyncManager::new();
+        sync.on_peer_status(addr(1), 50, 1);
+        sync.on_peer_status(addr(2), 40, 1);
+        
+        let request = sync.on_peer_failed(addr(1), 1).unwrap();
+        assert_eq!(request, BlockRequest { peer: addr(2), from: 1 });
+        
+        assert_eq!(sync.on_peer_failed(addr(2), 1), None);
+        assert!(!sync.is_syncing());
+        
+        // A fresh status makes a failed peer eligible again
+        let request = sync.on_peer_status(addr(1), 60, 1).unwrap();
+        assert_eq!(request.peer, addr(1));
+    }
+    
+    #[test]
+    fn test_retries_after_timeout() {
+        let sync = SyncManager::new();
+        sync.on_peer_status(addr(1), 50, 10);
+        sync.on_peer_status(addr(2), 50, 10);
+        let first = sync.status(10).peer.unwrap();
+        
+        assert_eq!(sync.on_tick(Instant::now(), 10), None);
+        
+        let later = Instant::now() + SYNC_REQUEST_TIMEOUT;
+        let request = sync.on_tick(later, 10).unwrap();
+        assert_ne!(request.peer.to_string(), first);
+        assert_eq!(request.from, 10);
+    }
+    
+    #[test]
+    fn test_disconnect_moves_to_next_peer() {
+        let sync = SyncManager::new();
+        sync.on_peer_status(addr(1), 50, 1);
+        sync.on_peer_status(addr(2), 20, 1);
+        
+        let request = sync.on_peer_disconnected(addr(1), 1).unwrap();
+        assert_eq!(request.peer, addr(2));
+        assert_eq!(sync.requested_from(addr(2)), Some(1));
+    }
+    
+    #[test]
+    fn test_unknown_parent_steps_back() {
+        let sync = SyncManager::new();
+        sync.on_peer_status(addr(1), 500, 300);
+        
+        let request = sync.on_unknown_parent(addr(1), 300).unwrap();
+        assert_eq!(request, BlockRequest { peer: addr(1), from: 300 - MAX_BLOCKS_PER_BATCH as u64 });
+        let request = sync.on_unknown_parent(addr(1), 300).unwrap();
+        assert_eq!(request.from, 300 - 2 * MAX_BLOCKS_PER_BATCH as u64);
+        let request = sync.on_unknown_parent(addr(1), 300).unwrap();
+        assert_eq!(request.from, 0);
+        
+        // A peer with a different genesis block is given up on
+        assert_eq!(sync.on_unknown_parent(addr(1), 300), None);
+        assert!(!sync.is_syncing());
+    }
+}
@@ -0,0 +1,28 @@
+//! Fixtures shared by the tests of the node and of the crates built on it
+//!
+//! Compiled for this crate's tests and, with the `test-util` feature, for other crates' tests.
+
+use crate::Node;
+use elysium_core::{account::KeyPair, GenesisSpec};
+
+/// Chain id of the [`funded_spec`] network
+pub const TEST_CHAIN_ID: u64 = 7;
+
+/// Proof-of-work genesis spec at difficulty 1 where `keypair` holds 1000 coins
+pub fn funded_spec(keypair: &KeyPair) -> GenesisSpec {
+    let mut spec = GenesisSpec {
+        chain_id: TEST_CHAIN_ID,
+        timestamp: 1_700_000_000,
+        difficulty: 1,
+        alloc: Default::default(),
+        consensus: Default::default(),
+        retarget: Default::default(),
+    };
+    spec.alloc.insert(keypair.address().as_hex().to_string(), 1000);
+    spec
+}
+
+/// Node on the [`funded_spec`] network of `keypair`
+pub fn funded_node(keypair: &KeyPair) -> Node {
+    Node::from_genesis(&funded_spec(keypair)).unwrap()
+}
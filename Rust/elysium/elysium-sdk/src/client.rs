@@ -1,9 +1,27 @@
-use elysium_core::{Block, Transaction, Address, account::KeyPair};
+use elysium_core::{
+    Block, BlockHeader, Transaction, TransactionProof, StateProof, Address, CallResult, MultisigPolicy, account::KeyPair,
+    AddressHistory, TransactionLookup, TransactionReceipt,
+};
 use serde_json::{json, Value};
-use crate::{Result, SdkError};
-use std::sync::Arc;
+use crate::{MultisigTransferBuilder, Result, SdkError};
+
+/// One method call of a batch request
+#[derive(Debug, Clone)]
+pub struct RpcCall {
+    pub method: String,
+    pub params: Value,
+}
+
+impl RpcCall {
+    pub fn new(method: impl Into<String>, params: Value) -> Self {
+        Self { method: method.into(), params }
+    }
+}
 
 /// Elysium SDK client for interacting with the blockchain
+///
+/// Clones share the underlying HTTP connection pool.
+#[derive(Clone)]
 pub struct ElysiumClient {
     client: reqwest::Client,
     rpc_url: String,
@@ -34,19 +52,56 @@ impl ElysiumClient {
             .await?;
         
         let json: Value = response.json().await?;
-        
-        if let Some(error) = json.get("error") {
-            return Err(SdkError::JsonRpcError(
-                error.get("message")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Unknown error")
-                    .to_string()
-            ));
+        parse_response(json)
+    }
+    
+    /// Send several calls in one batch request, returning their results in order
+    ///
+    /// The request fails as a whole only if it cannot be delivered; each call can
+    /// still fail on its own.
+    pub async fn batch(&self, calls: &[RpcCall]) -> Result<Vec<Result<Value>>> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
         }
+        let requests: Vec<Value> = calls.iter()
+            .enumerate()
+            .map(|(id, call)| json!({
+                "jsonrpc": "2.0",
+                "method": call.method,
+                "params": call.params,
+                "id": id
+            }))
+            .collect();
+        
+        let response = self.client
+            .post(&self.rpc_url)
+            .json(&requests)
+            .send()
+            .await?;
+        
+        let json: Value = response.json().await?;
+        // A malformed batch gets a single error response instead of a list
+        let responses = match json {
+            Value::Array(responses) => responses,
+            other => return Err(parse_response(other).err().unwrap_or_else(|| {
+                SdkError::JsonRpcError("Invalid batch response".to_string())
+            })),
+        };
         
-        json.get("result")
-            .cloned()
-            .ok_or_else(|| SdkError::JsonRpcError("No result in response".to_string()))
+        let mut results: Vec<Option<Result<Value>>> = calls.iter().map(|_| None).collect();
+        for response in responses {
+            let slot = response.get("id")
+                .and_then(Value::as_u64)
+                .and_then(|id| results.get_mut(id as usize));
+            if let Some(slot) = slot {
+                *slot = Some(parse_response(response));
+            }
+        }
+        Ok(results.into_iter()
+            .map(|result| result.unwrap_or_else(|| {
+                Err(SdkError::JsonRpcError("No response to batched call".to_string()))
+            }))
+            .collect())
     }
     
     /// Get the current blockchain height
@@ -68,6 +123,29 @@ impl ElysiumClient {
             .map_err(|e| SdkError::SerializationError(e.to_string()))
     }
     
+    /// Get a main-chain block by hash
+    pub async fn get_block_by_hash(&self, block_hash: &str) -> Result<Option<Block>> {
+        let result = self.call("elysium_getBlockByHash", json!([block_hash])).await?;
+        
+        if result.is_null() {
+            return Ok(None);
+        }
+        
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get the headers of up to `count` main-chain blocks starting at `start`
+    ///
+    /// Nodes return at most [`MAX_HEADERS_PER_REQUEST`](elysium_core::MAX_HEADERS_PER_REQUEST)
+    /// headers per call. Check them with a [`HeaderChain`](crate::HeaderChain) before
+    /// trusting them.
+    pub async fn get_headers(&self, start: u64, count: usize) -> Result<Vec<BlockHeader>> {
+        let result = self.call("elysium_getHeaders", json!([start, count])).await?;
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
     /// Get the latest block
     pub async fn get_latest_block(&self) -> Result<Block> {
         let result = self.call("elysium_getLatestBlock", json!([])).await?;
@@ -89,6 +167,13 @@ impl ElysiumClient {
             .ok_or_else(|| SdkError::JsonRpcError("Invalid nonce response".to_string()))
     }
     
+    /// Get the nonce the account's next transaction should use, counting its pending transactions
+    pub async fn get_pending_nonce(&self, address: &Address) -> Result<u64> {
+        let result = self.call("elysium_getNonce", json!([address.as_hex(), "pending"])).await?;
+        result.as_u64()
+            .ok_or_else(|| SdkError::JsonRpcError("Invalid nonce response".to_string()))
+    }
+    
     /// Send a transaction
     pub async fn send_transaction(&self, tx: &Transaction) -> Result<String> {
         let tx_json = serde_json::to_value(tx)
@@ -99,6 +184,25 @@ impl ElysiumClient {
             .ok_or_else(|| SdkError::JsonRpcError("Invalid transaction hash response".to_string()))
     }
     
+    /// Send several transactions in one batch request, returning each one's hash or error
+    pub async fn send_transactions(&self, txs: &[Transaction]) -> Result<Vec<Result<String>>> {
+        let mut calls = Vec::with_capacity(txs.len());
+        for tx in txs {
+            let tx_json = serde_json::to_value(tx)
+                .map_err(|e| SdkError::SerializationError(e.to_string()))?;
+            calls.push(RpcCall::new("elysium_sendTransaction", json!([tx_json])));
+        }
+        
+        let results = self.batch(&calls).await?;
+        Ok(results.into_iter()
+            .map(|result| {
+                result?.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| SdkError::JsonRpcError("Invalid transaction hash response".to_string()))
+            })
+            .collect())
+    }
+    
     /// Get pending transactions
     pub async fn get_pending_transactions(&self) -> Result<Vec<Transaction>> {
         let result = self.call("elysium_getPendingTransactions", json!([])).await?;
@@ -106,6 +210,100 @@ impl ElysiumClient {
             .map_err(|e| SdkError::SerializationError(e.to_string()))
     }
     
+    /// Find a transaction in the main chain or the pending pool, with the block it is in
+    pub async fn get_transaction_by_hash(&self, tx_hash: &str) -> Result<Option<TransactionLookup>> {
+        let result = self.call("elysium_getTransactionByHash", json!([tx_hash])).await?;
+        
+        if result.is_null() {
+            return Ok(None);
+        }
+        
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get the receipt of a transaction, or `None` if it is not in a block yet
+    pub async fn get_transaction_receipt(&self, tx_hash: &str) -> Result<Option<TransactionReceipt>> {
+        let result = self.call("elysium_getTransactionReceipt", json!([tx_hash])).await?;
+        
+        if result.is_null() {
+            return Ok(None);
+        }
+        
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get a page of the transactions sending from or to an address, newest first
+    ///
+    /// Skips `offset` transactions and returns at most `limit`; the node caps the page size.
+    pub async fn get_address_history(&self, address: &Address, offset: usize, limit: usize) -> Result<AddressHistory> {
+        let result = self.call("elysium_getAddressHistory", json!([address.as_hex(), offset, limit])).await?;
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get an inclusion proof for a transaction, or `None` if it is not in a block yet
+    ///
+    /// Check the result with [`verify_transaction_proof`](crate::verify_transaction_proof)
+    /// against a block hash obtained from a trusted source.
+    pub async fn get_transaction_proof(&self, tx_hash: &str) -> Result<Option<TransactionProof>> {
+        let result = self.call("elysium_getTransactionProof", json!([tx_hash])).await?;
+        
+        if result.is_null() {
+            return Ok(None);
+        }
+        
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get a proof of an account's state as of the latest block
+    ///
+    /// Check the result with [`verify_account_proof`](crate::verify_account_proof)
+    /// against a block hash obtained from a trusted source.
+    pub async fn get_account_proof(&self, address: &Address) -> Result<StateProof> {
+        let result = self.call("elysium_getAccountProof", json!([address.as_hex()])).await?;
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get the chain id transactions must be signed for
+    pub async fn get_chain_id(&self) -> Result<u64> {
+        let result = self.call("elysium_chainId", json!([])).await?;
+        result.as_u64()
+            .ok_or_else(|| SdkError::JsonRpcError("Invalid chain id response".to_string()))
+    }
+    
+    /// Get the hex-encoded code of a contract, empty for other accounts
+    pub async fn get_code(&self, address: &Address) -> Result<String> {
+        let result = self.call("elysium_getCode", json!([address.as_hex()])).await?;
+        result.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| SdkError::JsonRpcError("Invalid code response".to_string()))
+    }
+    
+    /// Run a contract against the node's current state without sending a transaction
+    pub async fn query_contract(&self, contract: &Address, input: &[u64], gas_limit: u64) -> Result<CallResult> {
+        let result = self.call("elysium_call", json!([contract.as_hex(), input, gas_limit])).await?;
+        serde_json::from_value(result)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))
+    }
+    
+    /// Get the difficulty the next block has to meet
+    pub async fn get_difficulty(&self) -> Result<u64> {
+        let result = self.call("elysium_getDifficulty", json!([])).await?;
+        result.as_u64()
+            .ok_or_else(|| SdkError::JsonRpcError("Invalid difficulty response".to_string()))
+    }
+    
+    /// Get the estimated network hash rate in hashes per second
+    pub async fn get_hashrate(&self) -> Result<u64> {
+        let result = self.call("elysium_getHashrate", json!([])).await?;
+        result.as_u64()
+            .ok_or_else(|| SdkError::JsonRpcError("Invalid hashrate response".to_string()))
+    }
+    
     /// Mine a block
     pub async fn mine_block(&self) -> Result<Block> {
         let result = self.call("elysium_mineBlock", json!([])).await?;
@@ -115,19 +313,78 @@ impl ElysiumClient {
     
     /// Create and send a transfer transaction
     pub async fn transfer(&self, from: &KeyPair, to: &Address, amount: u64) -> Result<String> {
-        // Get current nonce
-        let nonce = self.get_nonce(&from.address()).await?;
-        
-        // Create transaction
-        let mut tx = Transaction::new(
-            from.address(),
-            to.clone(),
-            amount,
-            nonce,
-        );
+        self.transfer_with_fee(from, to, amount, 0).await
+    }
+    
+    /// Create and send a transfer transaction paying `fee` to the miner
+    pub async fn transfer_with_fee(&self, from: &KeyPair, to: &Address, amount: u64, fee: u64) -> Result<String> {
+        let tx = Transaction::new(from.address(), to.clone(), amount, 0).with_fee(fee);
+        self.sign_and_send(from, tx).await
+    }
+    
+    /// Create and send a transfer the receiver can only spend from the block at `unlock_height` on
+    pub async fn transfer_locked(&self, from: &KeyPair, to: &Address, amount: u64, unlock_height: u64) -> Result<String> {
+        let tx = Transaction::time_locked(from.address(), to.clone(), amount, 0, unlock_height);
+        self.sign_and_send(from, tx).await
+    }
+    
+    /// Create the multisig account controlled by `policy`, funding it with `amount`
+    ///
+    /// The account's address is `policy.address()`.
+    pub async fn create_multisig(&self, from: &KeyPair, policy: MultisigPolicy, amount: u64) -> Result<String> {
+        let tx = Transaction::create_multisig(from.address(), policy, amount, 0);
+        self.sign_and_send(from, tx).await
+    }
+    
+    /// Prepare a transfer out of the multisig account controlled by `policy`
+    ///
+    /// The builder has the account's nonce and the chain id filled in. Collect the
+    /// co-signatures on it, then send the result of
+    /// [`build`](MultisigTransferBuilder::build) with [`send_transaction`](Self::send_transaction).
+    pub async fn multisig_transfer(&self, policy: MultisigPolicy, to: &Address, amount: u64, fee: u64) -> Result<MultisigTransferBuilder> {
+        let nonce = self.get_nonce(&policy.address()).await?;
+        let chain_id = self.get_chain_id().await?;
+        Ok(MultisigTransferBuilder::new(policy, to.clone(), amount, nonce)
+            .with_fee(fee)
+            .with_chain_id(chain_id))
+    }
+    
+    /// Deploy `code` as a contract, returning its address and the transaction hash
+    ///
+    /// The sender pays for up to `gas_limit` gas at `gas_price` per unit.
+    pub async fn deploy_contract(&self, from: &KeyPair, code: &[u8], gas_limit: u64, gas_price: u64) -> Result<(Address, String)> {
+        // The contract address depends on the nonce the deployment is signed with
+        let nonce = self.get_pending_nonce(&from.address()).await?;
+        let tx = Transaction::deploy(from.address(), code, nonce, gas_limit, gas_price);
+        let address = tx.to.clone();
+        let hash = self.sign_and_send(from, tx).await?;
+        Ok((address, hash))
+    }
+    
+    /// Send a transaction calling `contract` with `input` and sending it `amount`
+    ///
+    /// The amount only reaches the contract if the call succeeds; the gas used is paid either way.
+    pub async fn call_contract(
+        &self,
+        from: &KeyPair,
+        contract: &Address,
+        amount: u64,
+        input: Vec<u64>,
+        gas_limit: u64,
+        gas_price: u64,
+    ) -> Result<String> {
+        let tx = Transaction::call(from.address(), contract.clone(), amount, input, 0, gas_limit, gas_price);
+        self.sign_and_send(from, tx).await
+    }
+    
+    /// Fill in the sender's nonce and the chain id, then sign and send a transaction
+    async fn sign_and_send(&self, from: &KeyPair, mut tx: Transaction) -> Result<String> {
+        // Get the next nonce and the network to sign for
+        tx.nonce = self.get_pending_nonce(&from.address()).await?;
+        tx.chain_id = self.get_chain_id().await?;
         
         // Sign transaction
-        tx.sign(from.signing_key())
+        from.sign_transaction(&mut tx)
             .map_err(|e| SdkError::SerializationError(e.to_string()))?;
         
         // Send transaction
@@ -135,6 +392,17 @@ impl ElysiumClient {
     }
 }
 
+/// Result of a JSON-RPC response, or its error
+fn parse_response(json: Value) -> Result<Value> {
+    if let Some(error) = json.get("error") {
+        return Err(SdkError::from_rpc(error));
+    }
+    
+    json.get("result")
+        .cloned()
+        .ok_or_else(|| SdkError::JsonRpcError("No result in response".to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,8 +410,43 @@ mod tests {
     #[tokio::test]
     #[ignore] // Requires running node
     async fn test_client_creation() {
-        let client = ElysiumClient::new("http://127.0.0.1:8545");
+        let _client = ElysiumClient::new("http://127.0.0.1:8545");
         // This test would require a running node
     }
+    
+    #[tokio::test]
+    async fn test_node_errors_are_typed() {
+        use elysium_core::account::KeyPair;
+        use elysium_node::test_util::{funded_node, TEST_CHAIN_ID};
+        use elysium_node::RpcServer;
+        
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate().address();
+        let server = RpcServer::start(funded_node(&alice), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = ElysiumClient::new(format!("http://{}", server.address()));
+        let signed = |amount, nonce| {
+            let mut tx = Transaction::new(alice.address(), bob.clone(), amount, nonce);
+            tx.chain_id = TEST_CHAIN_ID;
+            alice.sign_transaction(&mut tx).unwrap();
+            tx
+        };
+        
+        let result = client.send_transaction(&signed(10_000, 0)).await;
+        assert!(matches!(result, Err(SdkError::InsufficientBalance)));
+        let mut forged = signed(10, 0);
+        forged.amount = 20;
+        assert!(matches!(client.send_transaction(&forged).await, Err(SdkError::InvalidSignature)));
+        
+        client.send_transaction(&signed(10, 0)).await.unwrap();
+        client.mine_block().await.unwrap();
+        let result = client.send_transaction(&signed(11, 0)).await;
+        assert!(matches!(result, Err(SdkError::InvalidNonce { expected: 1, got: 0 })));
+        
+        let result = client.query_contract(&bob, &[], 1_000).await;
+        assert!(matches!(result, Err(SdkError::ContractError(reason)) if reason.contains("not a contract")));
+        // Errors outside the node's codes keep their message
+        let result = client.batch(&[RpcCall::new("elysium_unknown", json!([]))]).await.unwrap();
+        assert!(matches!(&result[0], Err(SdkError::JsonRpcError(message)) if message == "Method not found"));
+    }
 }
 
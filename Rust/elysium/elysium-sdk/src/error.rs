@@ -1,3 +1,5 @@
+use elysium_core::ElysiumError;
+use serde_json::Value;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +7,9 @@ pub enum SdkError {
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
     
+    #[error("WebSocket error: {0}")]
+    WebSocketError(String),
+    
     #[error("JSON-RPC error: {0}")]
     JsonRpcError(String),
     
@@ -13,6 +18,71 @@ pub enum SdkError {
     
     #[error("Invalid address: {0}")]
     InvalidAddress(String),
+    
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+    
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    
+    #[error("Key error: {0}")]
+    KeyError(String),
+    
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    
+    #[error("Insufficient balance")]
+    InsufficientBalance,
+    
+    #[error("Invalid nonce: expected {expected}, got {got}")]
+    InvalidNonce { expected: u64, got: u64 },
+    
+    #[error("Invalid signature")]
+    InvalidSignature,
+    
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
+    
+    #[error("Contract error: {0}")]
+    ContractError(String),
+    
+    #[error("Blockchain error: {0}")]
+    BlockchainError(String),
+    
+    #[error("Storage error: {0}")]
+    StorageError(String),
+}
+
+impl SdkError {
+    /// Error for the `error` object of a JSON-RPC response, typed by its code when the
+    /// node sent one of the [`elysium_core::error_code`] codes
+    pub fn from_rpc(error: &Value) -> Self {
+        let node_error = error["code"].as_i64()
+            .and_then(|code| ElysiumError::from_code(code, error.get("data")));
+        match node_error {
+            Some(node_error) => node_error.into(),
+            None => SdkError::JsonRpcError(
+                error["message"].as_str().unwrap_or("Unknown error").to_string()
+            ),
+        }
+    }
+}
+
+impl From<ElysiumError> for SdkError {
+    fn from(error: ElysiumError) -> Self {
+        match error {
+            ElysiumError::InvalidTransaction(reason) => SdkError::InvalidTransaction(reason),
+            ElysiumError::InvalidBlock(reason) => SdkError::InvalidBlock(reason),
+            ElysiumError::InsufficientBalance => SdkError::InsufficientBalance,
+            ElysiumError::InvalidSignature => SdkError::InvalidSignature,
+            ElysiumError::BlockchainError(reason) => SdkError::BlockchainError(reason),
+            ElysiumError::SerializationError(reason) => SdkError::SerializationError(reason),
+            ElysiumError::StorageError(reason) => SdkError::StorageError(reason),
+            ElysiumError::ContractError(reason) => SdkError::ContractError(reason),
+            ElysiumError::KeyError(reason) => SdkError::KeyError(reason),
+            ElysiumError::InvalidNonce { expected, got } => SdkError::InvalidNonce { expected, got },
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, SdkError>;
@@ -0,0 +1,49 @@
+use std::path::Path;
+use elysium_core::{account::KeyPair, Keystore, Mnemonic};
+use crate::{Result, SdkError};
+
+/// Encrypt a key pair with `password` and write it to a keystore file
+pub fn save_keystore(keypair: &KeyPair, path: impl AsRef<Path>, password: &str) -> Result<()> {
+    Keystore::encrypt(keypair, password)
+        .and_then(|keystore| keystore.save(path))
+        .map_err(|e| SdkError::KeyError(e.to_string()))
+}
+
+/// Read a keystore file and decrypt its key pair with `password`
+pub fn load_keystore(path: impl AsRef<Path>, password: &str) -> Result<KeyPair> {
+    Keystore::load(path)
+        .and_then(|keystore| keystore.decrypt(password))
+        .map_err(|e| SdkError::KeyError(e.to_string()))
+}
+
+/// Generate a new recovery phrase with `word_count` words
+pub fn generate_mnemonic(word_count: usize) -> Result<Mnemonic> {
+    Mnemonic::generate(word_count)
+        .map_err(|e| SdkError::KeyError(e.to_string()))
+}
+
+/// Restore the account at `index` from a recovery phrase and optional passphrase
+pub fn keypair_from_mnemonic(phrase: &str, passphrase: &str, index: u32) -> Result<KeyPair> {
+    Mnemonic::from_phrase(phrase)
+        .and_then(|mnemonic| mnemonic.derive_keypair(passphrase, index))
+        .map_err(|e| SdkError::KeyError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_restore_from_mnemonic_and_keystore() {
+        let mnemonic = generate_mnemonic(12).unwrap();
+        let keypair = keypair_from_mnemonic(&mnemonic.phrase(), "", 3).unwrap();
+        assert_eq!(keypair.address(), mnemonic.derive_keypair("", 3).unwrap().address());
+        assert!(keypair_from_mnemonic("not a mnemonic", "", 0).is_err());
+        
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key.json");
+        Keystore::encrypt_with_cost(&keypair, "pw", 4).unwrap().save(&path).unwrap();
+        assert_eq!(load_keystore(&path, "pw").unwrap().address(), keypair.address());
+        assert!(matches!(load_keystore(&path, "nope"), Err(SdkError::KeyError(_))));
+    }
+}
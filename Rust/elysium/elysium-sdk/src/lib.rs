@@ -1,6 +1,21 @@
 pub mod client;
 pub mod error;
+pub mod keys;
+pub mod light;
+pub mod manager;
+pub mod multisig;
+pub mod proof;
+pub mod subscription;
 
 pub use client::*;
 pub use error::*;
+pub use keys::*;
+pub use light::*;
+pub use manager::*;
+pub use multisig::*;
+pub use proof::*;
+pub use subscription::*;
+
+/// Bytecode builder for contracts deployed with [`ElysiumClient::deploy_contract`]
+pub use elysium_vm::{Opcode, Program};
 
@@ -0,0 +1,418 @@
+use std::sync::Arc;
+use elysium_core::{
+    Account, Address, BlockHeader, Consensus, GenesisSpec, RetargetConfig, TransactionProof,
+    MAX_FUTURE_BLOCK_TIME, MAX_HEADERS_PER_REQUEST, MAX_REORG_DEPTH,
+};
+use tokio::sync::Mutex;
+use crate::{verify_account_proof, verify_transaction_proof, ElysiumClient, Result, SdkError};
+
+/// Main chain of block headers, checked without the blocks' transactions
+///
+/// Starting from a trusted genesis header, every header must link to its parent, carry a
+/// valid seal and the target the retarget rules give it, and have a plausible timestamp.
+/// Like a full node, the chain follows the branch with the most work.
+pub struct HeaderChain {
+    headers: Vec<BlockHeader>,
+    hashes: Vec<String>,
+    /// Work of the chain up to and including each header
+    total_work: Vec<u128>,
+    consensus: Arc<dyn Consensus>,
+    retarget: RetargetConfig,
+}
+
+impl HeaderChain {
+    /// Create a header chain from a trusted genesis header
+    pub fn new(genesis: BlockHeader, consensus: Arc<dyn Consensus>) -> Self {
+        Self {
+            hashes: vec![genesis.hash()],
+            headers: vec![genesis],
+            total_work: vec![0],
+            consensus,
+            retarget: RetargetConfig::default(),
+        }
+    }
+    
+    /// Create a header chain for the network of a genesis spec, with its retarget rules
+    pub fn from_genesis(spec: &GenesisSpec) -> Result<Self> {
+        spec.validate()?;
+        Ok(Self::new(spec.genesis_block().header, spec.consensus.engine()?).with_retarget(spec.retarget))
+    }
+    
+    /// Check targets against the network's retarget rules instead of the defaults
+    ///
+    /// Only needed for chains created with [`HeaderChain::new`]; [`HeaderChain::from_genesis`]
+    /// takes the rules from the spec.
+    pub fn with_retarget(mut self, retarget: RetargetConfig) -> Self {
+        self.retarget = retarget;
+        self
+    }
+    
+    /// Number of headers, including the genesis header
+    pub fn height(&self) -> u64 {
+        self.headers.len() as u64
+    }
+    
+    pub fn tip(&self) -> &BlockHeader {
+        self.headers.last().expect("header chain has a genesis header")
+    }
+    
+    /// Header of the main-chain block at `number`
+    pub fn header(&self, number: u64) -> Option<&BlockHeader> {
+        self.headers.get(number as usize)
+    }
+    
+    /// Hash of the main-chain block at `number`
+    pub fn hash_at(&self, number: u64) -> Option<&str> {
+        self.hashes.get(number as usize).map(String::as_str)
+    }
+    
+    /// Work of the main chain
+    pub fn total_work(&self) -> u128 {
+        *self.total_work.last().expect("header chain has a genesis header")
+    }
+    
+    /// Check consecutive headers branching off the chain and switch to them if the
+    /// resulting branch has more work
+    ///
+    /// The first header's parent must be on the main chain. Returns whether the chain
+    /// changed; a branch with no more work than the main chain is checked but not kept.
+    pub fn import(&mut self, headers: &[BlockHeader]) -> Result<bool> {
+        let first = match headers.first() {
+            Some(first) => first,
+            None => return Ok(false),
+        };
+        let fork = first.number.checked_sub(1)
+            .filter(|&fork| self.hash_at(fork) == Some(first.parent_hash.as_str()))
+            .ok_or_else(|| SdkError::InvalidBlock(
+                format!("Header {} does not connect to the header chain", first.number)
+            ))?;
+        
+        // Ancestors come from the new headers above the fork and from the chain below it
+        let ancestor = |number: u64| match number.checked_sub(first.number) {
+            Some(offset) => &headers[offset as usize],
+            None => &self.headers[number as usize],
+        };
+        
+        let mut work = self.total_work[fork as usize];
+        let mut hashes: Vec<String> = Vec::with_capacity(headers.len());
+        for (offset, header) in headers.iter().enumerate() {
+            let (parent, parent_hash) = match offset {
+                0 => (&self.headers[fork as usize], &self.hashes[fork as usize]),
+                _ => (&headers[offset - 1], &hashes[offset - 1]),
+            };
+            if header.number != parent.number + 1 || &header.parent_hash != parent_hash {
+                return Err(SdkError::InvalidBlock(
+                    format!("Header {} does not follow header {}", header.number, parent.number)
+                ));
+            }
+            self.validate_header(header, parent, ancestor)?;
+            work += self.consensus.work(header);
+            hashes.push(header.hash());
+        }
+        
+        if work <= self.total_work() {
+            return Ok(false);
+        }
+        
+        // Headers the chain already has do not count towards the reorganization depth
+        let common = headers.iter()
+            .zip(&hashes)
+            .take_while(|(header, hash)| self.hash_at(header.number) == Some(hash.as_str()))
+            .count() as u64;
+        let depth = (self.height() - 1).saturating_sub(fork + common);
+        if depth > MAX_REORG_DEPTH {
+            return Err(SdkError::InvalidBlock(
+                format!("Reorganization of {} headers exceeds the limit of {}", depth, MAX_REORG_DEPTH)
+            ));
+        }
+        
+        let keep = fork as usize + 1;
+        self.headers.truncate(keep);
+        self.hashes.truncate(keep);
+        self.total_work.truncate(keep);
+        let mut work = self.total_work();
+        for (header, hash) in headers.iter().zip(hashes) {
+            work += self.consensus.work(header);
+            self.headers.push(header.clone());
+            self.hashes.push(hash);
+            self.total_work.push(work);
+        }
+        Ok(true)
+    }
+    
+    /// Check the seal, target and timestamp of a header against its parent, the same way
+    /// a node checks a block
+    fn validate_header<'a>(
+        &self,
+        header: &BlockHeader,
+        parent: &'a BlockHeader,
+        ancestor: impl Fn(u64) -> &'a BlockHeader,
+    ) -> Result<()> {
+        self.consensus.verify_seal(header)?;
+        
+        // The first header of a retarget window gets a target scaled by how long the
+        // previous window took
+        let expected_bits = match self.consensus.is_proof_of_work() && self.retarget.is_retarget_height(header.number) {
+            true => {
+                let first = ancestor(header.number - self.retarget.window);
+                let timespan = parent.timestamp.saturating_sub(first.timestamp);
+                self.retarget.next_bits(parent.bits, timespan)
+            }
+            false => parent.bits,
+        };
+        if header.bits != expected_bits {
+            return Err(SdkError::InvalidBlock(
+                format!("Invalid target: expected bits {:#010x}, got {:#010x}", expected_bits, header.bits)
+            ));
+        }
+        
+        if header.timestamp < parent.timestamp {
+            return Err(SdkError::InvalidBlock(
+                "Block timestamp is before its parent's".to_string()
+            ));
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        if header.timestamp > now + MAX_FUTURE_BLOCK_TIME {
+            return Err(SdkError::InvalidBlock(
+                "Block timestamp is too far in the future".to_string()
+            ));
+        }
+        
+        Ok(())
+    }
+}
+
+/// Client that keeps only block headers and checks everything else it is told against them
+///
+/// Balances and transaction inclusion are fetched from a full node together with Merkle
+/// proofs, and are only returned once the proofs match a header of the verified chain.
+/// The node is trusted for nothing but availability.
+pub struct LightClient {
+    client: ElysiumClient,
+    headers: Mutex<HeaderChain>,
+}
+
+impl LightClient {
+    pub fn new(client: ElysiumClient, headers: HeaderChain) -> Self {
+        Self {
+            client,
+            headers: Mutex::new(headers),
+        }
+    }
+    
+    pub fn client(&self) -> &ElysiumClient {
+        &self.client
+    }
+    
+    /// Number of verified headers, including the genesis header
+    pub async fn height(&self) -> u64 {
+        self.headers.lock().await.height()
+    }
+    
+    /// Latest verified header
+    pub async fn tip(&self) -> BlockHeader {
+        self.headers.lock().await.tip().clone()
+    }
+    
+    /// Download and check the node's new headers, returning the new height
+    ///
+    /// If the node has switched to another branch, its headers are fetched again from
+    /// up to [`MAX_REORG_DEPTH`] blocks back and adopted if the branch has more work.
+    pub async fn sync(&self) -> Result<u64> {
+        let mut headers = self.headers.lock().await;
+        self.sync_headers(&mut headers).await?;
+        Ok(headers.height())
+    }
+    
+    /// Account of `address` as of the node's latest block, checked against its state root;
+    /// `None` if the account does not exist
+    pub async fn get_account(&self, address: &Address) -> Result<Option<Account>> {
+        let proof = self.client.get_account_proof(address).await?;
+        let block_hash = self.trusted_hash(&proof.header).await?;
+        verify_account_proof(&proof, address, &block_hash)
+    }
+    
+    /// Balance of `address` as of the node's latest block, checked against its state root
+    pub async fn get_balance(&self, address: &Address) -> Result<u64> {
+        Ok(self.get_account(address).await?.map_or(0, |account| account.balance))
+    }
+    
+    /// Check that a transaction is in a block of the verified chain
+    ///
+    /// Returns the checked inclusion proof, whose header tells the block, or `None` if
+    /// the node does not know the transaction to be in a block.
+    pub async fn verify_transaction(&self, tx_hash: &str) -> Result<Option<TransactionProof>> {
+        let proof = match self.client.get_transaction_proof(tx_hash).await? {
+            Some(proof) => proof,
+            None => return Ok(None),
+        };
+        let block_hash = self.trusted_hash(&proof.header).await?;
+        verify_transaction_proof(&proof, tx_hash, &block_hash)?;
+        Ok(Some(proof))
+    }
+    
+    /// Hash of the verified header at the height of `header`, syncing first if the node is ahead
+    async fn trusted_hash(&self, header: &BlockHeader) -> Result<String> {
+        let mut headers = self.headers.lock().await;
+        if header.number >= headers.height() || headers.hash_at(header.number) != Some(header.hash().as_str()) {
+            self.sync_headers(&mut headers).await?;
+        }
+        headers.hash_at(header.number)
+            .map(str::to_string)
+            .ok_or_else(|| SdkError::InvalidProof(
+                format!("Block {} is not on the verified header chain", header.number)
+            ))
+    }
+    
+    async fn sync_headers(&self, chain: &mut HeaderChain) -> Result<()> {
+        let mut start = chain.height();
+        let mut searched_fork = false;
+        loop {
+            let headers = self.client.get_headers(start, MAX_HEADERS_PER_REQUEST).await?;
+            let first = match headers.first() {
+                Some(first) => first,
+                None => return Ok(()),
+            };
+            
+            if first.number != start || chain.hash_at(start - 1) != Some(first.parent_hash.as_str()) {
+                // The node is on another branch: fetch its headers from below the fork point
+                let fork_search = chain.height().saturating_sub(MAX_REORG_DEPTH).max(1);
+                if searched_fork || first.number != start {
+                    return Err(SdkError::InvalidBlock(
+                        "Node's headers do not connect to the verified header chain".to_string()
+                    ));
+                }
+                searched_fork = true;
+                start = fork_search;
+                continue;
+            }
+            
+            // A branch with no more work than ours is not followed
+            if !chain.import(&headers)? {
+                return Ok(());
+            }
+            searched_fork = false;
+            start = chain.height();
+            if headers.len() < MAX_HEADERS_PER_REQUEST {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elysium_core::{account::KeyPair, Blockchain, Transaction};
+    use elysium_node::test_util::{funded_spec, TEST_CHAIN_ID};
+    use elysium_node::{Node, RpcServer};
+    
+    /// Mine `count` blocks, each with a transfer to a new address
+    fn mine_transfers(blockchain: &mut Blockchain, from: &KeyPair, count: usize) -> Vec<BlockHeader> {
+        (0..count)
+            .map(|_| {
+                let nonce = blockchain.get_nonce(&from.address());
+                let mut tx = Transaction::new(from.address(), KeyPair::generate().address(), 1, nonce)
+                    .with_chain_id(TEST_CHAIN_ID);
+                from.sign_transaction(&mut tx).unwrap();
+                blockchain.add_transaction(tx).unwrap();
+                blockchain.mine_block().unwrap().header
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_header_chain_checks_headers() {
+        let alice = KeyPair::generate();
+        let spec = funded_spec(&alice);
+        let mut blockchain = Blockchain::from_genesis(&spec).unwrap();
+        let headers = mine_transfers(&mut blockchain, &alice, 3);
+        
+        let mut chain = HeaderChain::from_genesis(&spec).unwrap();
+        assert!(chain.import(&headers).unwrap());
+        assert_eq!(chain.height(), 4);
+        assert_eq!(chain.hash_at(3), Some(blockchain.latest_block().hash().as_str()));
+        assert!(!chain.import(&headers[1..]).unwrap());
+        
+        // Headers must link up and carry valid proof of work
+        let mut fresh = HeaderChain::from_genesis(&spec).unwrap();
+        assert!(fresh.import(&headers[1..]).is_err());
+        let mut unsealed = headers.clone();
+        unsealed[0].bits = 0x03000001;
+        assert!(fresh.import(&unsealed).is_err());
+        let mut forged = headers.clone();
+        forged[1].state_root = "0".repeat(64);
+        assert!(fresh.import(&forged).is_err());
+        assert_eq!(fresh.height(), 1);
+        
+        // Another network's genesis does not lead to these headers
+        let mut other = HeaderChain::from_genesis(&GenesisSpec { chain_id: TEST_CHAIN_ID + 1, ..spec }).unwrap();
+        assert!(other.import(&headers).is_err());
+    }
+    
+    #[test]
+    fn test_header_chain_follows_most_work() {
+        let alice = KeyPair::generate();
+        let spec = funded_spec(&alice);
+        let mut chain_a = Blockchain::from_genesis(&spec).unwrap();
+        let mut chain_b = Blockchain::from_genesis(&spec).unwrap();
+        let common = mine_transfers(&mut chain_a, &alice, 1);
+        chain_b.add_block(chain_a.latest_block().clone()).unwrap();
+        let short = mine_transfers(&mut chain_a, &alice, 1);
+        let long = mine_transfers(&mut chain_b, &alice, 2);
+        
+        let mut chain = HeaderChain::from_genesis(&spec).unwrap();
+        chain.import(&common).unwrap();
+        assert!(chain.import(&short).unwrap());
+        assert!(chain.import(&long).unwrap());
+        assert_eq!(chain.height(), 4);
+        assert_eq!(chain.hash_at(3), Some(chain_b.latest_block().hash().as_str()));
+        assert_eq!(chain.total_work(), common[0].work() + long[0].work() + long[1].work());
+        
+        // The shorter branch does not take over again
+        assert!(!chain.import(&short).unwrap());
+        assert_eq!(chain.tip().hash(), chain_b.latest_block().hash());
+    }
+    
+    #[tokio::test]
+    async fn test_light_client_verifies_node_responses() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate().address();
+        let spec = funded_spec(&alice);
+        let node = Node::from_genesis(&spec).unwrap();
+        let server = RpcServer::start(node.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = ElysiumClient::new(format!("http://{}", server.address()));
+        let light = LightClient::new(client, HeaderChain::from_genesis(&spec).unwrap());
+        
+        let mut tx = Transaction::new(alice.address(), bob.clone(), 40, 0).with_chain_id(TEST_CHAIN_ID);
+        alice.sign_transaction(&mut tx).unwrap();
+        node.add_transaction(tx.clone()).await.unwrap();
+        node.mine_block().await.unwrap();
+        
+        // Proofs about blocks beyond the verified headers make the client sync first
+        assert_eq!(light.get_balance(&bob).await.unwrap(), 40);
+        assert_eq!(light.height().await, 2);
+        let proof = light.verify_transaction(&tx.hash()).await.unwrap().unwrap();
+        assert_eq!(proof.header.number, 1);
+        assert!(light.verify_transaction(&"0".repeat(64)).await.unwrap().is_none());
+        assert!(light.get_account(&KeyPair::generate().address()).await.unwrap().is_none());
+        
+        let mut tx = Transaction::new(alice.address(), bob.clone(), 2, 1).with_chain_id(TEST_CHAIN_ID);
+        alice.sign_transaction(&mut tx).unwrap();
+        node.add_transaction(tx).await.unwrap();
+        node.mine_block().await.unwrap();
+        assert_eq!(light.sync().await.unwrap(), 3);
+        assert_eq!(light.tip().await.hash(), node.blockchain().await.latest_block().hash());
+        
+        // A node on another network cannot convince the client
+        let other = LightClient::new(
+            light.client().clone(),
+            HeaderChain::from_genesis(&GenesisSpec { chain_id: TEST_CHAIN_ID + 1, ..spec }).unwrap(),
+        );
+        assert!(other.sync().await.is_err());
+        assert!(other.get_balance(&bob).await.is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use elysium_core::{account::KeyPair, contract_address, Address, Transaction, TransactionReceipt, TransactionType};
+use futures::StreamExt;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use crate::{ElysiumClient, Result, SdkError, SubscriptionClient};
+
+/// Time between receipt checks while waiting for a confirmation without a subscription
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends transactions with locally tracked nonces and waits for their confirmation
+///
+/// The first transaction from an account takes the node's pending nonce and later ones
+/// count up from it, so several transactions can be sent before any is mined. When a send
+/// fails, the account's nonce is asked from the node again for the next one.
+pub struct TransactionManager {
+    client: ElysiumClient,
+    subscriptions: Option<SubscriptionClient>,
+    poll_interval: Duration,
+    chain_id: Mutex<Option<u64>>,
+    /// Next nonce of each account that has sent through this manager
+    nonces: Mutex<HashMap<Address, u64>>,
+}
+
+impl TransactionManager {
+    pub fn new(client: ElysiumClient) -> Self {
+        Self {
+            client,
+            subscriptions: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            chain_id: Mutex::new(None),
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Wake up for every new block instead of polling while waiting for confirmations
+    pub fn with_subscriptions(mut self, subscriptions: SubscriptionClient) -> Self {
+        self.subscriptions = Some(subscriptions);
+        self
+    }
+    
+    /// Check for confirmations this often when not subscribed
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    
+    pub fn client(&self) -> &ElysiumClient {
+        &self.client
+    }
+    
+    /// Fill in the nonce and chain id of a transaction from `from`, then sign and send it
+    ///
+    /// A deployment gets the contract address for the nonce it is signed with.
+    pub async fn send(&self, from: &KeyPair, tx: Transaction) -> Result<String> {
+        self.send_all(from, vec![tx]).await?
+            .pop()
+            .expect("one result per transaction")
+    }
+    
+    /// Send transactions from `from` with consecutive nonces in one batch request,
+    /// returning each one's hash or error
+    pub async fn send_all(&self, from: &KeyPair, txs: Vec<Transaction>) -> Result<Vec<Result<String>>> {
+        let address = from.address();
+        let chain_id = self.chain_id().await?;
+        
+        // Held until the node answers, so concurrent sends get their nonces in order
+        let mut nonces = self.nonces.lock().await;
+        let mut nonce = match nonces.get(&address) {
+            Some(nonce) => *nonce,
+            None => self.client.get_pending_nonce(&address).await?,
+        };
+        
+        let mut signed = Vec::with_capacity(txs.len());
+        for mut tx in txs {
+            if tx.from != address {
+                return Err(SdkError::InvalidTransaction(
+                    "Transaction is not from the signing account".to_string()
+                ));
+            }
+            tx.nonce = nonce;
+            tx.chain_id = chain_id;
+            if let TransactionType::Deploy { .. } = tx.transaction_type {
+                tx.to = contract_address(&address, nonce);
+            }
+            from.sign_transaction(&mut tx)
+                .map_err(|e| SdkError::KeyError(e.to_string()))?;
+            signed.push(tx);
+            nonce += 1;
+        }
+        
+        let results = self.client.send_transactions(&signed).await;
+        match &results {
+            Ok(results) if results.iter().all(Result::is_ok) => {
+                nonces.insert(address, nonce);
+            }
+            // Which nonces the node took is unknown, so ask it next time
+            _ => {
+                nonces.remove(&address);
+            }
+        }
+        results
+    }
+    
+    /// Forget an account's local nonce, e.g. after its pending transactions were dropped
+    pub async fn reset_nonce(&self, address: &Address) {
+        self.nonces.lock().await.remove(address);
+    }
+    
+    /// Wait until a transaction is in a main-chain block with at least `confirmations`
+    /// confirmations (1 once it is included), returning its receipt
+    ///
+    /// A transaction rolled back by a reorganization is waited for until it is included again.
+    pub async fn wait_for_confirmation(
+        &self,
+        tx_hash: &str,
+        confirmations: u64,
+        timeout: Duration,
+    ) -> Result<TransactionReceipt> {
+        let deadline = Instant::now() + timeout;
+        // Subscribe before the first check so no block in between is missed
+        let mut heads = match &self.subscriptions {
+            Some(subscriptions) => Some(subscriptions.new_heads().await?),
+            None => None,
+        };
+        
+        loop {
+            if let Some(receipt) = self.client.get_transaction_receipt(tx_hash).await? {
+                if receipt.confirmations >= confirmations {
+                    return Ok(receipt);
+                }
+            }
+            
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(SdkError::Timeout(format!(
+                    "Transaction {} did not reach {} confirmations within {:?}", tx_hash, confirmations, timeout
+                )));
+            }
+            match &mut heads {
+                Some(stream) => {
+                    // Without a new block before the deadline, check one last time
+                    if let Ok(None) = tokio::time::timeout(remaining, stream.next()).await {
+                        // The connection closed, so poll from now on
+                        heads = None;
+                    }
+                }
+                None => tokio::time::sleep(self.poll_interval.min(remaining)).await,
+            }
+        }
+    }
+    
+    async fn chain_id(&self) -> Result<u64> {
+        let mut chain_id = self.chain_id.lock().await;
+        if let Some(chain_id) = *chain_id {
+            return Ok(chain_id);
+        }
+        let fetched = self.client.get_chain_id().await?;
+        *chain_id = Some(fetched);
+        Ok(fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RpcCall;
+    use elysium_node::test_util::funded_node;
+    use elysium_node::{Node, RpcServer, WsServer};
+    use serde_json::json;
+    
+    fn serve(node: &Node) -> (RpcServer, ElysiumClient) {
+        let server = RpcServer::start(node.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = ElysiumClient::new(format!("http://{}", server.address()));
+        (server, client)
+    }
+    
+    #[tokio::test]
+    async fn test_send_with_local_nonces() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate().address();
+        let node = funded_node(&alice);
+        let (_server, client) = serve(&node);
+        let manager = TransactionManager::new(client.clone());
+        
+        // Several sends before anything is mined get consecutive nonces
+        for amount in 1..=3 {
+            manager.send(&alice, Transaction::new(alice.address(), bob.clone(), amount, 0)).await.unwrap();
+        }
+        let batch = vec![
+            Transaction::new(alice.address(), bob.clone(), 4, 0),
+            Transaction::new(alice.address(), bob.clone(), 5, 0),
+        ];
+        let hashes = manager.send_all(&alice, batch).await.unwrap();
+        assert!(hashes.iter().all(Result::is_ok));
+        let mut nonces: Vec<u64> = client.get_pending_transactions().await.unwrap()
+            .iter()
+            .map(|tx| tx.nonce)
+            .collect();
+        nonces.sort();
+        assert_eq!(nonces, vec![0, 1, 2, 3, 4]);
+        
+        // A rejected transaction makes the manager ask the node for the nonce again
+        let result = manager.send(&alice, Transaction::new(alice.address(), bob.clone(), 10_000, 0)).await;
+        assert!(matches!(result, Err(SdkError::InsufficientBalance)));
+        let hash = manager.send(&alice, Transaction::new(alice.address(), bob.clone(), 6, 0)).await.unwrap();
+        node.mine_block().await.unwrap();
+        assert_eq!(client.get_transaction_by_hash(&hash).await.unwrap().unwrap().transaction.nonce, 5);
+        assert_eq!(client.get_balance(&bob).await.unwrap(), 21);
+        
+        // Other accounts' transactions cannot be signed
+        let other = Transaction::new(bob.clone(), alice.address(), 1, 0);
+        assert!(matches!(manager.send(&alice, other).await, Err(SdkError::InvalidTransaction(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_batch_results_keep_their_order() {
+        let (_server, client) = serve(&Node::new(1));
+        let results = client.batch(&[
+            RpcCall::new("elysium_getHeight", json!([])),
+            RpcCall::new("elysium_unknown", json!([])),
+            RpcCall::new("elysium_getNonce", json!([KeyPair::generate().address()])),
+        ]).await.unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), &json!(1));
+        assert!(matches!(results[1], Err(SdkError::JsonRpcError(_))));
+        assert_eq!(results[2].as_ref().unwrap(), &json!(0));
+        assert!(client.batch(&[]).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_wait_for_confirmation() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate().address();
+        let node = funded_node(&alice);
+        let (_server, client) = serve(&node);
+        let manager = TransactionManager::new(client).with_poll_interval(Duration::from_millis(20));
+        
+        let hash = manager.send(&alice, Transaction::new(alice.address(), bob.clone(), 1, 0)).await.unwrap();
+        let miner = node.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            miner.mine_block().await.unwrap();
+        });
+        let receipt = manager.wait_for_confirmation(&hash, 1, Duration::from_secs(10)).await.unwrap();
+        assert_eq!(receipt.block_number, 1);
+        
+        let result = manager.wait_for_confirmation(&hash, 2, Duration::from_millis(100)).await;
+        assert!(matches!(result, Err(SdkError::Timeout(_))));
+        
+        // With a subscription, every new block triggers a check
+        let ws = WsServer::start(node.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let subscriptions = SubscriptionClient::connect(&format!("ws://{}", ws.address())).await.unwrap();
+        let manager = manager.with_subscriptions(subscriptions).with_poll_interval(Duration::from_secs(60));
+        manager.send(&alice, Transaction::new(alice.address(), bob, 1, 0)).await.unwrap();
+        let miner = node.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            miner.mine_block().await.unwrap();
+        });
+        let receipt = manager.wait_for_confirmation(&hash, 2, Duration::from_secs(10)).await.unwrap();
+        assert_eq!(receipt.confirmations, 2);
+    }
+}
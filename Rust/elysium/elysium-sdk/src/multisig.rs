@@ -0,0 +1,131 @@
+use elysium_core::{account::KeyPair, Address, Cosignature, MultisigPolicy, Transaction, TransactionType};
+use crate::{Result, SdkError};
+
+/// Collects the co-signatures of a transfer out of a multisig account
+///
+/// One signer creates the builder and shares [`transaction`](Self::transaction) with the
+/// other signers. Each of them either signs a copy with [`sign`](Self::sign) and sends back
+/// the co-signature, which is added with [`add_cosignature`](Self::add_cosignature), or
+/// signs the shared builder directly. Once the threshold is met, [`build`](Self::build)
+/// returns the transaction to send.
+#[derive(Debug, Clone)]
+pub struct MultisigTransferBuilder {
+    policy: MultisigPolicy,
+    tx: Transaction,
+}
+
+impl MultisigTransferBuilder {
+    /// Start a transfer out of the account controlled by `policy`
+    pub fn new(policy: MultisigPolicy, to: Address, amount: u64, nonce: u64) -> Self {
+        let tx = Transaction::multisig_transfer(&policy, to, amount, nonce);
+        Self { policy, tx }
+    }
+    
+    /// Continue collecting co-signatures for a transaction shared by another signer
+    pub fn from_transaction(policy: MultisigPolicy, tx: Transaction) -> Result<Self> {
+        if tx.transaction_type != TransactionType::MultisigTransfer || tx.from != policy.address() {
+            return Err(SdkError::InvalidTransaction(
+                "Not a transfer out of this multisig account".to_string()
+            ));
+        }
+        let mut builder = Self { policy, tx: Transaction { cosignatures: Vec::new(), ..tx.clone() } };
+        for cosignature in tx.cosignatures {
+            builder.add_cosignature(cosignature)?;
+        }
+        Ok(builder)
+    }
+    
+    /// Set the fee paid to the miner, dropping any co-signatures collected so far
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.tx.fee = fee;
+        self.tx.cosignatures.clear();
+        self
+    }
+    
+    /// Set the network the transfer is valid on, dropping any co-signatures collected so far
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.tx.chain_id = chain_id;
+        self.tx.cosignatures.clear();
+        self
+    }
+    
+    /// The transfer with the co-signatures collected so far
+    pub fn transaction(&self) -> &Transaction {
+        &self.tx
+    }
+    
+    /// Add a signer's co-signature, returning it so it can be passed on
+    pub fn sign(&mut self, signer: &KeyPair) -> Result<Cosignature> {
+        if !self.policy.signers.contains(&signer.address()) {
+            return Err(SdkError::InvalidTransaction(
+                format!("{} is not a signer of this multisig account", signer.address())
+            ));
+        }
+        let cosignature = Cosignature::sign(signer.signing_key(), &self.tx);
+        self.add_cosignature(cosignature.clone())?;
+        Ok(cosignature)
+    }
+    
+    /// Add a co-signature received from another signer
+    pub fn add_cosignature(&mut self, cosignature: Cosignature) -> Result<()> {
+        let signer = cosignature.verify(&self.tx)
+            .map_err(|e| SdkError::InvalidTransaction(e.to_string()))?;
+        if !self.policy.signers.contains(&signer) {
+            return Err(SdkError::InvalidTransaction(
+                format!("{} is not a signer of this multisig account", signer)
+            ));
+        }
+        self.tx.cosignatures.retain(|existing| existing.public_key != cosignature.public_key);
+        self.tx.cosignatures.push(cosignature);
+        Ok(())
+    }
+    
+    /// Number of co-signatures still needed
+    pub fn missing(&self) -> usize {
+        (self.policy.threshold as usize).saturating_sub(self.tx.cosignatures.len())
+    }
+    
+    /// Check whether enough signers have co-signed
+    pub fn is_complete(&self) -> bool {
+        self.missing() == 0
+    }
+    
+    /// The co-signed transfer, ready to send
+    pub fn build(self) -> Result<Transaction> {
+        self.policy.authorize(&self.tx)
+            .map_err(|e| SdkError::InvalidTransaction(e.to_string()))?;
+        Ok(self.tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_collect_cosignatures() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let carol = KeyPair::generate();
+        let policy = MultisigPolicy::new(2, vec![alice.address(), bob.address(), carol.address()]).unwrap();
+        let mut builder = MultisigTransferBuilder::new(policy.clone(), carol.address(), 50, 0)
+            .with_fee(1)
+            .with_chain_id(7);
+        
+        builder.sign(&alice).unwrap();
+        assert_eq!(builder.missing(), 1);
+        assert!(builder.clone().build().is_err());
+        assert!(builder.sign(&KeyPair::generate()).is_err());
+        
+        // Bob signs the shared transaction elsewhere and sends back their co-signature
+        let mut remote = MultisigTransferBuilder::from_transaction(policy, builder.transaction().clone()).unwrap();
+        let cosignature = remote.sign(&bob).unwrap();
+        builder.add_cosignature(cosignature).unwrap();
+        assert!(builder.is_complete());
+        
+        let tx = builder.build().unwrap();
+        assert_eq!(tx.cosignatures.len(), 2);
+        assert_eq!(tx.chain_id, 7);
+        tx.verify().unwrap();
+    }
+}
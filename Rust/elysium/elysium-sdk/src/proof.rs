@@ -0,0 +1,128 @@
+use elysium_core::{Account, Address, StateProof, TransactionProof};
+use crate::{Result, SdkError};
+
+/// Verify a transaction inclusion proof returned by a node
+///
+/// `block_hash` must come from a source the caller trusts (e.g. its own view of the
+/// chain); the proof then shows that `tx_hash` is committed to by that block without
+/// downloading the block's transactions.
+pub fn verify_transaction_proof(proof: &TransactionProof, tx_hash: &str, block_hash: &str) -> Result<()> {
+    if proof.transaction_hash != tx_hash {
+        return Err(SdkError::InvalidProof(
+            format!("Proof is for transaction {}, expected {}", proof.transaction_hash, tx_hash)
+        ));
+    }
+    
+    if proof.block_hash != block_hash || proof.header.hash() != block_hash {
+        return Err(SdkError::InvalidProof(
+            "Block header does not match the trusted block hash".to_string()
+        ));
+    }
+    
+    if !proof.proof.verify(tx_hash, &proof.header.transactions_root) {
+        return Err(SdkError::InvalidProof(
+            "Merkle path does not lead to the transactions root".to_string()
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Verify an account state proof returned by a node
+///
+/// On success returns the proven account, or `None` if the proof shows that
+/// `address` has no account as of the trusted block.
+pub fn verify_account_proof(proof: &StateProof, address: &Address, block_hash: &str) -> Result<Option<Account>> {
+    if &proof.proof.address != address {
+        return Err(SdkError::InvalidProof(
+            format!("Proof is for account {}, expected {}", proof.proof.address, address)
+        ));
+    }
+    
+    if proof.block_hash != block_hash || proof.header.hash() != block_hash {
+        return Err(SdkError::InvalidProof(
+            "Block header does not match the trusted block hash".to_string()
+        ));
+    }
+    
+    if !proof.proof.verify(&proof.header.state_root) {
+        return Err(SdkError::InvalidProof(
+            "Account proof does not match the state root".to_string()
+        ));
+    }
+    
+    Ok(proof.proof.account.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elysium_core::{Block, Blockchain, Transaction};
+    use elysium_core::account::KeyPair;
+    
+    fn block_with_transactions(count: u64) -> Block {
+        let keypair = KeyPair::generate();
+        let to = KeyPair::generate().address();
+        let mut block = Block::new(1, "0".repeat(64), 1);
+        for nonce in 0..count {
+            let mut tx = Transaction::new(keypair.address(), to.clone(), 1, nonce);
+            tx.sign(keypair.signing_key()).unwrap();
+            block.add_transaction(tx);
+        }
+        block.mine();
+        block
+    }
+    
+    #[test]
+    fn test_verify_transaction_proof() {
+        let block = block_with_transactions(6);
+        let tx_hash = block.transactions[4].hash();
+        let proof = block.transaction_proof(&tx_hash).unwrap();
+        
+        assert!(verify_transaction_proof(&proof, &tx_hash, &block.hash()).is_ok());
+        
+        // Another block hash is not trusted
+        assert!(verify_transaction_proof(&proof, &tx_hash, &"0".repeat(64)).is_err());
+        
+        // A proof for one transaction does not prove another
+        let other = block.transactions[1].hash();
+        assert!(verify_transaction_proof(&proof, &other, &block.hash()).is_err());
+        
+        // A forged header root is caught by the block hash
+        let mut forged = proof.clone();
+        forged.header.transactions_root = "0".repeat(64);
+        assert!(verify_transaction_proof(&forged, &tx_hash, &block.hash()).is_err());
+        
+        // A tampered path is caught by the root
+        let mut tampered = proof;
+        tampered.proof.siblings[0] = "0".repeat(64);
+        assert!(verify_transaction_proof(&tampered, &tx_hash, &block.hash()).is_err());
+    }
+    
+    #[test]
+    fn test_verify_account_proof() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut blockchain = Blockchain::new(1);
+        let mut account = Account::new(alice.address());
+        account.balance = 100;
+        blockchain.accounts.insert(alice.address(), account);
+        let mut tx = Transaction::new(alice.address(), bob.address(), 30, 0);
+        tx.sign(alice.signing_key()).unwrap();
+        blockchain.add_transaction(tx).unwrap();
+        let block = blockchain.mine_block().unwrap();
+        
+        let proof = blockchain.get_account_proof(&bob.address());
+        let account = verify_account_proof(&proof, &bob.address(), &block.hash()).unwrap();
+        assert_eq!(account.unwrap().balance, 30);
+        
+        let missing = KeyPair::generate().address();
+        let proof = blockchain.get_account_proof(&missing);
+        assert!(verify_account_proof(&proof, &missing, &block.hash()).unwrap().is_none());
+        assert!(verify_account_proof(&proof, &bob.address(), &block.hash()).is_err());
+        
+        let mut forged = blockchain.get_account_proof(&bob.address());
+        forged.proof.account.as_mut().unwrap().balance = 1_000;
+        assert!(verify_account_proof(&forged, &bob.address(), &block.hash()).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use elysium_core::{Address, BlockHeader, Transaction};
+use futures::{SinkExt, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use crate::{Result, SdkError};
+
+/// Balance of an address after a block changed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub address: Address,
+    pub balance: u64,
+    /// Number of the block the balance was read at
+    pub block: u64,
+}
+
+/// Client for a node's WebSocket endpoint, streaming events as they happen
+///
+/// Each subscription is a [`Subscription`] stream. Dropping it unsubscribes; the connection
+/// stays open while the client or any of its subscriptions is alive.
+pub struct SubscriptionClient {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl SubscriptionClient {
+    /// Connect to a WebSocket URL such as `ws://127.0.0.1:8546`
+    pub async fn connect(ws_url: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(ws_url).await
+            .map_err(|e| SdkError::WebSocketError(e.to_string()))?;
+        let (commands, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(socket, receiver));
+        Ok(Self { commands })
+    }
+    
+    /// Header of every new chain tip, including tips reached by a reorganization
+    pub async fn new_heads(&self) -> Result<Subscription<BlockHeader>> {
+        self.subscribe(json!(["newHeads"])).await
+    }
+    
+    /// Every transaction the node accepts into its pending pool
+    pub async fn pending_transactions(&self) -> Result<Subscription<Transaction>> {
+        self.subscribe(json!(["newPendingTransactions"])).await
+    }
+    
+    /// Balance of `address` whenever a new block changes it
+    pub async fn balance_changes(&self, address: &Address) -> Result<Subscription<BalanceChange>> {
+        self.subscribe(json!(["balance", address.as_hex()])).await
+    }
+    
+    async fn subscribe<T>(&self, params: Value) -> Result<Subscription<T>> {
+        let (notifications, receiver) = mpsc::unbounded_channel();
+        let (reply, response) = oneshot::channel();
+        self.commands.send(Command::Subscribe { params, notifications, reply })
+            .map_err(|_| connection_closed())?;
+        let id = response.await.map_err(|_| connection_closed())??;
+        Ok(Subscription {
+            id,
+            notifications: receiver,
+            commands: self.commands.clone(),
+            _item: PhantomData,
+        })
+    }
+}
+
+/// Stream of subscription events; ends when the connection closes
+pub struct Subscription<T> {
+    id: Value,
+    notifications: mpsc::UnboundedReceiver<Value>,
+    commands: mpsc::UnboundedSender<Command>,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> Subscription<T> {
+    /// Id the node assigned to the subscription
+    pub fn id(&self) -> &Value {
+        &self.id
+    }
+}
+
+impl<T: DeserializeOwned> Stream for Subscription<T> {
+    type Item = Result<T>;
+    
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications.poll_recv(cx).map(|event| event.map(|value| {
+            serde_json::from_value(value).map_err(|e| SdkError::SerializationError(e.to_string()))
+        }))
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Unsubscribe(self.id.clone()));
+    }
+}
+
+enum Command {
+    Subscribe {
+        params: Value,
+        notifications: mpsc::UnboundedSender<Value>,
+        reply: oneshot::Sender<Result<Value>>,
+    },
+    Unsubscribe(Value),
+}
+
+fn connection_closed() -> SdkError {
+    SdkError::WebSocketError("Connection closed".to_string())
+}
+
+/// Own the socket: send requests for commands and route responses and notifications
+async fn run<S>(socket: S, mut commands: mpsc::UnboundedReceiver<Command>)
+where
+    S: Stream<Item = tokio_tungstenite::tungstenite::Result<Message>>
+        + futures::Sink<Message> + Unpin,
+{
+    let (mut writer, mut reader) = socket.split();
+    let mut next_id = 1u64;
+    let mut pending = HashMap::new();
+    // Keyed by the JSON text of the subscription id
+    let mut subscriptions: HashMap<String, mpsc::UnboundedSender<Value>> = HashMap::new();
+    // Notifications that beat the response of a pending subscribe
+    let mut early: HashMap<String, Vec<Value>> = HashMap::new();
+    
+    loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else { break };
+                let (method, params) = match command {
+                    Command::Subscribe { params, notifications, reply } => {
+                        pending.insert(next_id, (notifications, reply));
+                        ("elysium_subscribe", params)
+                    }
+                    Command::Unsubscribe(id) => {
+                        subscriptions.remove(&id.to_string());
+                        ("elysium_unsubscribe", json!([id]))
+                    }
+                };
+                let request = json!({ "jsonrpc": "2.0", "id": next_id, "method": method, "params": params });
+                next_id += 1;
+                if writer.send(Message::Text(request.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            message = reader.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let Ok(message) = serde_json::from_str::<Value>(&text) else { continue };
+                
+                if message["method"] == "elysium_subscription" {
+                    let key = message["params"]["subscription"].to_string();
+                    let result = message["params"]["result"].clone();
+                    match subscriptions.get(&key) {
+                        Some(notifications) => {
+                            let _ = notifications.send(result);
+                        }
+                        None if !pending.is_empty() => early.entry(key).or_default().push(result),
+                        None => {}
+                    }
+                } else if let Some((notifications, reply)) = message["id"].as_u64().and_then(|id| pending.remove(&id)) {
+                    let response = match message.get("error") {
+                        Some(error) => Err(SdkError::from_rpc(error)),
+                        None => Ok(message["result"].clone()),
+                    };
+                    if let Ok(id) = &response {
+                        let key = id.to_string();
+                        for result in early.remove(&key).unwrap_or_default() {
+                            let _ = notifications.send(result);
+                        }
+                        subscriptions.insert(key, notifications);
+                    }
+                    if pending.is_empty() {
+                        early.clear();
+                    }
+                    let _ = reply.send(response);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elysium_core::account::KeyPair;
+    use elysium_node::test_util::{funded_node, TEST_CHAIN_ID};
+    use elysium_node::WsServer;
+    
+    #[tokio::test]
+    async fn test_subscription_streams() {
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let node = funded_node(&alice);
+        let server = WsServer::start(node.clone(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = SubscriptionClient::connect(&format!("ws://{}", server.address())).await.unwrap();
+        
+        let mut heads = client.new_heads().await.unwrap();
+        let mut pending = client.pending_transactions().await.unwrap();
+        let mut balances = client.balance_changes(&bob.address()).await.unwrap();
+        assert_ne!(heads.id(), pending.id());
+        assert!(client.subscribe::<Value>(json!(["logs"])).await.is_err());
+        
+        let mut tx = Transaction::new(alice.address(), bob.address(), 40, 0);
+        tx.chain_id = TEST_CHAIN_ID;
+        alice.sign_transaction(&mut tx).unwrap();
+        node.add_transaction(tx.clone()).await.unwrap();
+        assert_eq!(pending.next().await.unwrap().unwrap().hash(), tx.hash());
+        
+        let block = node.mine_block().await.unwrap();
+        assert_eq!(heads.next().await.unwrap().unwrap().hash(), block.hash());
+        let change = balances.next().await.unwrap().unwrap();
+        assert_eq!(change, BalanceChange { address: bob.address(), balance: 40, block: 1 });
+    }
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Reason a contract execution stopped without succeeding
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VmError {
+    #[error("Out of gas")]
+    OutOfGas,
+    
+    #[error("Stack underflow")]
+    StackUnderflow,
+    
+    #[error("Stack overflow")]
+    StackOverflow,
+    
+    #[error("Invalid opcode 0x{0:02x}")]
+    InvalidOpcode(u8),
+    
+    #[error("Invalid jump destination {0}")]
+    InvalidJump(u64),
+    
+    #[error("Truncated immediate at {0}")]
+    TruncatedImmediate(usize),
+    
+    #[error("Division by zero")]
+    DivisionByZero,
+    
+    #[error("Code too large: {0} bytes")]
+    CodeTooLarge(usize),
+    
+    #[error("Execution reverted")]
+    Reverted,
+}
@@ -0,0 +1,9 @@
+pub mod error;
+pub mod opcode;
+pub mod program;
+pub mod vm;
+
+pub use error::*;
+pub use opcode::*;
+pub use program::*;
+pub use vm::*;
@@ -0,0 +1,138 @@
+/// VM instruction
+///
+/// Every instruction is one byte. `Push` is followed by an 8-byte big-endian word and
+/// `Pick` by a one-byte stack depth; no other instruction has an immediate. Binary
+/// operations pop `b`, then `a`, and push `a op b`, so `push a, push b, Sub` computes
+/// `a - b`. Arithmetic wraps on overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Opcode {
+    /// Stop successfully with no output
+    Stop = 0x00,
+    /// Push the following 8-byte word
+    Push = 0x01,
+    Pop = 0x02,
+    /// Duplicate the top word
+    Dup = 0x03,
+    /// Swap the top two words
+    Swap = 0x04,
+    /// Copy the word at the depth given by the following byte (0 is the top) onto the stack
+    Pick = 0x05,
+    
+    Add = 0x10,
+    Sub = 0x11,
+    Mul = 0x12,
+    Div = 0x13,
+    Mod = 0x14,
+    
+    /// Push 1 if `a == b`, else 0
+    Eq = 0x20,
+    Lt = 0x21,
+    Gt = 0x22,
+    /// Push 1 if the top word is 0, else 0
+    IsZero = 0x23,
+    And = 0x24,
+    Or = 0x25,
+    Xor = 0x26,
+    Not = 0x27,
+    
+    /// Pop a destination and continue there; it must be a `JumpDest`
+    Jump = 0x30,
+    /// Pop a destination, then a condition, and jump if the condition is not 0
+    JumpI = 0x31,
+    /// Marks a valid jump destination
+    JumpDest = 0x32,
+    
+    /// Pop a key and push the contract's stored value for it (0 if unset)
+    SLoad = 0x40,
+    /// Pop a key, then a value, and store the value under the key
+    SStore = 0x41,
+    
+    /// Push the amount sent with the call
+    CallValue = 0x50,
+    /// Pop an index and push that word of the call input (0 past the end)
+    Input = 0x51,
+    /// Push the number of input words
+    InputSize = 0x52,
+    /// Push the height of the block the call runs in
+    Height = 0x53,
+    /// Push the contract's balance before the call
+    Balance = 0x54,
+    /// Push the gas left after this instruction
+    Gas = 0x55,
+    
+    /// Pop a count `n`, then `n` words, and stop successfully returning them in the
+    /// order they were pushed
+    Return = 0xf0,
+    /// Stop and discard all storage writes
+    Revert = 0xf1,
+}
+
+impl Opcode {
+    /// Decode an instruction byte
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        use Opcode::*;
+        let opcode = match byte {
+            0x00 => Stop,
+            0x01 => Push,
+            0x02 => Pop,
+            0x03 => Dup,
+            0x04 => Swap,
+            0x05 => Pick,
+            0x10 => Add,
+            0x11 => Sub,
+            0x12 => Mul,
+            0x13 => Div,
+            0x14 => Mod,
+            0x20 => Eq,
+            0x21 => Lt,
+            0x22 => Gt,
+            0x23 => IsZero,
+            0x24 => And,
+            0x25 => Or,
+            0x26 => Xor,
+            0x27 => Not,
+            0x30 => Jump,
+            0x31 => JumpI,
+            0x32 => JumpDest,
+            0x40 => SLoad,
+            0x41 => SStore,
+            0x50 => CallValue,
+            0x51 => Input,
+            0x52 => InputSize,
+            0x53 => Height,
+            0x54 => Balance,
+            0x55 => Gas,
+            0xf0 => Return,
+            0xf1 => Revert,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+    
+    /// Number of immediate bytes following the instruction
+    pub fn immediate_size(self) -> usize {
+        match self {
+            Opcode::Push => 8,
+            Opcode::Pick => 1,
+            _ => 0,
+        }
+    }
+    
+    /// Gas charged before the instruction runs
+    pub fn gas_cost(self) -> u64 {
+        use Opcode::*;
+        match self {
+            Stop | JumpDest => 0,
+            Push | Pop | Dup | Swap | Pick => 1,
+            Add | Sub | Eq | Lt | Gt | IsZero | And | Or | Xor | Not => 2,
+            Mul | Div | Mod => 4,
+            CallValue | Input | InputSize | Height | Balance | Gas => 2,
+            Jump => 6,
+            JumpI => 8,
+            SLoad => 50,
+            SStore => 200,
+            Return | Revert => 1,
+        }
+    }
+}
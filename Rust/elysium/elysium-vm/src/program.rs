@@ -0,0 +1,54 @@
+use crate::Opcode;
+
+/// Builder for contract bytecode
+///
+/// ```
+/// use elysium_vm::{Opcode, Program};
+///
+/// // Return the first input word plus one
+/// let code = Program::new()
+///     .push(0).op(Opcode::Input).push(1).op(Opcode::Add)
+///     .push(1).op(Opcode::Return)
+///     .build();
+/// assert_eq!(code.len(), 30);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    code: Vec<u8>,
+}
+
+impl Program {
+    /// Start an empty program
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Append an instruction without immediate
+    pub fn op(mut self, opcode: Opcode) -> Self {
+        self.code.push(opcode as u8);
+        self
+    }
+    
+    /// Append a `Push` of `word`
+    pub fn push(mut self, word: u64) -> Self {
+        self.code.push(Opcode::Push as u8);
+        self.code.extend_from_slice(&word.to_be_bytes());
+        self
+    }
+    
+    /// Append a raw byte, such as the depth following `Pick`
+    pub fn byte(mut self, byte: u8) -> Self {
+        self.code.push(byte);
+        self
+    }
+    
+    /// Offset of the next instruction, for use as a jump destination
+    pub fn label(&self) -> u64 {
+        self.code.len() as u64
+    }
+    
+    /// The bytecode
+    pub fn build(self) -> Vec<u8> {
+        self.code
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+use crate::{Opcode, VmError};
+
+/// Largest contract code, in bytes
+pub const MAX_CODE_SIZE: usize = 16 * 1024;
+
+/// Most words the stack can hold
+pub const MAX_STACK_DEPTH: usize = 1024;
+
+/// Chain state a contract can read while it runs
+///
+/// Execution never writes through the host: storage writes are collected in the
+/// [`Outcome`] and only applied by the caller if the execution succeeded.
+pub trait Host {
+    /// Value stored under `key` in the contract's storage, 0 if unset
+    fn storage(&self, key: u64) -> u64;
+    
+    /// Contract's balance before the call
+    fn balance(&self) -> u64;
+    
+    /// Height of the block the call runs in
+    fn block_height(&self) -> u64;
+}
+
+/// Parameters of one contract call
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Context {
+    /// Amount sent with the call
+    pub value: u64,
+    /// Call arguments
+    pub input: Vec<u64>,
+    /// Most gas the call may use
+    pub gas_limit: u64,
+}
+
+/// Result of running a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Gas consumed; on errors other than [`VmError::Reverted`] this is the whole limit
+    pub gas_used: u64,
+    /// Returned words, or why execution failed
+    pub result: Result<Vec<u64>, VmError>,
+    /// Storage writes to apply, empty unless execution succeeded
+    pub writes: BTreeMap<u64, u64>,
+}
+
+impl Outcome {
+    /// Check if execution succeeded
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Run `code` to completion
+///
+/// Execution is fully deterministic: it depends only on the code, the context and
+/// the host's answers, and every step is paid for with gas.
+pub fn execute(code: &[u8], context: &Context, host: &dyn Host) -> Outcome {
+    let mut machine = Machine {
+        code,
+        context,
+        host,
+        pc: 0,
+        stack: Vec::new(),
+        gas_left: context.gas_limit,
+        writes: BTreeMap::new(),
+    };
+    match machine.run() {
+        Ok(output) => Outcome {
+            gas_used: context.gas_limit - machine.gas_left,
+            result: Ok(output),
+            writes: machine.writes,
+        },
+        Err(VmError::Reverted) => Outcome {
+            gas_used: context.gas_limit - machine.gas_left,
+            result: Err(VmError::Reverted),
+            writes: BTreeMap::new(),
+        },
+        Err(error) => Outcome {
+            gas_used: context.gas_limit,
+            result: Err(error),
+            writes: BTreeMap::new(),
+        },
+    }
+}
+
+/// Check that `code` is small enough and made of whole, known instructions
+pub fn validate_code(code: &[u8]) -> Result<(), VmError> {
+    if code.len() > MAX_CODE_SIZE {
+        return Err(VmError::CodeTooLarge(code.len()));
+    }
+    jump_destinations(code).map(|_| ())
+}
+
+/// Positions of the `JumpDest` instructions, skipping over immediates
+fn jump_destinations(code: &[u8]) -> Result<Vec<bool>, VmError> {
+    let mut destinations = vec![false; code.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = Opcode::from_byte(code[pc]).ok_or(VmError::InvalidOpcode(code[pc]))?;
+        if opcode == Opcode::JumpDest {
+            destinations[pc] = true;
+        }
+        let next = pc + 1 + opcode.immediate_size();
+        if next > code.len() {
+            return Err(VmError::TruncatedImmediate(pc));
+        }
+        pc = next;
+    }
+    Ok(destinations)
+}
+
+struct Machine<'a> {
+    code: &'a [u8],
+    context: &'a Context,
+    host: &'a dyn Host,
+    pc: usize,
+    stack: Vec<u64>,
+    gas_left: u64,
+    writes: BTreeMap<u64, u64>,
+}
+
+impl<'a> Machine<'a> {
+    fn run(&mut self) -> Result<Vec<u64>, VmError> {
+        if self.code.len() > MAX_CODE_SIZE {
+            return Err(VmError::CodeTooLarge(self.code.len()));
+        }
+        let destinations = jump_destinations(self.code)?;
+        
+        while self.pc < self.code.len() {
+            let opcode = Opcode::from_byte(self.code[self.pc])
+                .ok_or(VmError::InvalidOpcode(self.code[self.pc]))?;
+            self.charge(opcode.gas_cost())?;
+            let immediate = self.immediate(opcode)?;
+            self.pc += 1 + opcode.immediate_size();
+            
+            match opcode {
+                Opcode::Stop => return Ok(Vec::new()),
+                Opcode::Push => {
+                    let word = u64::from_be_bytes(immediate.try_into().expect("8-byte immediate"));
+                    self.push(word)?;
+                }
+                Opcode::Pop => {
+                    self.pop()?;
+                }
+                Opcode::Dup => {
+                    let word = self.peek(0)?;
+                    self.push(word)?;
+                }
+                Opcode::Swap => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(b)?;
+                    self.push(a)?;
+                }
+                Opcode::Pick => {
+                    let word = self.peek(immediate[0] as usize)?;
+                    self.push(word)?;
+                }
+                Opcode::Add => self.binary(|a, b| Ok(a.wrapping_add(b)))?,
+                Opcode::Sub => self.binary(|a, b| Ok(a.wrapping_sub(b)))?,
+                Opcode::Mul => self.binary(|a, b| Ok(a.wrapping_mul(b)))?,
+                Opcode::Div => self.binary(|a, b| a.checked_div(b).ok_or(VmError::DivisionByZero))?,
+                Opcode::Mod => self.binary(|a, b| a.checked_rem(b).ok_or(VmError::DivisionByZero))?,
+                Opcode::Eq => self.binary(|a, b| Ok((a == b) as u64))?,
+                Opcode::Lt => self.binary(|a, b| Ok((a < b) as u64))?,
+                Opcode::Gt => self.binary(|a, b| Ok((a > b) as u64))?,
+                Opcode::IsZero => {
+                    let a = self.pop()?;
+                    self.push((a == 0) as u64)?;
+                }
+                Opcode::And => self.binary(|a, b| Ok(a & b))?,
+                Opcode::Or => self.binary(|a, b| Ok(a | b))?,
+                Opcode::Xor => self.binary(|a, b| Ok(a ^ b))?,
+                Opcode::Not => {
+                    let a = self.pop()?;
+                    self.push(!a)?;
+                }
+                Opcode::Jump => {
+                    let destination = self.pop()?;
+                    self.jump(destination, &destinations)?;
+                }
+                Opcode::JumpI => {
+                    let destination = self.pop()?;
+                    let condition = self.pop()?;
+                    if condition != 0 {
+                        self.jump(destination, &destinations)?;
+                    }
+                }
+                Opcode::JumpDest => {}
+                Opcode::SLoad => {
+                    let key = self.pop()?;
+                    let value = match self.writes.get(&key) {
+                        Some(value) => *value,
+                        None => self.host.storage(key),
+                    };
+                    self.push(value)?;
+                }
+                Opcode::SStore => {
+                    let key = self.pop()?;
+                    let value = self.pop()?;
+                    self.writes.insert(key, value);
+                }
+                Opcode::CallValue => self.push(self.context.value)?,
+                Opcode::Input => {
+                    let index = self.pop()?;
+                    let word = usize::try_from(index).ok()
+                        .and_then(|index| self.context.input.get(index))
+                        .copied()
+                        .unwrap_or(0);
+                    self.push(word)?;
+                }
+                Opcode::InputSize => self.push(self.context.input.len() as u64)?,
+                Opcode::Height => self.push(self.host.block_height())?,
+                Opcode::Balance => self.push(self.host.balance())?,
+                Opcode::Gas => self.push(self.gas_left)?,
+                Opcode::Return => {
+                    let count = self.pop()?;
+                    if count > self.stack.len() as u64 {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    let output = self.stack.split_off(self.stack.len() - count as usize);
+                    return Ok(output);
+                }
+                Opcode::Revert => return Err(VmError::Reverted),
+            }
+        }
+        
+        // Running off the end of the code stops like `Stop`
+        Ok(Vec::new())
+    }
+    
+    fn charge(&mut self, gas: u64) -> Result<(), VmError> {
+        self.gas_left = self.gas_left.checked_sub(gas).ok_or(VmError::OutOfGas)?;
+        Ok(())
+    }
+    
+    fn immediate(&self, opcode: Opcode) -> Result<&'a [u8], VmError> {
+        let start = self.pc + 1;
+        self.code.get(start..start + opcode.immediate_size())
+            .ok_or(VmError::TruncatedImmediate(self.pc))
+    }
+    
+    fn push(&mut self, word: u64) -> Result<(), VmError> {
+        if self.stack.len() >= MAX_STACK_DEPTH {
+            return Err(VmError::StackOverflow);
+        }
+        self.stack.push(word);
+        Ok(())
+    }
+    
+    fn pop(&mut self) -> Result<u64, VmError> {
+        self.stack.pop().ok_or(VmError::StackUnderflow)
+    }
+    
+    fn peek(&self, depth: usize) -> Result<u64, VmError> {
+        self.stack.len().checked_sub(depth + 1)
+            .map(|index| self.stack[index])
+            .ok_or(VmError::StackUnderflow)
+    }
+    
+    fn binary(&mut self, op: impl FnOnce(u64, u64) -> Result<u64, VmError>) -> Result<(), VmError> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.push(op(a, b)?)
+    }
+    
+    fn jump(&mut self, destination: u64, destinations: &[bool]) -> Result<(), VmError> {
+        match usize::try_from(destination).ok().filter(|pc| destinations.get(*pc) == Some(&true)) {
+            Some(pc) => {
+                self.pc = pc;
+                Ok(())
+            }
+            None => Err(VmError::InvalidJump(destination)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Program;
+    
+    #[derive(Default)]
+    struct TestHost {
+        storage: BTreeMap<u64, u64>,
+    }
+    
+    impl Host for TestHost {
+        fn storage(&self, key: u64) -> u64 {
+            self.storage.get(&key).copied().unwrap_or(0)
+        }
+        
+        fn balance(&self) -> u64 {
+            500
+        }
+        
+        fn block_height(&self) -> u64 {
+            9
+        }
+    }
+    
+    fn run(code: &[u8], input: Vec<u64>, gas_limit: u64) -> Outcome {
+        let context = Context { value: 7, input, gas_limit };
+        execute(code, &context, &TestHost::default())
+    }
+    
+    #[test]
+    fn test_arithmetic_and_return() {
+        let code = Program::new()
+            .push(10).push(3).op(Opcode::Sub)
+            .push(4).op(Opcode::Mul)
+            .op(Opcode::CallValue).op(Opcode::Height)
+            .push(3).op(Opcode::Return)
+            .build();
+        let outcome = run(&code, Vec::new(), 1_000);
+        assert_eq!(outcome.result, Ok(vec![28, 7, 9]));
+        assert!(outcome.gas_used > 0);
+        
+        // Wrapping, not trapping
+        let code = Program::new().push(0).push(1).op(Opcode::Sub).push(1).op(Opcode::Return).build();
+        assert_eq!(run(&code, Vec::new(), 1_000).result, Ok(vec![u64::MAX]));
+        
+        let code = Program::new().push(1).push(0).op(Opcode::Div).build();
+        let outcome = run(&code, Vec::new(), 1_000);
+        assert_eq!(outcome.result, Err(VmError::DivisionByZero));
+        assert_eq!(outcome.gas_used, 1_000);
+    }
+    
+    #[test]
+    fn test_loop_runs_out_of_gas() {
+        // Sum the inputs: counter at depth 1, total on top
+        let mut program = Program::new().push(0).push(0);
+        let head = program.label();
+        let done = 60;
+        program = program
+            .op(Opcode::JumpDest)
+            .op(Opcode::Pick).byte(1).op(Opcode::InputSize).op(Opcode::Lt).op(Opcode::IsZero)
+            .push(done).op(Opcode::JumpI)
+            .op(Opcode::Pick).byte(1).op(Opcode::Input).op(Opcode::Add)
+            .op(Opcode::Swap).push(1).op(Opcode::Add).op(Opcode::Swap)
+            .push(head).op(Opcode::Jump);
+        assert_eq!(program.label(), done);
+        let code = program.op(Opcode::JumpDest).push(1).op(Opcode::Return).build();
+        
+        let outcome = run(&code, vec![1, 2, 3, 4], 10_000);
+        assert_eq!(outcome.result, Ok(vec![10]));
+        
+        let outcome = run(&code, (0..1_000).collect(), 10_000);
+        assert_eq!(outcome.result, Err(VmError::OutOfGas));
+        assert_eq!(outcome.gas_used, 10_000);
+    }
+    
+    #[test]
+    fn test_storage_writes_are_buffered() {
+        // storage[1] += input[0]
+        let code = Program::new()
+            .push(0).op(Opcode::Input).push(1).op(Opcode::SLoad).op(Opcode::Add)
+            .push(1).op(Opcode::SStore)
+            .push(1).op(Opcode::SLoad).push(1).op(Opcode::Return)
+            .build();
+        let outcome = run(&code, vec![5], 1_000);
+        assert_eq!(outcome.result, Ok(vec![5]));
+        assert_eq!(outcome.writes, BTreeMap::from([(1, 5)]));
+        
+        // Reverting discards the writes but only charges the gas used
+        let code = Program::new().push(5).push(1).op(Opcode::SStore).op(Opcode::Revert).build();
+        let outcome = run(&code, Vec::new(), 1_000);
+        assert_eq!(outcome.result, Err(VmError::Reverted));
+        assert!(outcome.writes.is_empty());
+        assert!(outcome.gas_used < 1_000);
+    }
+    
+    #[test]
+    fn test_invalid_code_is_rejected() {
+        assert_eq!(run(&[0xee], Vec::new(), 100).result, Err(VmError::InvalidOpcode(0xee)));
+        assert_eq!(run(&[0x01, 0x00], Vec::new(), 100).result, Err(VmError::TruncatedImmediate(0)));
+        assert_eq!(run(&[0x02], Vec::new(), 100).result, Err(VmError::StackUnderflow));
+        assert_eq!(validate_code(&[0x02, 0x05]), Err(VmError::TruncatedImmediate(1)));
+        assert_eq!(validate_code(&vec![0; MAX_CODE_SIZE + 1]), Err(VmError::CodeTooLarge(MAX_CODE_SIZE + 1)));
+        
+        // Jumps must land on a `JumpDest`, and not inside a push
+        let code = Program::new().push(0x32).push(1).op(Opcode::Jump).build();
+        assert_eq!(run(&code, Vec::new(), 100).result, Err(VmError::InvalidJump(1)));
+    }
+}
@@ -0,0 +1,109 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use anyhow::{bail, Context};
+use elysium_core::{account::KeyPair, Address, Keystore, DEFAULT_SCRYPT_LOG_N};
+
+/// Directory of keystore files, one per account, named after the account's address
+pub struct KeystoreDir {
+    path: PathBuf,
+    /// scrypt cost for newly saved accounts
+    log_n: u8,
+}
+
+impl KeystoreDir {
+    /// Open a keystore directory, creating it if needed
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Cannot create keystore directory {}", path.display()))?;
+        Ok(Self { path, log_n: DEFAULT_SCRYPT_LOG_N })
+    }
+    
+    /// `~/.elysium/keystore`, or `.elysium/keystore` if there is no home directory
+    pub fn default_path() -> PathBuf {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        home.join(".elysium").join("keystore")
+    }
+    
+    /// Use scrypt cost `log_n` for accounts saved from now on
+    pub fn with_cost(mut self, log_n: u8) -> Self {
+        self.log_n = log_n;
+        self
+    }
+    
+    /// Addresses of the stored accounts, sorted
+    pub fn accounts(&self) -> anyhow::Result<Vec<Address>> {
+        let mut accounts = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let keystore = Keystore::load(&path)
+                .with_context(|| format!("Cannot read keystore {}", path.display()))?;
+            accounts.push(keystore.address);
+        }
+        accounts.sort_by(|a, b| a.as_hex().cmp(b.as_hex()));
+        Ok(accounts)
+    }
+    
+    /// Encrypt and store a key pair, refusing to overwrite an existing account
+    pub fn save(&self, keypair: &KeyPair, password: &str) -> anyhow::Result<PathBuf> {
+        let path = self.file(&keypair.address());
+        let json = Keystore::encrypt_with_cost(keypair, password, self.log_n)?.to_json()?;
+        
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        // Only the owner may read the encrypted key
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                anyhow::anyhow!("Account {} is already in the keystore", keypair.address().as_hex())
+            }
+            _ => anyhow::anyhow!("Cannot write {}: {}", path.display(), e),
+        })?;
+        file.write_all(json.as_bytes())?;
+        Ok(path)
+    }
+    
+    /// Decrypt a stored account
+    pub fn unlock(&self, address: &Address, password: &str) -> anyhow::Result<KeyPair> {
+        let path = self.file(address);
+        if !path.exists() {
+            bail!("Account {} is not in the keystore {}", address.as_hex(), self.path.display());
+        }
+        Ok(Keystore::load(&path)?.decrypt(password)?)
+    }
+    
+    fn file(&self, address: &Address) -> PathBuf {
+        self.path.join(format!("{}.json", address.as_hex()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_save_list_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = KeystoreDir::open(dir.path().join("keys")).unwrap().with_cost(4);
+        assert!(keystore.accounts().unwrap().is_empty());
+        
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        keystore.save(&alice, "a").unwrap();
+        keystore.save(&bob, "b").unwrap();
+        assert!(keystore.save(&alice, "other").is_err());
+        
+        let mut expected = vec![alice.address(), bob.address()];
+        expected.sort_by(|a, b| a.as_hex().cmp(b.as_hex()));
+        assert_eq!(keystore.accounts().unwrap(), expected);
+        
+        assert_eq!(keystore.unlock(&alice.address(), "a").unwrap().address(), alice.address());
+        assert!(keystore.unlock(&alice.address(), "b").is_err());
+        assert!(keystore.unlock(&KeyPair::generate().address(), "a").is_err());
+    }
+}
@@ -0,0 +1,314 @@
+mod accounts;
+
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use elysium_core::{
+    account::KeyPair, Address, Block, Mnemonic, Transaction, TransactionReceipt, DEFAULT_SCRYPT_LOG_N, MAX_SCRYPT_LOG_N,
+};
+use elysium_sdk::{ElysiumClient, TransactionManager};
+use serde_json::{json, Value};
+use accounts::KeystoreDir;
+
+#[derive(Parser)]
+#[command(name = "elysium-wallet")]
+#[command(about = "Elysium command-line wallet")]
+struct Args {
+    /// RPC server URL
+    #[arg(long, default_value = "http://127.0.0.1:8545", global = true)]
+    rpc_url: String,
+    
+    /// Keystore directory (defaults to ~/.elysium/keystore)
+    #[arg(long, global = true)]
+    keystore: Option<PathBuf>,
+    
+    /// Read the keystore password from this file instead of prompting
+    #[arg(long, global = true)]
+    password_file: Option<PathBuf>,
+    
+    /// scrypt cost (log2 of N) for newly saved keys; lower is faster but weaker
+    #[arg(long, global = true, default_value_t = DEFAULT_SCRYPT_LOG_N,
+          value_parser = clap::value_parser!(u8).range(10..=MAX_SCRYPT_LOG_N as i64))]
+    scrypt_log_n: u8,
+    
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new account in the keystore
+    Create {
+        /// Derive the account from a new recovery phrase of this many words, printed once
+        #[arg(long, value_name = "WORDS")]
+        mnemonic: Option<usize>,
+    },
+    /// Import a hex secret key, or a recovery phrase with --mnemonic, into the keystore
+    Import {
+        /// Import from a recovery phrase instead of a secret key
+        #[arg(long)]
+        mnemonic: bool,
+        
+        /// Account index to derive from the recovery phrase
+        #[arg(long, default_value_t = 0)]
+        index: u32,
+    },
+    /// List the accounts in the keystore
+    List,
+    /// Show the balance and nonce of an address
+    Balance {
+        address: String,
+    },
+    /// Send a transfer from a keystore account
+    Send {
+        #[arg(long)]
+        from: String,
+        
+        #[arg(long)]
+        to: String,
+        
+        #[arg(long)]
+        amount: u64,
+        
+        #[arg(long, default_value_t = 0)]
+        fee: u64,
+        
+        /// Wait until the transfer is included in a block
+        #[arg(long)]
+        wait: bool,
+        
+        /// Seconds to wait for inclusion
+        #[arg(long, default_value_t = 120)]
+        timeout: u64,
+    },
+    /// Show a block by number, or the latest block
+    Block {
+        number: Option<u64>,
+    },
+    /// Show a pending or confirmed transaction
+    Tx {
+        hash: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let client = ElysiumClient::new(args.rpc_url.clone());
+    
+    match &args.command {
+        Command::Create { mnemonic } => {
+            let keystore = open_keystore(&args)?;
+            let (keypair, phrase) = match mnemonic {
+                Some(words) => {
+                    let mnemonic = Mnemonic::generate(*words)?;
+                    (mnemonic.derive_keypair("", 0)?, Some(mnemonic.phrase()))
+                }
+                None => (KeyPair::generate(), None),
+            };
+            let password = new_password(&args)?;
+            let path = keystore.save(&keypair, &password)?;
+            
+            if args.json {
+                print_json(&json!({
+                    "address": keypair.address(),
+                    "keystore": path,
+                    "mnemonic": phrase,
+                }));
+            } else {
+                println!("Address:  {}", keypair.address().as_hex());
+                println!("Keystore: {}", path.display());
+                if let Some(phrase) = phrase {
+                    println!();
+                    println!("Recovery phrase (write it down, it will not be shown again):");
+                    println!("{}", phrase);
+                }
+            }
+        }
+        Command::Import { mnemonic, index } => {
+            let keystore = open_keystore(&args)?;
+            let keypair = if *mnemonic {
+                let phrase = read_secret("Recovery phrase: ")?;
+                Mnemonic::from_phrase(phrase.trim())?.derive_keypair("", *index)?
+            } else {
+                KeyPair::from_secret_hex(read_secret("Secret key: ")?.trim())?
+            };
+            let password = new_password(&args)?;
+            let path = keystore.save(&keypair, &password)?;
+            
+            if args.json {
+                print_json(&json!({ "address": keypair.address(), "keystore": path }));
+            } else {
+                println!("Imported {} into {}", keypair.address().as_hex(), path.display());
+            }
+        }
+        Command::List => {
+            let accounts = open_keystore(&args)?.accounts()?;
+            if args.json {
+                print_json(&json!(accounts));
+            } else if accounts.is_empty() {
+                println!("No accounts");
+            } else {
+                for address in accounts {
+                    println!("{}", address.as_hex());
+                }
+            }
+        }
+        Command::Balance { address } => {
+            let address = parse_address(address)?;
+            let balance = client.get_balance(&address).await?;
+            let nonce = client.get_nonce(&address).await?;
+            if args.json {
+                print_json(&json!({ "address": address, "balance": balance, "nonce": nonce }));
+            } else {
+                println!("Balance: {}", balance);
+                println!("Nonce:   {}", nonce);
+            }
+        }
+        Command::Send { from, to, amount, fee, wait, timeout } => {
+            let from = parse_address(from)?;
+            let to = parse_address(to)?;
+            let password = password(&args, "Password: ")?;
+            let keypair = open_keystore(&args)?.unlock(&from, &password)?;
+            let manager = TransactionManager::new(client.clone());
+            let hash = manager.send(&keypair, Transaction::new(from, to, *amount, 0).with_fee(*fee)).await?;
+            
+            let block = match wait {
+                true => {
+                    let receipt = manager.wait_for_confirmation(&hash, 1, Duration::from_secs(*timeout)).await?;
+                    Some(receipt.block_number)
+                }
+                false => None,
+            };
+            if args.json {
+                print_json(&json!({ "hash": hash, "block": block }));
+            } else {
+                println!("Sent {}", hash);
+                if let Some(number) = block {
+                    println!("Included in block {}", number);
+                }
+            }
+        }
+        Command::Block { number } => {
+            let block = match number {
+                Some(number) => client.get_block_by_number(*number).await?
+                    .with_context(|| format!("Block {} not found", number))?,
+                None => client.get_latest_block().await?,
+            };
+            if args.json {
+                print_json(&json!(block));
+            } else {
+                print_block(&block);
+            }
+        }
+        Command::Tx { hash } => {
+            let tx = client.get_transaction_by_hash(hash).await?
+                .with_context(|| format!("Transaction {} not found", hash))?
+                .transaction;
+            let receipt = client.get_transaction_receipt(hash).await?;
+            if args.json {
+                print_json(&json!({ "transaction": tx, "receipt": receipt }));
+            } else {
+                match &receipt {
+                    Some(receipt) => print_receipt(receipt),
+                    None => println!("Status:        pending"),
+                }
+                print_transaction(&tx);
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+fn open_keystore(args: &Args) -> anyhow::Result<KeystoreDir> {
+    Ok(KeystoreDir::open(args.keystore.clone().unwrap_or_else(KeystoreDir::default_path))?
+        .with_cost(args.scrypt_log_n))
+}
+
+fn parse_address(address: &str) -> anyhow::Result<Address> {
+    Address::from_hex(address.strip_prefix("0x").unwrap_or(address))
+        .map_err(|e| anyhow::anyhow!("Invalid address {}: {}", address, e))
+}
+
+/// Read a secret without echoing it, or a line of standard input when it is not a terminal
+fn read_secret(prompt: &str) -> anyhow::Result<String> {
+    if io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password(prompt)?);
+    }
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Keystore password from `--password-file` or the user
+fn password(args: &Args, prompt: &str) -> anyhow::Result<String> {
+    match &args.password_file {
+        Some(path) => {
+            let password = fs::read_to_string(path)
+                .with_context(|| format!("Cannot read password file {}", path.display()))?;
+            Ok(password.trim_end_matches(['\r', '\n']).to_string())
+        }
+        None => read_secret(prompt),
+    }
+}
+
+/// Password for a new keystore file, asked twice when typed
+fn new_password(args: &Args) -> anyhow::Result<String> {
+    let password = password(args, "New password: ")?;
+    if args.password_file.is_none() && io::stdin().is_terminal()
+        && read_secret("Repeat password: ")? != password
+    {
+        bail!("Passwords do not match");
+    }
+    if password.is_empty() {
+        bail!("The password cannot be empty");
+    }
+    Ok(password)
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).expect("JSON values serialize"));
+}
+
+fn print_block(block: &Block) {
+    println!("Number:        {}", block.header.number);
+    println!("Hash:          {}", block.hash());
+    println!("Parent:        {}", block.header.parent_hash);
+    println!("Timestamp:     {}", block.header.timestamp);
+    println!("Difficulty:    {}", block.header.difficulty());
+    println!("Transactions:  {}", block.transactions.len());
+    for tx in &block.transactions {
+        println!("  {}", tx.hash());
+    }
+}
+
+fn print_receipt(receipt: &TransactionReceipt) {
+    println!(
+        "Status:        confirmed in block {} ({} confirmations)",
+        receipt.block_number, receipt.confirmations
+    );
+    if !receipt.success {
+        println!("Result:        contract execution failed");
+    }
+    if receipt.gas_used > 0 {
+        println!("Gas used:      {}", receipt.gas_used);
+    }
+    println!("Cost:          {}", receipt.cost);
+}
+
+fn print_transaction(tx: &Transaction) {
+    println!("Hash:          {}", tx.hash());
+    println!("From:          {}", tx.from.as_hex());
+    println!("To:            {}", tx.to.as_hex());
+    println!("Amount:        {}", tx.amount);
+    println!("Fee:           {}", tx.fee);
+    println!("Nonce:         {}", tx.nonce);
+}